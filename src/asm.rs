@@ -0,0 +1,772 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::chip8::PROGRAM_START;
+use crate::error::AsmError;
+use crate::instruction::Instruction;
+
+// =================================
+// Two-pass assembler
+// =================================
+
+// Sources use the mnemonics printed by the disassembler, one statement per line:
+//
+//     SPEED equ 4            ; constant
+//     start:                 ; label, may also precede a statement on the same line
+//         LD VA, SPEED + 1
+//         JP start
+//     org 0x300              ; continue at 0x300, the gap is filled with zeros
+//     sprite: db 0xF0, 0x90
+//     table:  dw sprite, 0x1234
+//
+// Mnemonics, registers and directives are case-insensitive, symbols are not. Numbers are
+// decimal, 0x hexadecimal or 0b binary, and operands may add and subtract them and symbols.
+// The first pass lays out the statements and collects the labels, the second one encodes them,
+// so labels can be used before they are defined.
+
+// End of the largest address space, XO-CHIP's 64 KiB
+const MEMORY_END: i64 = 0x10000;
+
+// Names that can't be used as symbols, besides V0-VF
+const RESERVED: [&str; 9] = ["I", "DT", "ST", "K", "F", "HF", "B", "R", "LONG"];
+
+const MNEMONICS: [&str; 35] = [
+    "SYS", "CLS", "RET", "SCD", "SCU", "SCR", "SCL", "EXIT", "LOW", "HIGH", "JP", "CALL", "SE",
+    "SNE", "SAVE", "LOAD", "LD", "ADD", "OR", "AND", "XOR", "SUB", "SHR", "SUBN", "SHL", "RND",
+    "DRW", "SKP", "SKNP", "PLANE", "AUDIO", "PITCH", "DB", "DW", "ORG",
+];
+
+/// Assemble a source file into a ROM loaded at 0x200
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut symbols = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = PROGRAM_START as i64;
+
+    // First pass: lay out statements and define symbols
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let tokens = tokenize(line, text)?;
+        let mut rest = tokens.as_slice();
+
+        if let [
+            Token {
+                kind: TokenKind::Ident(name),
+                column,
+            },
+            Token {
+                kind: TokenKind::Colon,
+                ..
+            },
+            tail @ ..,
+        ] = rest
+        {
+            define(&mut symbols, line, *column, name, address)?;
+            rest = tail;
+        }
+
+        let [first, operands @ ..] = rest else {
+            continue;
+        };
+        let TokenKind::Ident(word) = &first.kind else {
+            return Err(error(
+                line,
+                first.column,
+                format!("unexpected {}", first.kind),
+            ));
+        };
+
+        // Constant definition
+        if let [equ, value @ ..] = operands
+            && matches!(&equ.kind, TokenKind::Ident(name) if name.eq_ignore_ascii_case("equ"))
+        {
+            let value = parse_expr(line, equ.column, value)?.eval(line, &symbols)?;
+            define(&mut symbols, line, first.column, word, value)?;
+            continue;
+        }
+
+        let mnemonic = word.trim_start_matches('.').to_ascii_uppercase();
+        let operands = split_operands(line, first.column, operands)?;
+
+        let (item, size) = match mnemonic.as_str() {
+            "ORG" => {
+                let [(column, Operand::Value(expr))] = operands.as_slice() else {
+                    return Err(error(
+                        line,
+                        first.column,
+                        "org takes an address".to_string(),
+                    ));
+                };
+                let origin = expr.eval(line, &symbols)?;
+                if origin < address || origin > MEMORY_END {
+                    return Err(error(
+                        line,
+                        *column,
+                        format!(
+                            "org {:#X} is before {:#X} or beyond memory",
+                            origin, address
+                        ),
+                    ));
+                }
+                address = origin;
+                continue;
+            }
+            "DB" | "DW" => {
+                let values = operands
+                    .into_iter()
+                    .map(|(column, operand)| match operand {
+                        Operand::Value(expr) => Ok(expr),
+                        _ => Err(error(line, column, "expected a value".to_string())),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let size = if mnemonic == "DB" { 1 } else { 2 } * values.len() as i64;
+                let item = if mnemonic == "DB" {
+                    Item::Bytes(values)
+                } else {
+                    Item::Words(values)
+                };
+                (item, size)
+            }
+            _ => {
+                let long = mnemonic == "LD"
+                    && matches!(
+                        operands.as_slice(),
+                        [(_, Operand::I), (_, Operand::Long(_))]
+                    );
+                let item = Item::Instruction {
+                    mnemonic,
+                    column: first.column,
+                    operands,
+                };
+                (item, if long { 4 } else { 2 })
+            }
+        };
+
+        if address + size > MEMORY_END {
+            return Err(error(
+                line,
+                first.column,
+                "program exceeds memory".to_string(),
+            ));
+        }
+
+        statements.push(Statement {
+            line,
+            address,
+            item,
+        });
+        address += size;
+    }
+
+    // Second pass: encode statements with all symbols known
+    let mut rom = Vec::new();
+
+    for statement in statements {
+        let line = statement.line;
+        rom.resize((statement.address - PROGRAM_START as i64) as usize, 0);
+
+        match statement.item {
+            Item::Bytes(values) => {
+                for expr in values {
+                    rom.push(expr.value(line, &symbols, 0xFF)? as u8);
+                }
+            }
+            Item::Words(values) => {
+                for expr in values {
+                    rom.extend((expr.value(line, &symbols, 0xFFFF)? as u16).to_be_bytes());
+                }
+            }
+            Item::Instruction {
+                mnemonic,
+                column,
+                operands,
+            } => {
+                let instruction = encode(line, column, &mnemonic, &operands, &symbols)?;
+                rom.extend(instruction.opcode().to_be_bytes());
+                if let Instruction::LdILong(address) = instruction {
+                    rom.extend(address.to_be_bytes());
+                }
+            }
+        }
+    }
+
+    Ok(rom)
+}
+
+// Helper function to create an error
+fn error(line: usize, column: usize, message: String) -> AsmError {
+    AsmError {
+        line,
+        column,
+        message,
+    }
+}
+
+// Helper function to add a label or constant to the symbol table
+fn define(
+    symbols: &mut HashMap<String, i64>,
+    line: usize,
+    column: usize,
+    name: &str,
+    value: i64,
+) -> Result<(), AsmError> {
+    let upper = name.to_ascii_uppercase();
+    if RESERVED.contains(&upper.as_str()) || MNEMONICS.contains(&upper.as_str()) {
+        return Err(error(
+            line,
+            column,
+            format!("'{}' is a reserved name", name),
+        ));
+    }
+    if register(name).is_some() {
+        return Err(error(line, column, format!("'{}' is a register", name)));
+    }
+    if symbols.insert(name.to_string(), value).is_some() {
+        return Err(error(
+            line,
+            column,
+            format!("'{}' is already defined", name),
+        ));
+    }
+
+    Ok(())
+}
+
+// Helper function to parse a register name V0-VF
+fn register(name: &str) -> Option<u8> {
+    let digit = name.strip_prefix(['V', 'v'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+
+    u8::from_str_radix(digit, 16).ok()
+}
+
+// A statement laid out by the first pass
+struct Statement {
+    line: usize,
+    address: i64,
+    item: Item,
+}
+
+enum Item {
+    Instruction {
+        mnemonic: String,
+        column: usize,
+        operands: Vec<(usize, Operand)>,
+    },
+    Bytes(Vec<Expr>),
+    Words(Vec<Expr>),
+}
+
+// =================================
+// Tokens and expressions
+// =================================
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Number(i64),
+    Comma,
+    Colon,
+    Plus,
+    Minus,
+    LBracket,
+    RBracket,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Ident(name) => write!(f, "'{}'", name),
+            TokenKind::Number(value) => write!(f, "'{}'", value),
+            TokenKind::Comma => write!(f, "','"),
+            TokenKind::Colon => write!(f, "':'"),
+            TokenKind::Plus => write!(f, "'+'"),
+            TokenKind::Minus => write!(f, "'-'"),
+            TokenKind::LBracket => write!(f, "'['"),
+            TokenKind::RBracket => write!(f, "']'"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+// Split a line into tokens, stopping at a ; comment
+fn tokenize(line: usize, text: &str) -> Result<Vec<Token>, AsmError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let column = i + 1;
+        let c = chars[i];
+
+        let kind = match c {
+            ';' => break,
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ',' => TokenKind::Comma,
+            ':' => TokenKind::Colon,
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '[' => TokenKind::LBracket,
+            ']' => TokenKind::RBracket,
+            _ if c.is_ascii_alphanumeric() || c == '_' || c == '.' => {
+                let end = (i..chars.len())
+                    .find(|&j| {
+                        !(chars[j].is_ascii_alphanumeric() || chars[j] == '_' || chars[j] == '.')
+                    })
+                    .unwrap_or(chars.len());
+                let word: String = chars[i..end].iter().collect();
+                i = end;
+
+                tokens.push(Token {
+                    kind: if c.is_ascii_digit() {
+                        TokenKind::Number(parse_number(&word).ok_or_else(|| {
+                            error(line, column, format!("invalid number '{}'", word))
+                        })?)
+                    } else {
+                        TokenKind::Ident(word)
+                    },
+                    column,
+                });
+                continue;
+            }
+            _ => return Err(error(line, column, format!("unexpected character '{}'", c))),
+        };
+
+        tokens.push(Token { kind, column });
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+// Helper function to parse a decimal, 0x hexadecimal or 0b binary number
+fn parse_number(word: &str) -> Option<i64> {
+    let lower = word.to_ascii_lowercase();
+
+    if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+// Sum of numbers and symbols
+#[derive(Clone, Debug, PartialEq)]
+struct Expr {
+    column: usize,
+    terms: Vec<(bool, Term)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Term {
+    Number { value: i64, column: usize },
+    Symbol { name: String, column: usize },
+}
+
+// Parse an expression of + and - separated terms, column is used if there are no tokens
+fn parse_expr(line: usize, column: usize, tokens: &[Token]) -> Result<Expr, AsmError> {
+    let Some(first) = tokens.first() else {
+        return Err(error(line, column, "missing value".to_string()));
+    };
+
+    let mut expr = Expr {
+        column: first.column,
+        terms: Vec::new(),
+    };
+    let mut negative = false;
+    let mut expect_term = true;
+
+    for token in tokens {
+        match (&token.kind, expect_term) {
+            (TokenKind::Number(value), true) => {
+                expr.terms.push((
+                    negative,
+                    Term::Number {
+                        value: *value,
+                        column: token.column,
+                    },
+                ));
+            }
+            (TokenKind::Ident(name), true) => {
+                expr.terms.push((
+                    negative,
+                    Term::Symbol {
+                        name: name.clone(),
+                        column: token.column,
+                    },
+                ));
+            }
+            (TokenKind::Minus, true) if expr.terms.is_empty() && !negative => {
+                negative = true;
+                continue;
+            }
+            (TokenKind::Plus | TokenKind::Minus, false) => {
+                negative = token.kind == TokenKind::Minus;
+                expect_term = true;
+                continue;
+            }
+            _ => {
+                return Err(error(
+                    line,
+                    token.column,
+                    format!("unexpected {}", token.kind),
+                ));
+            }
+        }
+        expect_term = false;
+    }
+
+    if expect_term {
+        let last = tokens.last().map_or(column, |token| token.column);
+        return Err(error(line, last, "missing value".to_string()));
+    }
+
+    Ok(expr)
+}
+
+impl Expr {
+    fn eval(&self, line: usize, symbols: &HashMap<String, i64>) -> Result<i64, AsmError> {
+        self.terms.iter().try_fold(0i64, |sum, (negative, term)| {
+            let (value, column) = match term {
+                Term::Number { value, column } => (*value, *column),
+                Term::Symbol { name, column } => (
+                    *symbols.get(name).ok_or_else(|| {
+                        error(line, *column, format!("undefined symbol '{}'", name))
+                    })?,
+                    *column,
+                ),
+            };
+
+            let result = if *negative {
+                sum.checked_sub(value)
+            } else {
+                sum.checked_add(value)
+            };
+            result.ok_or_else(|| error(line, column, "value out of range".to_string()))
+        })
+    }
+
+    // Evaluate and check the result is between 0 and max
+    fn value(
+        &self,
+        line: usize,
+        symbols: &HashMap<String, i64>,
+        max: i64,
+    ) -> Result<i64, AsmError> {
+        let value = self.eval(line, symbols)?;
+        if !(0..=max).contains(&value) {
+            return Err(error(
+                line,
+                self.column,
+                format!("value {:#X} is out of range 0x0-{:#X}", value, max),
+            ));
+        }
+
+        Ok(value)
+    }
+}
+
+// =================================
+// Operands and encoding
+// =================================
+
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+    Register(u8),
+    Range(u8, u8),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    Hf,
+    B,
+    R,
+    Long(Expr),
+    Value(Expr),
+}
+
+// Split the operands of a statement at commas, column is the one of the mnemonic
+fn split_operands(
+    line: usize,
+    column: usize,
+    tokens: &[Token],
+) -> Result<Vec<(usize, Operand)>, AsmError> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut operands = Vec::new();
+    let mut start_column = column;
+
+    for part in tokens.split(|token| token.kind == TokenKind::Comma) {
+        let column = part.first().map_or(start_column, |token| token.column);
+        operands.push((column, parse_operand(line, column, part)?));
+
+        // Missing operands after a comma are reported at the comma
+        start_column = tokens
+            .iter()
+            .find(|token| token.column > column && token.kind == TokenKind::Comma)
+            .map_or(column, |token| token.column);
+    }
+
+    Ok(operands)
+}
+
+fn parse_operand(line: usize, column: usize, tokens: &[Token]) -> Result<Operand, AsmError> {
+    let ident = |token: &Token| match &token.kind {
+        TokenKind::Ident(name) => Some(name.to_ascii_uppercase()),
+        _ => None,
+    };
+    let reg = |token: &Token| ident(token).and_then(|name| register(&name));
+    let is = |token: &Token, kind: TokenKind| token.kind == kind;
+
+    Ok(match tokens {
+        [token] => match (reg(token), ident(token).as_deref()) {
+            (Some(x), _) => Operand::Register(x),
+            (_, Some("I")) => Operand::I,
+            (_, Some("DT")) => Operand::Dt,
+            (_, Some("ST")) => Operand::St,
+            (_, Some("K")) => Operand::K,
+            (_, Some("F")) => Operand::F,
+            (_, Some("HF")) => Operand::Hf,
+            (_, Some("B")) => Operand::B,
+            (_, Some("R")) => Operand::R,
+            _ => Operand::Value(parse_expr(line, column, tokens)?),
+        },
+        [x, dash, y] if is(dash, TokenKind::Minus) && reg(x).is_some() && reg(y).is_some() => {
+            Operand::Range(reg(x).unwrap(), reg(y).unwrap())
+        }
+        [open, i, close]
+            if is(open, TokenKind::LBracket)
+                && ident(i).as_deref() == Some("I")
+                && is(close, TokenKind::RBracket) =>
+        {
+            Operand::IndirectI
+        }
+        [token, rest @ ..] if ident(token).as_deref() == Some("LONG") => {
+            Operand::Long(parse_expr(line, token.column, rest)?)
+        }
+        _ => Operand::Value(parse_expr(line, column, tokens)?),
+    })
+}
+
+// Turn a mnemonic and its operands into an instruction
+fn encode(
+    line: usize,
+    column: usize,
+    mnemonic: &str,
+    operands: &[(usize, Operand)],
+    symbols: &HashMap<String, i64>,
+) -> Result<Instruction, AsmError> {
+    use Operand::*;
+
+    let address = |expr: &Expr| Ok::<_, AsmError>(expr.value(line, symbols, 0xFFF)? as u16);
+    let long = |expr: &Expr| Ok::<_, AsmError>(expr.value(line, symbols, 0xFFFF)? as u16);
+    let byte = |expr: &Expr| Ok::<_, AsmError>(expr.value(line, symbols, 0xFF)? as u8);
+    let nibble = |expr: &Expr| Ok::<_, AsmError>(expr.value(line, symbols, 0xF)? as u8);
+
+    let operands: Vec<&Operand> = operands.iter().map(|(_, operand)| operand).collect();
+
+    Ok(match (mnemonic, operands.as_slice()) {
+        ("SYS", [Value(e)]) => Instruction::Sys(address(e)?),
+        ("CLS", []) => Instruction::Cls,
+        ("RET", []) => Instruction::Ret,
+        ("SCD", [Value(e)]) => Instruction::ScrollDown(nibble(e)?),
+        ("SCU", [Value(e)]) => Instruction::ScrollUp(nibble(e)?),
+        ("SCR", []) => Instruction::ScrollRight,
+        ("SCL", []) => Instruction::ScrollLeft,
+        ("EXIT", []) => Instruction::Exit,
+        ("LOW", []) => Instruction::Low,
+        ("HIGH", []) => Instruction::High,
+        ("JP", [Value(e)]) => Instruction::Jp(address(e)?),
+        ("JP", [Register(0), Value(e)]) => Instruction::JpV0(address(e)?),
+        ("CALL", [Value(e)]) => Instruction::Call(address(e)?),
+        ("SE", [Register(x), Value(e)]) => Instruction::SeByte(*x, byte(e)?),
+        ("SE", [Register(x), Register(y)]) => Instruction::SeReg(*x, *y),
+        ("SNE", [Register(x), Value(e)]) => Instruction::SneByte(*x, byte(e)?),
+        ("SNE", [Register(x), Register(y)]) => Instruction::SneReg(*x, *y),
+        ("SAVE", [Range(x, y)]) => Instruction::Save(*x, *y),
+        ("LOAD", [Range(x, y)]) => Instruction::Load(*x, *y),
+        ("LD", [Register(x), Value(e)]) => Instruction::Ld(*x, byte(e)?),
+        ("LD", [Register(x), Register(y)]) => Instruction::LdReg(*x, *y),
+        ("LD", [I, Value(e)]) => Instruction::LdI(address(e)?),
+        ("LD", [I, Long(e)]) => Instruction::LdILong(long(e)?),
+        ("LD", [Register(x), Dt]) => Instruction::LdVxDt(*x),
+        ("LD", [Register(x), K]) => Instruction::LdVxK(*x),
+        ("LD", [Dt, Register(x)]) => Instruction::LdDtVx(*x),
+        ("LD", [St, Register(x)]) => Instruction::LdStVx(*x),
+        ("LD", [F, Register(x)]) => Instruction::LdFVx(*x),
+        ("LD", [Hf, Register(x)]) => Instruction::LdHfVx(*x),
+        ("LD", [B, Register(x)]) => Instruction::LdBVx(*x),
+        ("LD", [IndirectI, Register(x)]) => Instruction::LdIVx(*x),
+        ("LD", [Register(x), IndirectI]) => Instruction::LdVxI(*x),
+        ("LD", [R, Register(x)]) => Instruction::LdRVx(*x),
+        ("LD", [Register(x), R]) => Instruction::LdVxR(*x),
+        ("ADD", [Register(x), Value(e)]) => Instruction::AddByte(*x, byte(e)?),
+        ("ADD", [Register(x), Register(y)]) => Instruction::AddReg(*x, *y),
+        ("ADD", [I, Register(x)]) => Instruction::AddIVx(*x),
+        ("OR", [Register(x), Register(y)]) => Instruction::Or(*x, *y),
+        ("AND", [Register(x), Register(y)]) => Instruction::And(*x, *y),
+        ("XOR", [Register(x), Register(y)]) => Instruction::Xor(*x, *y),
+        ("SUB", [Register(x), Register(y)]) => Instruction::Sub(*x, *y),
+        ("SUBN", [Register(x), Register(y)]) => Instruction::Subn(*x, *y),
+        ("SHR", [Register(x), Register(y)]) => Instruction::Shr(*x, *y),
+        ("SHR", [Register(x)]) => Instruction::Shr(*x, *x),
+        ("SHL", [Register(x), Register(y)]) => Instruction::Shl(*x, *y),
+        ("SHL", [Register(x)]) => Instruction::Shl(*x, *x),
+        ("RND", [Register(x), Value(e)]) => Instruction::Rnd(*x, byte(e)?),
+        ("DRW", [Register(x), Register(y), Value(e)]) => Instruction::Drw {
+            x: *x,
+            y: *y,
+            n: nibble(e)?,
+        },
+        ("SKP", [Register(x)]) => Instruction::Skp(*x),
+        ("SKNP", [Register(x)]) => Instruction::Sknp(*x),
+        ("PLANE", [Value(e)]) => Instruction::Plane(nibble(e)?),
+        ("AUDIO", []) => Instruction::Audio,
+        ("PITCH", [Register(x)]) => Instruction::Pitch(*x),
+        _ if MNEMONICS.contains(&mnemonic) => {
+            return Err(error(
+                line,
+                column,
+                format!("invalid operands for {}", mnemonic),
+            ));
+        }
+        _ => {
+            return Err(error(
+                line,
+                column,
+                format!("unknown mnemonic '{}'", mnemonic),
+            ));
+        }
+    })
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm;
+
+    // Macro to shadow prelude with pretty_assertions
+    macro_rules! assert_eq {
+        ($($tt:tt)*) => {
+            pretty_assertions::assert_eq!($($tt)*)
+        };
+    }
+
+    // Helper function to build the expected error
+    fn err(line: usize, column: usize, message: &str) -> Result<Vec<u8>, AsmError> {
+        Err(error(line, column, message.to_string()))
+    }
+
+    #[test]
+    fn test_assemble() {
+        let source = "\
+; Bounce a sprite
+SPEED equ 2
+
+start:  cls
+        ld va, 0
+        ld i, sprite
+loop:   drw va, vb, 2
+        add va, SPEED
+        shr v1
+        jp loop + 2 - 2
+        ld i, long table
+        save v0 - v3
+
+org 0x220
+sprite: db 0xF0, 0b10010000
+table:  dw start, 0x1234
+";
+
+        let expected = [
+            0x00, 0xE0, 0x6A, 0x00, 0xA2, 0x20, 0xDA, 0xB2, // start
+            0x7A, 0x02, 0x81, 0x16, 0x12, 0x06, 0xF0, 0x00, //
+            0x02, 0x22, 0x50, 0x32, 0x00, 0x00, 0x00, 0x00, // long load, save, org gap
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+            0xF0, 0x90, 0x02, 0x00, 0x12, 0x34, // data
+        ];
+        assert_eq!(Ok(expected.to_vec()), assemble(source));
+    }
+
+    #[test]
+    fn test_disassembly_round_trip() {
+        // Every possible word, in chunks that fit into memory
+        let words: Vec<u8> = (0..=0xFFFFu16)
+            .flat_map(|word| word.to_be_bytes())
+            .collect();
+
+        for rom in words.chunks(0x8000).chain([&[0x12, 0x00, 0xFF][..]]) {
+            let source: Vec<String> = disasm::disassemble(rom, PROGRAM_START)
+                .iter()
+                .map(|line| line.to_string())
+                .collect();
+
+            assert_eq!(Ok(rom.to_vec()), assemble(&source.join("\n")));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            ("  foo v0", err(1, 3, "unknown mnemonic 'FOO'")),
+            ("\n  ld v0, k, 2", err(2, 3, "invalid operands for LD")),
+            ("jp nowhere", err(1, 4, "undefined symbol 'nowhere'")),
+            (
+                "ld v0, 0x100",
+                err(1, 8, "value 0x100 is out of range 0x0-0xFF"),
+            ),
+            (
+                "drw v0, v1, 16",
+                err(1, 13, "value 0x10 is out of range 0x0-0xF"),
+            ),
+            (
+                "jp 0x1000",
+                err(1, 4, "value 0x1000 is out of range 0x0-0xFFF"),
+            ),
+            ("a: cls\na: cls", err(2, 1, "'a' is already defined")),
+            ("f: cls", err(1, 1, "'f' is a reserved name")),
+            ("vA equ 1", err(1, 1, "'vA' is a register")),
+            ("ld v0, 1 +", err(1, 10, "missing value")),
+            ("ld v0,", err(1, 6, "missing value")),
+            ("ld v0, 1 2", err(1, 10, "unexpected '2'")),
+            ("ld v0, @", err(1, 8, "unexpected character '@'")),
+            ("db 0x1G", err(1, 4, "invalid number '0x1G'")),
+            (
+                "db 0x7FFFFFFFFFFFFFFF + 1",
+                err(1, 25, "value out of range"),
+            ),
+            (
+                "big equ 0x7FFFFFFFFFFFFFFF\ndb 2 - big - big",
+                err(2, 14, "value out of range"),
+            ),
+            (
+                "cls\norg 0x200",
+                err(2, 5, "org 0x200 is before 0x202 or beyond memory"),
+            ),
+            ("org 0xFFFF\ncls", err(2, 1, "program exceeds memory")),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(expected, assemble(source), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,605 @@
+use std::io::{self, Write};
+
+use crate::chip8::{AUDIO_PATTERN_SIZE, Chip8, DEFAULT_PITCH, TIMER_FREQUENCY};
+
+// Bits of an XO-CHIP audio pattern
+const PATTERN_BITS: f32 = (AUDIO_PATTERN_SIZE * 8) as f32;
+// Bits per second an XO-CHIP audio pattern is played at with the default pitch
+const BASE_PLAYBACK_RATE: f32 = 4000.0;
+
+// =================================
+// Beeper audio synthesis
+// =================================
+
+/// Settings of the square-wave beeper
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BeeperConfig {
+    /// Pitch of the tone in Hz, must be below half the sample rate
+    pub frequency: f32,
+    /// Amplitude of the tone between 0.0 and 1.0
+    pub volume: f32,
+    /// Output samples per second
+    pub sample_rate: u32,
+}
+
+impl Default for BeeperConfig {
+    fn default() -> Self {
+        BeeperConfig {
+            frequency: 440.0,
+            volume: 0.25,
+            sample_rate: 44_100,
+        }
+    }
+}
+
+/// Sound generator producing one frame worth of samples at a time
+///
+/// Plays a square wave, or the audio pattern of XO-CHIP programs. Samples are generated per
+/// emulated frame rather than per wall-clock interval, so the audio stays in sync with the
+/// emulation no matter how fast it runs.
+#[derive(Clone, Debug)]
+pub struct Beeper {
+    config: BeeperConfig,
+    // Position within the current period, between 0.0 and 1.0
+    phase: f32,
+    // Position within the audio pattern in bits, between 0.0 and 128.0
+    pattern_position: f32,
+    // Fraction of a sample carried over between frames, in units of 1 / TIMER_FREQUENCY
+    sample_clock: u32,
+}
+
+impl Beeper {
+    /// Create a beeper
+    ///
+    /// # Panics
+    ///
+    /// Panics if the frequency is not between 0 Hz and half the sample rate
+    pub fn new(config: BeeperConfig) -> Beeper {
+        assert!(
+            config.frequency > 0.0 && config.frequency < config.sample_rate as f32 / 2.0,
+            "beeper frequency {} Hz is not representable at {} Hz",
+            config.frequency,
+            config.sample_rate
+        );
+
+        Beeper {
+            config,
+            phase: 0.0,
+            pattern_position: 0.0,
+            sample_clock: 0,
+        }
+    }
+
+    /// Settings of the beeper
+    pub fn config(&self) -> &BeeperConfig {
+        &self.config
+    }
+
+    /// Append the samples of one emulated frame to `out`, returns the number of samples
+    ///
+    /// `beeping` is the state of the sound timer for that frame, see [`crate::Chip8::is_beeping`].
+    /// Frames without sound produce silence, so the output keeps its timing.
+    pub fn render_frame<E: Extend<f32>>(&mut self, beeping: bool, out: &mut E) -> usize {
+        let count = self.frame_length();
+
+        if !beeping {
+            return self.render_silence(count, out);
+        }
+
+        let step = self.config.frequency / self.config.sample_rate as f32;
+        let volume = self.config.volume.clamp(0.0, 1.0);
+
+        out.extend((0..count).map(|_| {
+            let sample = square(self.phase, step);
+            self.phase = (self.phase + step).fract();
+            sample * volume
+        }));
+
+        count
+    }
+
+    /// Append one frame of an XO-CHIP audio pattern played at `rate` bits per second to `out`,
+    /// returns the number of samples
+    ///
+    /// The pattern is played as a loop of 128 one-bit samples, most significant bit first.
+    pub fn render_pattern_frame<E: Extend<f32>>(
+        &mut self,
+        beeping: bool,
+        pattern: &[u8; AUDIO_PATTERN_SIZE],
+        rate: f32,
+        out: &mut E,
+    ) -> usize {
+        let count = self.frame_length();
+
+        if !beeping {
+            return self.render_silence(count, out);
+        }
+
+        let step = rate / self.config.sample_rate as f32;
+        let volume = self.config.volume.clamp(0.0, 1.0);
+
+        out.extend((0..count).map(|_| {
+            let start = self.pattern_position;
+            self.pattern_position = (start + step) % PATTERN_BITS;
+            pattern_level(pattern, start, start + step) * volume
+        }));
+
+        count
+    }
+
+    /// Append the samples of one emulated frame of `chip` to `out`, returns the number of samples
+    ///
+    /// Plays the audio pattern at the pitch of the chip8 once an XO-CHIP program loaded one, the
+    /// square wave otherwise.
+    pub fn render_chip_frame<E: Extend<f32>>(&mut self, chip: &Chip8, out: &mut E) -> usize {
+        match chip.audio_pattern() {
+            Some(pattern) => {
+                let rate = playback_rate(chip.pitch());
+                self.render_pattern_frame(chip.is_beeping(), pattern, rate, out)
+            }
+            None => self.render_frame(chip.is_beeping(), out),
+        }
+    }
+
+    // Number of samples in the next frame
+    fn frame_length(&mut self) -> usize {
+        // Sample rates that are not a multiple of the frame rate alternate between frame lengths
+        self.sample_clock += self.config.sample_rate;
+        let count = (self.sample_clock / TIMER_FREQUENCY) as usize;
+        self.sample_clock %= TIMER_FREQUENCY;
+
+        count
+    }
+
+    fn render_silence<E: Extend<f32>>(&mut self, count: usize, out: &mut E) -> usize {
+        // Every tone starts at the same point of the wave
+        self.phase = 0.0;
+        self.pattern_position = 0.0;
+
+        out.extend(std::iter::repeat_n(0.0, count));
+        count
+    }
+}
+
+/// Bits per second an XO-CHIP audio pattern is played at for a pitch register value
+///
+/// The default pitch of 64 plays at 4000 Hz, every 48 steps double or halve the rate.
+pub fn playback_rate(pitch: u8) -> f32 {
+    BASE_PLAYBACK_RATE * 2f32.powf((pitch as f32 - DEFAULT_PITCH as f32) / 48.0)
+}
+
+// Average level of the pattern between two positions measured in bits, set bits are 1.0 and
+// cleared bits -1.0
+// Averaging over the whole sample instead of picking a single bit keeps aliasing down
+fn pattern_level(pattern: &[u8; AUDIO_PATTERN_SIZE], from: f32, to: f32) -> f32 {
+    let mut level = 0.0;
+    let mut position = from;
+
+    while position < to {
+        let bit = position.floor();
+        let end = (bit + 1.0).min(to);
+        let index = bit as usize % (AUDIO_PATTERN_SIZE * 8);
+
+        let set = extract_bit(pattern[index / 8], 7 - index % 8);
+        level += if set { 1.0 } else { -1.0 } * (end - position);
+        position = end;
+    }
+
+    level / (to - from)
+}
+
+fn extract_bit(byte: u8, bit: usize) -> bool {
+    (byte >> bit) & 1 == 1
+}
+
+// Band-limited square wave using PolyBLEP to smooth both edges of the period
+fn square(phase: f32, step: f32) -> f32 {
+    let naive = if phase < 0.5 { 1.0 } else { -1.0 };
+    naive + poly_blep(phase, step) - poly_blep((phase + 0.5).fract(), step)
+}
+
+// Polynomial correction around a discontinuity at phase 0
+fn poly_blep(phase: f32, step: f32) -> f32 {
+    if phase < step {
+        let t = phase / step;
+        2.0 * t - t * t - 1.0
+    } else if phase > 1.0 - step {
+        let t = (phase - 1.0) / step;
+        t * t + 2.0 * t + 1.0
+    } else {
+        0.0
+    }
+}
+
+// =================================
+// Sample ring buffer
+// =================================
+
+/// Fixed-size FIFO of samples between the emulator and an audio backend
+///
+/// The emulator pushes samples after every frame and the backend pulls them whenever its device
+/// needs more. When the backend falls behind the oldest samples are dropped, so the latency never
+/// grows beyond the capacity.
+#[derive(Clone, Debug)]
+pub struct SampleRing {
+    samples: Box<[f32]>,
+    // Index of the oldest sample
+    start: usize,
+    len: usize,
+}
+
+impl SampleRing {
+    /// Create an empty ring holding at most `capacity` samples
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero
+    pub fn new(capacity: usize) -> SampleRing {
+        assert!(capacity > 0, "sample ring needs a capacity of at least 1");
+
+        SampleRing {
+            samples: vec![0.0; capacity].into_boxed_slice(),
+            start: 0,
+            len: 0,
+        }
+    }
+
+    /// Maximum number of samples held
+    pub fn capacity(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Number of samples ready to be read
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether there are no samples to read
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append a sample, dropping the oldest one if the ring is full
+    pub fn push(&mut self, sample: f32) {
+        let end = (self.start + self.len) % self.capacity();
+        self.samples[end] = sample;
+
+        if self.len == self.capacity() {
+            self.start = (self.start + 1) % self.capacity();
+        } else {
+            self.len += 1;
+        }
+    }
+
+    /// Remove the oldest sample
+    pub fn pop(&mut self) -> Option<f32> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let sample = self.samples[self.start];
+        self.start = (self.start + 1) % self.capacity();
+        self.len -= 1;
+        Some(sample)
+    }
+
+    /// Move the oldest samples into `out`, returns how many were written
+    ///
+    /// Backends should fill the rest of their buffer with silence.
+    pub fn read(&mut self, out: &mut [f32]) -> usize {
+        let count = out.len().min(self.len);
+        for slot in &mut out[..count] {
+            *slot = self.samples[self.start];
+            self.start = (self.start + 1) % self.capacity();
+        }
+
+        self.len -= count;
+        count
+    }
+
+    /// Drop all samples
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+    }
+}
+
+impl Extend<f32> for SampleRing {
+    fn extend<I: IntoIterator<Item = f32>>(&mut self, samples: I) {
+        for sample in samples {
+            self.push(sample);
+        }
+    }
+}
+
+// =================================
+// WAV export
+// =================================
+
+/// Write mono samples as a 16-bit PCM WAV file
+///
+/// Samples are clamped to -1.0..=1.0 and rounded, so the output is reproducible byte for byte.
+pub fn write_wav<W: Write>(mut out: W, samples: &[f32], sample_rate: u32) -> io::Result<()> {
+    const CHANNELS: u16 = 1;
+    const BITS_PER_SAMPLE: u16 = 16;
+    const BLOCK_ALIGN: u16 = CHANNELS * BITS_PER_SAMPLE / 8;
+
+    let data_size = u32::try_from(samples.len() * BLOCK_ALIGN as usize)
+        .ok()
+        .filter(|size| *size <= u32::MAX - 36)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "audio too long for WAV"))?;
+
+    out.write_all(b"RIFF")?;
+    out.write_all(&(36 + data_size).to_le_bytes())?;
+    out.write_all(b"WAVE")?;
+
+    out.write_all(b"fmt ")?;
+    out.write_all(&16u32.to_le_bytes())?;
+    out.write_all(&1u16.to_le_bytes())?; // PCM
+    out.write_all(&CHANNELS.to_le_bytes())?;
+    out.write_all(&sample_rate.to_le_bytes())?;
+    out.write_all(&(sample_rate * BLOCK_ALIGN as u32).to_le_bytes())?;
+    out.write_all(&BLOCK_ALIGN.to_le_bytes())?;
+    out.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+    out.write_all(b"data")?;
+    out.write_all(&data_size.to_le_bytes())?;
+    for &sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+        out.write_all(&value.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Macro to shadow prelude with pretty_assertions
+    macro_rules! assert_eq {
+        ($($tt:tt)*) => {
+            pretty_assertions::assert_eq!($($tt)*)
+        };
+    }
+
+    #[test]
+    fn test_samples_per_frame() {
+        let mut beeper = Beeper::new(BeeperConfig::default());
+        let mut out = Vec::new();
+
+        assert_eq!(735, beeper.render_frame(true, &mut out));
+        assert_eq!(735, beeper.render_frame(false, &mut out));
+        assert_eq!(1470, out.len());
+    }
+
+    #[test]
+    fn test_fractional_samples_per_frame() {
+        let mut beeper = Beeper::new(BeeperConfig {
+            sample_rate: 22_050,
+            ..BeeperConfig::default()
+        });
+        let mut out = Vec::new();
+
+        // 367.5 samples per frame alternate between 367 and 368
+        let counts: Vec<usize> = (0..4)
+            .map(|_| beeper.render_frame(false, &mut out))
+            .collect();
+        assert_eq!(vec![367, 368, 367, 368], counts);
+
+        // One second of frames is exactly one second of samples
+        for _ in 4..TIMER_FREQUENCY {
+            beeper.render_frame(true, &mut out);
+        }
+        assert_eq!(22_050, out.len());
+    }
+
+    #[test]
+    fn test_silence() {
+        let mut beeper = Beeper::new(BeeperConfig::default());
+        let mut out = Vec::new();
+        beeper.render_frame(false, &mut out);
+
+        assert!(out.iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn test_square_wave() {
+        let config = BeeperConfig {
+            frequency: 600.0,
+            volume: 0.5,
+            sample_rate: 48_000,
+        };
+        let mut beeper = Beeper::new(config);
+        let mut out = Vec::new();
+        beeper.render_frame(true, &mut out);
+
+        // 800 samples with a period of 80 samples
+        assert_eq!(800, out.len());
+        assert!(out.iter().all(|sample| sample.abs() <= config.volume));
+        assert_eq!(0.5, out[20]);
+        assert_eq!(-0.5, out[60]);
+
+        // Two sign changes in each of the ten periods
+        let edges = out
+            .windows(2)
+            .filter(|pair| (pair[0] > 0.0) != (pair[1] > 0.0))
+            .count();
+        assert_eq!(20, edges);
+    }
+
+    #[test]
+    fn test_band_limited_edges() {
+        // Edges land halfway between the levels instead of jumping straight across
+        assert_eq!(0.0, square(0.0, 0.25));
+        assert_eq!(1.0, square(0.25, 0.25));
+        assert_eq!(0.0, square(0.5, 0.25));
+    }
+
+    #[test]
+    fn test_tone_restarts_in_phase() {
+        let mut beeper = Beeper::new(BeeperConfig::default());
+        let mut first = Vec::new();
+        let mut second = Vec::new();
+
+        beeper.render_frame(true, &mut first);
+        beeper.render_frame(false, &mut Vec::new());
+        beeper.render_frame(true, &mut second);
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_playback_rate() {
+        assert_eq!(4000.0, playback_rate(DEFAULT_PITCH));
+        assert_eq!(8000.0, playback_rate(112));
+        assert_eq!(2000.0, playback_rate(16));
+    }
+
+    #[test]
+    fn test_pattern_one_bit_per_sample() {
+        let mut beeper = Beeper::new(BeeperConfig {
+            volume: 0.5,
+            ..BeeperConfig::default()
+        });
+        let mut pattern = [0xFF; AUDIO_PATTERN_SIZE];
+        pattern[0] = 0b1010_0000;
+        let mut out = Vec::new();
+
+        beeper.render_pattern_frame(true, &pattern, 44_100.0, &mut out);
+
+        // Assert: the pattern loops after 128 samples
+        assert_eq!(735, out.len());
+        assert_eq!(
+            [0.5, -0.5, 0.5, -0.5, -0.5, -0.5, -0.5, -0.5, 0.5],
+            out[..9]
+        );
+        assert_eq!(out[..128], out[128..256]);
+    }
+
+    #[test]
+    fn test_pattern_averaged_between_bits() {
+        let mut beeper = Beeper::new(BeeperConfig {
+            volume: 1.0,
+            ..BeeperConfig::default()
+        });
+        let pattern = [0b1010_1010; AUDIO_PATTERN_SIZE];
+        let mut out = Vec::new();
+
+        // Two bits per sample cancel each other out, half a bit per sample repeats every bit
+        beeper.render_pattern_frame(true, &pattern, 88_200.0, &mut out);
+        assert!(out.iter().all(|&sample| sample == 0.0));
+
+        out.clear();
+        beeper.render_pattern_frame(false, &pattern, 88_200.0, &mut out);
+        beeper.render_pattern_frame(true, &pattern, 22_050.0, &mut out);
+        assert_eq!([1.0, 1.0, -1.0, -1.0, 1.0], out[735..740]);
+    }
+
+    #[test]
+    fn test_render_chip_frame() {
+        let mut beeper = Beeper::new(BeeperConfig::default());
+        let mut chip = Chip8::with_config(crate::Config {
+            platform: crate::Platform::XoChip,
+            seed: Some(0),
+            ..crate::Config::default()
+        });
+
+        // 6A02: VA = 2, FA18: ST = VA
+        chip.load_rom(&[0x6A, 0x02, 0xFA, 0x18]).unwrap();
+        chip.run_frame(2).unwrap();
+
+        // Square wave without a pattern
+        let mut square = Vec::new();
+        Beeper::new(BeeperConfig::default()).render_frame(true, &mut square);
+        let mut out = Vec::new();
+        beeper.render_chip_frame(&chip, &mut out);
+        assert_eq!(square, out);
+
+        // A300: I = 0x300, F002: load the cleared pattern there, which sounds as a constant level
+        chip.load_rom(&[0x6A, 0x02, 0xFA, 0x18, 0xA3, 0x00, 0xF0, 0x02])
+            .unwrap();
+        chip.run_frame(4).unwrap();
+        out.clear();
+        beeper.render_chip_frame(&chip, &mut out);
+        assert!(out.iter().all(|&sample| sample == -0.25));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_frequency_above_nyquist() {
+        Beeper::new(BeeperConfig {
+            frequency: 30_000.0,
+            ..BeeperConfig::default()
+        });
+    }
+
+    #[test]
+    fn test_write_wav() {
+        let mut out = Vec::new();
+        write_wav(&mut out, &[0.0, 1.0, -1.0, 0.5, 2.0], 8_000).unwrap();
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(b"RIFF");
+        expected.extend_from_slice(&46u32.to_le_bytes());
+        expected.extend_from_slice(b"WAVEfmt ");
+        expected.extend_from_slice(&[16, 0, 0, 0, 1, 0, 1, 0]);
+        expected.extend_from_slice(&8_000u32.to_le_bytes());
+        expected.extend_from_slice(&16_000u32.to_le_bytes());
+        expected.extend_from_slice(&[2, 0, 16, 0]);
+        expected.extend_from_slice(b"data");
+        expected.extend_from_slice(&10u32.to_le_bytes());
+        for value in [0i16, 32767, -32767, 16384, 32767] {
+            expected.extend_from_slice(&value.to_le_bytes());
+        }
+
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn test_ring_read() {
+        let mut ring = SampleRing::new(4);
+        ring.extend([0.1, 0.2, 0.3]);
+
+        let mut out = [0.0; 2];
+        assert_eq!(2, ring.read(&mut out));
+        assert_eq!([0.1, 0.2], out);
+
+        assert_eq!(1, ring.read(&mut out));
+        assert_eq!(0.3, out[0]);
+        assert!(ring.is_empty());
+        assert_eq!(None, ring.pop());
+    }
+
+    #[test]
+    fn test_ring_overflow_drops_oldest() {
+        let mut ring = SampleRing::new(3);
+        ring.extend([0.1, 0.2, 0.3, 0.4, 0.5]);
+
+        assert_eq!(3, ring.len());
+        assert_eq!(Some(0.3), ring.pop());
+        assert_eq!(Some(0.4), ring.pop());
+
+        ring.push(0.6);
+        let mut out = [0.0; 4];
+        assert_eq!(2, ring.read(&mut out));
+        assert_eq!([0.5, 0.6], out[..2]);
+    }
+
+    #[test]
+    fn test_ring_with_beeper() {
+        let mut beeper = Beeper::new(BeeperConfig::default());
+        let mut ring = SampleRing::new(1024);
+
+        beeper.render_frame(true, &mut ring);
+        assert_eq!(735, ring.len());
+
+        // Only the newest samples survive a backend that stopped pulling
+        beeper.render_frame(false, &mut ring);
+        assert_eq!(1024, ring.len());
+    }
+}
@@ -1,6 +1,10 @@
-use rand::Rng;
-use rand::distr::StandardUniform;
-use rand::rngs::ThreadRng;
+#![allow(non_snake_case)]
+
+use crate::error::{Chip8Error, Chip8ErrorKind, LoadError};
+use crate::instruction::{self, Instruction};
+use crate::platform::Platform;
+use crate::quirks::{IndexIncrement, Quirks};
+use crate::rng::{RandomSource, SeededRng};
 
 // =================================
 // Fontset for Chip8
@@ -24,12 +28,64 @@ const FONTSET: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+// 8x10 font of SUPER-CHIP, stored behind the small font
+const BIG_FONTSET: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
 // VF register index
 const REG_V0: usize = 0;
 const REG_VF: usize = 0xF;
 const ADDRESS_BITS: u16 = 12;
 const MAX_ADDRESS: u16 = (1 << ADDRESS_BITS) - 1;
 const SIZE_OF_SPRITE: u16 = 5;
+const SIZE_OF_BIG_SPRITE: u16 = 10;
+const BIG_FONT_START: u16 = FONTSET.len() as u16;
+// Pixels the display moves with 00FB and 00FC
+const SCROLL_DISTANCE: usize = 4;
+
+/// Address ROMs are loaded to and execution starts at
+pub const PROGRAM_START: u16 = 0x200;
+/// Width of the display in pixels
+pub const DISPLAY_WIDTH: usize = 64;
+/// Height of the display in pixels
+pub const DISPLAY_HEIGHT: usize = 32;
+/// Width of the display in the SUPER-CHIP hi-res mode
+pub const HIRES_WIDTH: usize = 128;
+/// Height of the display in the SUPER-CHIP hi-res mode
+pub const HIRES_HEIGHT: usize = 64;
+/// Number of SUPER-CHIP RPL user flags saved by FX75
+pub const RPL_FLAGS: usize = 16;
+/// Size of the XO-CHIP audio pattern loaded by F002, 128 one-bit samples
+pub const AUDIO_PATTERN_SIZE: usize = 16;
+/// Pitch register value after reset, plays the audio pattern at 4000 bits per second
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// Frequency in Hz at which the delay and sound timers count down, independent of the
+/// instruction rate
+pub const TIMER_FREQUENCY: u32 = 60;
+
+/// Number of XO-CHIP bitplanes, each pixel of the framebuffer holds one bit per plane
+pub const PLANES: usize = 2;
+
+/// Largest ROM that fits into 4 KiB of memory behind the program start address, XO-CHIP
+/// allows more, see [`Chip8::max_rom_size`]
+pub const MAX_ROM_SIZE: usize = MAX_ADDRESS as usize + 1 - PROGRAM_START as usize;
 
 // =================================
 // Useful macros
@@ -40,24 +96,81 @@ macro_rules! extract_bits {
     };
 }
 
-macro_rules! reg_x {
-    ($num: expr) => {
-        extract_bits!($num, 8, 0xF) as usize
-    };
+// =================================
+// Implementation of Chip8
+// =================================
+
+/// Result of a successfully emulated CPU cycle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// Instruction was executed
+    Executed,
+    /// FX0A is blocking until a key gets pressed and released
+    WaitingForKey,
+    /// Program ended with 00FD, no further instructions are executed
+    Exited,
 }
 
-macro_rules! reg_y {
-    ($num: expr) => {
-        extract_bits!($num, 4, 0xF) as usize
-    };
+/// How an instruction accessed memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    /// Instruction was fetched from memory
+    Fetch,
+    /// Instruction read data, e.g. sprites drawn by DXYN or registers loaded by FX65
+    Read,
+    /// Instruction wrote data, e.g. FX33 or FX55
+    Write,
 }
 
-// =================================
-// Implementation of Chip8
-// =================================
+/// Range of memory accessed by a cycle, see [`Chip8::memory_accesses`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccess {
+    /// How the memory was accessed
+    pub kind: AccessKind,
+    /// First address accessed
+    pub address: u16,
+    /// Number of bytes accessed
+    pub len: usize,
+}
+
+// Execution state of the CPU
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CpuState {
+    // Fetching and executing instructions
+    Running,
+    // FX0A is waiting for any key to go down, holds the target register
+    WaitingForPress { register: usize },
+    // FX0A saw a key go down and waits for its release, like the COSMAC VIP did
+    WaitingForRelease { register: usize, key: u8 },
+    // 00FD ended the program
+    Exited,
+}
+
+/// Change of the beeper state, reported when the timers are ticked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundEvent {
+    /// Sound timer became non-zero, the beeper should start
+    Started,
+    /// Sound timer ran out, the beeper should stop
+    Stopped,
+}
+
+/// Settings a chip8 is constructed with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Instruction set to emulate
+    pub platform: Platform,
+    /// Behaviour of the instructions that differ between interpreters
+    pub quirks: Quirks,
+    /// Seed for the CXNN random source, a random seed is picked if not set
+    pub seed: Option<u64>,
+}
 
-#[cfg_attr(test, derive(Clone, Debug))]
-struct Chip8 {
+/// A CHIP-8 virtual machine
+///
+/// Cloning a chip8 takes a full snapshot, including the state of its random source.
+#[derive(Clone, Debug)]
+pub struct Chip8 {
     // Registers
     registers: [u8; 16],
     pc: u16,
@@ -66,544 +179,1223 @@ struct Chip8 {
     timer_sound: u8,
 
     // Memory
-    memory: [u8; 4096],
+    // Sized to the address space of the platform
+    memory: Box<[u8]>,
     stack: [u16; 16],
     sp: u16,
 
     // I/O
-    graphics: [u8; 64 * 32],
+    // Row-major with the width of the current resolution, one bit per plane
+    graphics: [u8; HIRES_WIDTH * HIRES_HEIGHT],
+    hires: bool,
+    // Bitmask of the planes drawing and scrolling operate on
+    planes: u8,
     keypad: [u8; 16],
+    beeping: bool,
+    // XO-CHIP sound, the plain beeper is used until F002 loads a pattern
+    audio_pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    pitch: u8,
+    // Survive resets, like the HP-48 calculator registers they come from
+    rpl: [u8; RPL_FLAGS],
+    // Memory accessed by the last cycle, only recorded for debuggers that ask for it
+    trace_accesses: bool,
+    accesses: Vec<MemoryAccess>,
+    // 0NNN machine language calls skipped since the last reset
+    machine_calls: u64,
+
+    // State
+    cpu_state: CpuState,
+    platform: Platform,
+    quirks: Quirks,
 
     // Utils
-    rng: ThreadRng,
+    rng: Box<dyn RandomSource>,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Chip8::new()
+    }
 }
 
 impl Chip8 {
-    // Creating a new chip8 instance
-    fn new() -> Chip8 {
-        return Chip8 {
+    /// Create a new chip8 with the default configuration
+    pub fn new() -> Chip8 {
+        Chip8::with_config(Config::default())
+    }
+
+    /// Create a new chip8 emulating the given interpreter quirks
+    pub fn with_quirks(quirks: Quirks) -> Chip8 {
+        Chip8::with_config(Config {
+            quirks,
+            ..Config::default()
+        })
+    }
+
+    /// Create a new chip8 from a configuration
+    pub fn with_config(config: Config) -> Chip8 {
+        let rng = match config.seed {
+            Some(seed) => SeededRng::new(seed),
+            None => SeededRng::from_entropy(),
+        };
+
+        Chip8 {
             registers: [0; 16],
-            pc: 0x200,
+            pc: PROGRAM_START,
             index: 0,
             timer_delay: 0,
             timer_sound: 0,
-            memory: [0; 4096],
+            memory: vec![0; config.platform.memory_size()].into_boxed_slice(),
             stack: [0; 16],
             sp: 0,
-            graphics: [0; 64 * 32],
+            graphics: [0; HIRES_WIDTH * HIRES_HEIGHT],
+            hires: false,
+            planes: 1,
             keypad: [0; 16],
-
-            rng: rand::thread_rng(),
-        };
+            beeping: false,
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            rpl: [0; RPL_FLAGS],
+            trace_accesses: false,
+            accesses: Vec::new(),
+            machine_calls: 0,
+            cpu_state: CpuState::Running,
+            platform: config.platform,
+            quirks: config.quirks,
+
+            rng: Box::new(rng),
+        }
     }
 
-    // Init/Reset a chip8
-    fn init(&mut self, program: &[u8]) {
+    /// Reset the chip8 and load a program at 0x200
+    ///
+    /// Panics if the program is larger than [`Chip8::max_rom_size`], use [`Chip8::load_rom`] for
+    /// untrusted input.
+    pub fn init(&mut self, program: &[u8]) {
         // Set reset all values
         self.registers = [0; 16];
-        self.pc = 0x200;
+        self.pc = PROGRAM_START;
         self.index = 0;
         self.timer_delay = 0;
         self.timer_sound = 0;
-        self.memory = [0; 4096];
+        self.memory.fill(0);
         self.stack = [0; 16];
         self.sp = 0;
-        self.graphics = [0; 64 * 32];
+        self.graphics = [0; HIRES_WIDTH * HIRES_HEIGHT];
+        self.hires = false;
+        self.planes = 1;
         self.keypad = [0; 16];
-
-        // Load fontset into memory
-        for i in 0..80 {
-            self.memory[i] = FONTSET[i];
-        }
+        self.beeping = false;
+        self.audio_pattern = None;
+        self.pitch = DEFAULT_PITCH;
+        self.accesses.clear();
+        self.machine_calls = 0;
+        self.cpu_state = CpuState::Running;
+
+        // Load fontsets into memory
+        self.memory[..FONTSET.len()].copy_from_slice(&FONTSET);
+        let big_font = BIG_FONT_START as usize;
+        self.memory[big_font..big_font + BIG_FONTSET.len()].copy_from_slice(&BIG_FONTSET);
 
         // Load program into memory
-        for i in 0..program.len() {
-            self.memory[i + self.pc as usize] = program[i];
+        let start = self.pc as usize;
+        self.memory[start..start + program.len()].copy_from_slice(program);
+    }
+
+    /// Reset the chip8 and load a ROM image at 0x200
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), LoadError> {
+        if rom.len() > self.max_rom_size() {
+            return Err(LoadError::TooLarge {
+                size: rom.len(),
+                max: self.max_rom_size(),
+            });
         }
+
+        self.init(rom);
+        Ok(())
+    }
+
+    /// Largest ROM that fits into the memory of the platform behind the program start address
+    pub fn max_rom_size(&self) -> usize {
+        self.memory.len() - PROGRAM_START as usize
+    }
+
+    /// Emulate a single CPU cycle
+    ///
+    /// On error, the program counter points at the faulting instruction and the remaining
+    /// state is left untouched, so the chip8 can be inspected or the cycle retried.
+    pub fn step(&mut self) -> Result<StepOutcome, Chip8Error> {
+        self.emulateCycle()
     }
 
     // Emulating one CPU cycle
-    fn emulateCycle(&mut self) {
-        // Fetch opcode
-        let opcode: u16 = u16::from_be_bytes([
-            self.memory[self.pc as usize],
-            self.memory[self.pc as usize + 1],
-        ]);
+    // On error, pc is reset to the faulting instruction and the remaining state is left untouched
+    fn emulateCycle(&mut self) -> Result<StepOutcome, Chip8Error> {
+        self.accesses.clear();
+
+        // No fetching while FX0A is blocking or after the program exited
+        match self.cpu_state {
+            CpuState::Running => {}
+            CpuState::Exited => return Ok(StepOutcome::Exited),
+            _ => return Ok(self.poll_key_wait()),
+        }
+
+        let pc = self.pc;
+
+        // Fetch and decode instruction
+        let Some(instruction) = self.instruction_at(pc) else {
+            return Err(Chip8Error {
+                pc,
+                opcode: 0,
+                kind: Chip8ErrorKind::MemoryOutOfBounds(pc),
+            });
+        };
+
+        self.record_access(AccessKind::Fetch, pc, instruction.size() as usize);
 
         // Increment pc directly in order to avoid confusion at jumps
-        self.pc += 2;
-
-        match (opcode & 0xF000) {
-            0x0000 => match opcode {
-                0x00E0 => {
-                    self._opcode_00E0();
-                } // Clear screen
-                0x00EE => {
-                    self._opcode_00EE();
-                } // Return from subroutine
-                _ => {
-                    self._opcode_0NNN(opcode);
-                } // Execute machine language subroutine at NNN
-            },
-
-            0x1000 => {
-                self._opcode_1NNN(opcode);
-            } // Jump to address NNN
-            0x2000 => {
-                self._opcode_2NNN(opcode);
-            } // Execute subroutine at NNN
-            0x3000 => {
-                self._opcode_3XNN(opcode);
-            } // Skip the following instruction in value of VX equals NN
-            0x4000 => {
-                self._opcode_4XNN(opcode);
-            } // Skip the following instruction if value of VX not equal NN
-            0x5000 => {
-                self._opcode_5XY0(opcode);
-            } // Skip the following instruction if value in VX equal to value in VY
-            0x6000 => {
-                self._opcode_6XNN(opcode);
-            } // Store number NN in VX
-            0x7000 => {
-                self._opcode_7XNN(opcode);
-            } // Add value NN to VX
-            0x8000 => match (opcode & 0x000F) {
-                0x0 => {
-                    self._opcode_8XY0(opcode);
-                } // Set VX to VY
-                0x1 => {
-                    self._opcode_8XY1(opcode);
-                } // Set VX to VX OR VY
-                0x2 => {
-                    self._opcode_8XY2(opcode);
-                } // Set VX to VX AND VY
-                0x3 => {
-                    self._opcode_8XY3(opcode);
-                } // Set VX to VX XOR VY
-                0x4 => {
-                    self._opcode_8XY4(opcode);
-                } // Add the value of VY to VX (VF = 1 if carry otherwise 0)
-                0x5 => {
-                    self._opcode_8XY5(opcode);
-                } // Subtract VY from VX (VF = 1 if borrow occurs, otherwise 0)
-                0x6 => {
-                    self._opcode_8XY6(opcode);
-                } // Shift VY right 1 bit, store in VX (VF = LSB prior to shift)
-                0x7 => {
-                    self._opcode_8XY7(opcode);
-                } // Set VX to VY minus VX (VF = 1 if borrow occurs)
-                0xE => {
-                    self._opcode_8XYE(opcode);
-                } // Shift VY left 1 bit, store in VX (VF = MSB prior to shift)
-                _ => panic!("Unknown opcode"),
-            },
-            0x9000 => {
-                self._opcode_9XY0(opcode);
-            } // Skip instruction if VX and VY not equal
-            0xA000 => {
-                self._opcode_ANNN(opcode);
-            } // Store memory address NNN in I
-            0xB000 => {
-                self._opcode_BNNN(opcode);
-            } // Jump to address NNN + V0
-            0xD000 => {
-                self._opcode_DXYN(opcode);
-            } // Draw sprite
-            0xE000 => match (opcode & 0x00FF) {
-                0x9E => {
-                    self._opcode_EX9E(opcode);
-                } // Skip instruction if key in VX pressed
-                0xA1 => {
-                    self._opcode_EXA1(opcode);
-                } // Skip instruction if key in VX not pressed
-                _ => panic!("Unknown opcode"),
-            },
-
-            0xF000 => match (opcode & 0x00FF) {
-                0x07 => {
-                    self._opcode_FX07(opcode);
-                } // Store the current delay in register VX
-                0x0A => {
-                    self._opcode_FX0A(opcode);
-                } // Wait for keypress, store result in VX
-                0x15 => {
-                    self._opcode_FX0A(opcode);
-                } // Set delay timer to VX
-                0x18 => {
-                    self._opcode_FX18(opcode);
-                } // Set sound timer to VX
-                0x1E => {
-                    self._opcode_FX1E(opcode);
-                } // Add value in VX to I
-                0x29 => {
-                    self._opcode_FX29(opcode);
-                } // Set I to memory of sprite stored in VX
-                0x33 => {
-                    self._opcode_FX33(opcode);
-                } // Store V0-VX inclusive in memory starting at I
-                0x65 => {} // Fill V0-VX inclusive with memory starting at I
-                _ => panic!("Unknown opcode"),
-            },
-
-            _ => panic!("Unknown opcode"),
-        }
-    }
-
-    // Clear the screen
+        self.pc = pc.wrapping_add(instruction.size());
+
+        self.execute(instruction).map_err(|kind| {
+            self.pc = pc;
+            Chip8Error {
+                pc,
+                opcode: instruction.opcode(),
+                kind,
+            }
+        })
+    }
+
+    // Advance the FX0A state machine based on the current keypad
+    fn poll_key_wait(&mut self) -> StepOutcome {
+        match self.cpu_state {
+            CpuState::Running => return StepOutcome::Executed,
+            CpuState::Exited => return StepOutcome::Exited,
+            CpuState::WaitingForPress { register } => {
+                if let Some(key) = self.keypad.iter().position(|&k| k == 1) {
+                    self.cpu_state = CpuState::WaitingForRelease {
+                        register,
+                        key: key as u8,
+                    };
+                }
+            }
+            CpuState::WaitingForRelease { register, key } => {
+                if self.keypad[key as usize] == 0 {
+                    self.registers[register] = key;
+                    self.cpu_state = CpuState::Running;
+                    return StepOutcome::Executed;
+                }
+            }
+        }
+
+        StepOutcome::WaitingForKey
+    }
+
+    /// Whether the CPU is blocked in FX0A, the host may sleep until the keypad changes
+    pub fn is_waiting_for_key(&self) -> bool {
+        matches!(
+            self.cpu_state,
+            CpuState::WaitingForPress { .. } | CpuState::WaitingForRelease { .. }
+        )
+    }
+
+    /// Whether the program ended with 00FD
+    pub fn has_exited(&self) -> bool {
+        self.cpu_state == CpuState::Exited
+    }
+
+    /// Mark the given key (0x0-0xF) as pressed
+    pub fn press_key(&mut self, key: u8) {
+        self.keypad[key as usize & 0xF] = 1;
+    }
+
+    /// Mark the given key (0x0-0xF) as released
+    pub fn release_key(&mut self, key: u8) {
+        self.keypad[key as usize & 0xF] = 0;
+    }
+
+    /// Replace the random source used by CXNN
+    pub fn set_rng(&mut self, rng: Box<dyn RandomSource>) {
+        self.rng = rng;
+    }
+
+    /// Framebuffer in row-major order, one byte per pixel
+    ///
+    /// Each pixel holds a bit per plane, so its value is a colour index between 0 and 3 (only 0
+    /// or 1 outside of XO-CHIP). Its size follows the current resolution, see
+    /// [`Chip8::display_width`] and [`Chip8::display_height`].
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.graphics[..self.display_width() * self.display_height()]
+    }
+
+    /// Whether the pixel at the given position is set in any plane
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.graphics[y * self.display_width() + x] != 0
+    }
+
+    /// Width of the display in the current resolution
+    pub fn display_width(&self) -> usize {
+        if self.hires {
+            HIRES_WIDTH
+        } else {
+            DISPLAY_WIDTH
+        }
+    }
+
+    /// Height of the display in the current resolution
+    pub fn display_height(&self) -> usize {
+        if self.hires {
+            HIRES_HEIGHT
+        } else {
+            DISPLAY_HEIGHT
+        }
+    }
+
+    /// Whether the SUPER-CHIP hi-res mode is active
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Bitmask of the XO-CHIP planes selected with FN01
+    pub fn selected_planes(&self) -> u8 {
+        self.planes
+    }
+
+    /// RPL user flags written by FX75, the host may persist them between runs
+    pub fn rpl_flags(&self) -> &[u8; RPL_FLAGS] {
+        &self.rpl
+    }
+
+    /// Restore previously saved RPL user flags
+    pub fn set_rpl_flags(&mut self, flags: [u8; RPL_FLAGS]) {
+        self.rpl = flags;
+    }
+
+    /// General purpose registers V0-VF
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.registers
+    }
+
+    /// Change the general purpose registers, e.g. from a debugger
+    pub fn registers_mut(&mut self) -> &mut [u8; 16] {
+        &mut self.registers
+    }
+
+    /// Program counter
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// Continue execution at another address
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    /// Index register I
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// Change the index register I
+    pub fn set_index(&mut self, index: u16) {
+        self.index = index;
+    }
+
+    /// Stack pointer, the number of return addresses on the stack
+    pub fn sp(&self) -> u16 {
+        self.sp
+    }
+
+    /// Return addresses currently on the stack, innermost call last
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    /// Current value of the delay timer
+    pub fn delay_timer(&self) -> u8 {
+        self.timer_delay
+    }
+
+    /// Current value of the sound timer
+    pub fn sound_timer(&self) -> u8 {
+        self.timer_sound
+    }
+
+    /// Change the delay and sound timer
+    pub fn set_timers(&mut self, delay: u8, sound: u8) {
+        self.timer_delay = delay;
+        self.timer_sound = sound;
+    }
+
+    /// Whole address space including the font and the loaded program
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// Change the address space, e.g. to patch a running program
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+
+    /// State of the 16 keys, 1 if pressed
+    pub fn keypad(&self) -> &[u8; 16] {
+        &self.keypad
+    }
+
+    /// Quirks the chip8 was created with
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Instruction set the chip8 emulates
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    /// Record the memory accesses of every cycle, see [`Chip8::memory_accesses`]
+    pub fn set_access_tracing(&mut self, enabled: bool) {
+        self.trace_accesses = enabled;
+        self.accesses.clear();
+    }
+
+    /// Memory the last cycle fetched, read and wrote, empty unless access tracing is enabled
+    ///
+    /// Accesses are reported for whole ranges, even if e.g. a clipped sprite used only part of
+    /// its data.
+    pub fn memory_accesses(&self) -> &[MemoryAccess] {
+        &self.accesses
+    }
+
+    /// Number of 0NNN machine language calls skipped since the last reset
+    ///
+    /// These calls need the original hardware, a ROM using them likely misbehaves. Hosts that want
+    /// to know where they happen can look for [`Instruction::Sys`] with [`Chip8::instruction_at`].
+    pub fn machine_calls(&self) -> u64 {
+        self.machine_calls
+    }
+
+    /// Decode the instruction at the given address the way this chip8 executes it
+    ///
+    /// Instructions the platform lacks become [`Instruction::Unknown`], except for the SUPER-CHIP
+    /// additions in the 0NNN range, which stay machine language calls. None if the opcode
+    /// exceeds memory.
+    pub fn instruction_at(&self, address: u16) -> Option<Instruction> {
+        let instruction = instruction::decode_at(&self.memory, address as usize)?;
+
+        Some(match instruction {
+            _ if self.platform.supports(&instruction) => instruction,
+            Instruction::ScrollDown(_)
+            | Instruction::ScrollUp(_)
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Exit
+            | Instruction::Low
+            | Instruction::High => Instruction::Sys(instruction.opcode()),
+            _ => Instruction::Unknown(instruction.opcode()),
+        })
+    }
+
+    // Execute a single decoded instruction
+    fn execute(&mut self, instruction: Instruction) -> Result<StepOutcome, Chip8ErrorKind> {
+        match instruction {
+            // Clear screen
+            Instruction::Cls => self._opcode_00E0(),
+            // Return from subroutine
+            Instruction::Ret => self._opcode_00EE()?,
+            // Scroll display down N rows
+            Instruction::ScrollDown(n) => self._opcode_00CN(n as usize),
+            // Scroll display up N rows
+            Instruction::ScrollUp(n) => self._opcode_00DN(n as usize),
+            // Scroll display right 4 pixels
+            Instruction::ScrollRight => self._opcode_00FB(),
+            // Scroll display left 4 pixels
+            Instruction::ScrollLeft => self._opcode_00FC(),
+            // Exit interpreter
+            Instruction::Exit => return Ok(self._opcode_00FD()),
+            // Switch to lo-res mode
+            Instruction::Low => self._opcode_00FE(),
+            // Switch to hi-res mode
+            Instruction::High => self._opcode_00FF(),
+            // Execute machine language subroutine at NNN
+            Instruction::Sys(address) => self._opcode_0NNN(address),
+
+            // Jump to address NNN
+            Instruction::Jp(address) => self._opcode_1NNN(address),
+            // Execute subroutine at NNN
+            Instruction::Call(address) => self._opcode_2NNN(address)?,
+            // Skip the following instruction if value of VX equals NN
+            Instruction::SeByte(x, nn) => self._opcode_3XNN(x as usize, nn),
+            // Skip the following instruction if value of VX not equal NN
+            Instruction::SneByte(x, nn) => self._opcode_4XNN(x as usize, nn),
+            // Skip the following instruction if value in VX equal to value in VY
+            Instruction::SeReg(x, y) => self._opcode_5XY0(x as usize, y as usize),
+            // Store VX-VY inclusive in memory starting at I
+            Instruction::Save(x, y) => self._opcode_5XY2(x as usize, y as usize)?,
+            // Fill VX-VY inclusive with memory starting at I
+            Instruction::Load(x, y) => self._opcode_5XY3(x as usize, y as usize)?,
+            // Store number NN in VX
+            Instruction::Ld(x, nn) => self._opcode_6XNN(x as usize, nn),
+            // Add value NN to VX
+            Instruction::AddByte(x, nn) => self._opcode_7XNN(x as usize, nn),
+            // Set VX to VY
+            Instruction::LdReg(x, y) => self._opcode_8XY0(x as usize, y as usize),
+            // Set VX to VX OR VY
+            Instruction::Or(x, y) => self._opcode_8XY1(x as usize, y as usize),
+            // Set VX to VX AND VY
+            Instruction::And(x, y) => self._opcode_8XY2(x as usize, y as usize),
+            // Set VX to VX XOR VY
+            Instruction::Xor(x, y) => self._opcode_8XY3(x as usize, y as usize),
+            // Add the value of VY to VX (VF = 1 if carry otherwise 0)
+            Instruction::AddReg(x, y) => self._opcode_8XY4(x as usize, y as usize),
+            // Subtract VY from VX (VF = 0 if borrow occurs, otherwise 1)
+            Instruction::Sub(x, y) => self._opcode_8XY5(x as usize, y as usize),
+            // Shift VX right 1 bit (VF = LSB prior to shift)
+            Instruction::Shr(x, y) => self._opcode_8XY6(x as usize, y as usize),
+            // Set VX to VY minus VX (VF = 0 if borrow occurs, otherwise 1)
+            Instruction::Subn(x, y) => self._opcode_8XY7(x as usize, y as usize),
+            // Shift VX left 1 bit (VF = MSB prior to shift)
+            Instruction::Shl(x, y) => self._opcode_8XYE(x as usize, y as usize),
+            // Skip instruction if VX and VY not equal
+            Instruction::SneReg(x, y) => self._opcode_9XY0(x as usize, y as usize),
+            // Store memory address NNN in I
+            Instruction::LdI(address) => self._opcode_ANNN(address),
+            // Jump to address NNN + V0
+            Instruction::JpV0(address) => self._opcode_BNNN(address)?,
+            // Set VX to a random number masked with NN
+            Instruction::Rnd(x, nn) => self._opcode_CXNN(x as usize, nn),
+            // Draw sprite
+            Instruction::Drw { x, y, n } => {
+                self._opcode_DXYN(x as usize, y as usize, n as usize)?
+            }
+            // Skip instruction if key in VX pressed
+            Instruction::Skp(x) => self._opcode_EX9E(x as usize)?,
+            // Skip instruction if key in VX not pressed
+            Instruction::Sknp(x) => self._opcode_EXA1(x as usize)?,
+
+            // Load the following 16-bit address into I
+            Instruction::LdILong(address) => self._opcode_F000(address),
+            // Select drawing planes N
+            Instruction::Plane(n) => self._opcode_FN01(n),
+            // Load 16 bytes audio pattern from I
+            Instruction::Audio => self._opcode_F002()?,
+            // Store the current delay in register VX
+            Instruction::LdVxDt(x) => self._opcode_FX07(x as usize),
+            // Wait for keypress, store result in VX
+            Instruction::LdVxK(x) => return Ok(self._opcode_FX0A(x as usize)),
+            // Set delay timer to VX
+            Instruction::LdDtVx(x) => self._opcode_FX15(x as usize),
+            // Set sound timer to VX
+            Instruction::LdStVx(x) => self._opcode_FX18(x as usize),
+            // Add value in VX to I
+            Instruction::AddIVx(x) => self._opcode_FX1E(x as usize),
+            // Set I to memory of sprite stored in VX
+            Instruction::LdFVx(x) => self._opcode_FX29(x as usize),
+            // Set I to memory of big sprite stored in VX
+            Instruction::LdHfVx(x) => self._opcode_FX30(x as usize),
+            // Store BCD of VX at I, I + 1 and I + 2
+            Instruction::LdBVx(x) => self._opcode_FX33(x as usize)?,
+            // Set audio pitch to VX
+            Instruction::Pitch(x) => self._opcode_FX3A(x as usize),
+            // Store V0-VX inclusive in memory starting at I
+            Instruction::LdIVx(x) => self._opcode_FX55(x as usize)?,
+            // Fill V0-VX inclusive with memory starting at I
+            Instruction::LdVxI(x) => self._opcode_FX65(x as usize)?,
+            // Store V0-VX inclusive in RPL user flags
+            Instruction::LdRVx(x) => self._opcode_FX75(x as usize),
+            // Fill V0-VX inclusive with RPL user flags
+            Instruction::LdVxR(x) => self._opcode_FX85(x as usize),
+
+            Instruction::Unknown(_) => return Err(Chip8ErrorKind::UnknownOpcode),
+        }
+
+        Ok(StepOutcome::Executed)
+    }
+
+    /// Decrement the delay and sound timers, should be called at [`TIMER_FREQUENCY`]
+    ///
+    /// Returns a sound event if the beeper has been switched on or off.
+    pub fn tick_timers(&mut self) -> Option<SoundEvent> {
+        // The beeper sounds for as long as the sound timer is non-zero
+        let beeping = self.timer_sound > 0;
+
+        self.timer_delay = self.timer_delay.saturating_sub(1);
+        self.timer_sound = self.timer_sound.saturating_sub(1);
+
+        let event = match (self.beeping, beeping) {
+            (false, true) => Some(SoundEvent::Started),
+            (true, false) => Some(SoundEvent::Stopped),
+            _ => None,
+        };
+
+        self.beeping = beeping;
+        event
+    }
+
+    /// Emulate one frame: run a number of instructions followed by a single timer tick
+    ///
+    /// Stops early while waiting for a key or after 00FD, and at the first fault without ticking
+    /// the timers.
+    pub fn run_frame(
+        &mut self,
+        instructions_per_frame: usize,
+    ) -> Result<Option<SoundEvent>, Chip8Error> {
+        for _ in 0..instructions_per_frame {
+            // The keypad won't change until the host gets control back
+            if self.emulateCycle()? != StepOutcome::Executed {
+                break;
+            }
+        }
+
+        Ok(self.tick_timers())
+    }
+
+    /// Whether the beeper should currently be sounding
+    pub fn is_beeping(&self) -> bool {
+        self.beeping
+    }
+
+    /// XO-CHIP audio pattern loaded by F002, None while the plain beeper should be used
+    pub fn audio_pattern(&self) -> Option<&[u8; AUDIO_PATTERN_SIZE]> {
+        self.audio_pattern.as_ref()
+    }
+
+    /// XO-CHIP pitch register set by FX3A, see [`crate::audio::playback_rate`]
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    // Clear the selected planes of the screen
     #[inline]
     fn _opcode_00E0(&mut self) {
-        self.graphics = [0; 64 * 32];
+        let planes = self.planes;
+        for pixel in self.graphics.iter_mut() {
+            *pixel &= !planes;
+        }
+    }
+
+    // Scroll the selected planes of the display down by N rows
+    #[inline]
+    fn _opcode_00CN(&mut self, rows: usize) {
+        let width = self.display_width();
+
+        self.scroll(|x, y| (y >= rows).then(|| (y - rows) * width + x));
+    }
+
+    // Scroll the selected planes of the display up by N rows
+    #[inline]
+    fn _opcode_00DN(&mut self, rows: usize) {
+        let (width, height) = (self.display_width(), self.display_height());
+
+        self.scroll(|x, y| (y + rows < height).then(|| (y + rows) * width + x));
+    }
+
+    // Scroll the selected planes of the display right by 4 pixels
+    #[inline]
+    fn _opcode_00FB(&mut self) {
+        let width = self.display_width();
+
+        self.scroll(|x, y| (x >= SCROLL_DISTANCE).then(|| y * width + x - SCROLL_DISTANCE));
+    }
+
+    // Scroll the selected planes of the display left by 4 pixels
+    #[inline]
+    fn _opcode_00FC(&mut self) {
+        let width = self.display_width();
+
+        self.scroll(|x, y| (x + SCROLL_DISTANCE < width).then(|| y * width + x + SCROLL_DISTANCE));
+    }
+
+    // Exit the interpreter
+    #[inline]
+    fn _opcode_00FD(&mut self) -> StepOutcome {
+        // Stay on the exit instruction, like the calculator returning to its prompt
+        self.pc -= 2;
+        self.cpu_state = CpuState::Exited;
+
+        StepOutcome::Exited
+    }
+
+    // Switch to the 64x32 lo-res mode, clearing all planes of the screen
+    #[inline]
+    fn _opcode_00FE(&mut self) {
+        self.hires = false;
+        self.graphics = [0; HIRES_WIDTH * HIRES_HEIGHT];
+    }
+
+    // Switch to the 128x64 hi-res mode, clearing all planes of the screen
+    #[inline]
+    fn _opcode_00FF(&mut self) {
+        self.hires = true;
+        self.graphics = [0; HIRES_WIDTH * HIRES_HEIGHT];
     }
 
     // Return from subroutine
     #[inline]
-    fn _opcode_00EE(&mut self) {
-        self.pc = self.stack_pop();
+    fn _opcode_00EE(&mut self) -> Result<(), Chip8ErrorKind> {
+        self.pc = self.stack_pop()?;
+        Ok(())
     }
 
     // Execute machine language subroutine at address NNN
+    // There is no 1802 to run it on, so the call is skipped and only counted
     #[inline]
-    fn _opcode_0NNN(&mut self, opcode: u16) {
-        println!("Warning: 0NNN opcode called at {:04X}", self.pc);
+    fn _opcode_0NNN(&mut self, _address: u16) {
+        self.machine_calls += 1;
     }
 
     // Jump to address NNN
     #[inline]
-    fn _opcode_1NNN(&mut self, opcode: u16) {
-        self.pc = opcode & 0x0FFF;
+    fn _opcode_1NNN(&mut self, address: u16) {
+        self.pc = address;
     }
 
     // Execute subroutine starting at address NNN
     #[inline]
-    fn _opcode_2NNN(&mut self, opcode: u16) {
-        self.stack_push(self.pc);
-        self.pc = opcode & 0x0FFF;
+    fn _opcode_2NNN(&mut self, address: u16) -> Result<(), Chip8ErrorKind> {
+        self.stack_push(self.pc)?;
+        self.pc = address;
+        Ok(())
     }
 
     // Skip the following instruction if the value of register VX is not equal to NN
     #[inline]
-    fn _opcode_3XNN(&mut self, opcode: u16) {
-        let register: usize = reg_x!(opcode);
-        let value: u8 = (opcode & 0x00FF) as u8;
-
-        if (self.registers[register] == value) {
-            self.pc += 2;
+    fn _opcode_3XNN(&mut self, register: usize, value: u8) {
+        if self.registers[register] == value {
+            self.skip_instruction();
         }
     }
 
     // Skip the following instruction if the value of register VX is not equal to the value of
     // register VY
     #[inline]
-    fn _opcode_4XNN(&mut self, opcode: u16) {
-        let register: usize = reg_x!(opcode);
-        let value: u8 = (opcode & 0x00FF) as u8;
-
-        if (self.registers[register] != value) {
-            self.pc += 2;
+    fn _opcode_4XNN(&mut self, register: usize, value: u8) {
+        if self.registers[register] != value {
+            self.skip_instruction();
         }
     }
 
     // Skip the following instructionif the value of register VX is equal to the value of register
     // VY
     #[inline]
-    fn _opcode_5XY0(&mut self, opcode: u16) {
-        let registerX: usize = reg_x!(opcode);
-        let registerY: usize = reg_y!(opcode);
+    fn _opcode_5XY0(&mut self, registerX: usize, registerY: usize) {
+        if self.registers[registerX] == self.registers[registerY] {
+            self.skip_instruction();
+        }
+    }
+
+    // Store the values of registers VX to VY inclusive in memory starting at address I
+    // Registers are stored in reverse order if X > Y, I is left unchanged
+    #[inline]
+    fn _opcode_5XY2(&mut self, registerX: usize, registerY: usize) -> Result<(), Chip8ErrorKind> {
+        let registers = register_range(registerX, registerY);
+        let start = self.check_range(AccessKind::Write, self.index, registers.len())?;
+
+        for (offset, register) in registers.enumerate() {
+            self.memory[start + offset] = self.registers[register];
+        }
+
+        Ok(())
+    }
+
+    // Fill registers VX to VY inclusive with the values stored in memory starting at address I
+    // Registers are loaded in reverse order if X > Y, I is left unchanged
+    #[inline]
+    fn _opcode_5XY3(&mut self, registerX: usize, registerY: usize) -> Result<(), Chip8ErrorKind> {
+        let registers = register_range(registerX, registerY);
+        let start = self.check_range(AccessKind::Read, self.index, registers.len())?;
 
-        if (self.registers[registerX] == self.registers[registerY]) {
-            self.pc += 2;
+        for (offset, register) in registers.enumerate() {
+            self.registers[register] = self.memory[start + offset];
         }
+
+        Ok(())
     }
 
     // Store number NN in register VX
     #[inline]
-    fn _opcode_6XNN(&mut self, opcode: u16) {
-        let value: u8 = (opcode & 0x00FF) as u8;
-        let register: usize = reg_x!(opcode);
-
+    fn _opcode_6XNN(&mut self, register: usize, value: u8) {
         self.registers[register] = value;
     }
 
     // Add the value NN to register VX
     #[inline]
-    fn _opcode_7XNN(&mut self, opcode: u16) {
-        let value: u8 = (opcode & 0x00FF) as u8;
-        let register: usize = reg_x!(opcode);
-
+    fn _opcode_7XNN(&mut self, register: usize, value: u8) {
         self.registers[register] = self.registers[register].wrapping_add(value);
     }
 
     // Store the value of register VY in register VX
     #[inline]
-    fn _opcode_8XY0(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let registerY = reg_y!(opcode);
-
+    fn _opcode_8XY0(&mut self, registerX: usize, registerY: usize) {
         self.registers[registerX] = self.registers[registerY];
     }
 
     // Set VX to VX OR VY
     #[inline]
-    fn _opcode_8XY1(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let registerY = reg_y!(opcode);
+    fn _opcode_8XY1(&mut self, registerX: usize, registerY: usize) {
+        self.registers[registerX] |= self.registers[registerY];
 
-        self.registers[registerX] = self.registers[registerX] | self.registers[registerY];
+        if self.quirks.vf_reset {
+            self.registers[REG_VF] = 0;
+        }
     }
 
     // Set VX to VX AND VY
     #[inline]
-    fn _opcode_8XY2(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let registerY = reg_y!(opcode);
+    fn _opcode_8XY2(&mut self, registerX: usize, registerY: usize) {
+        self.registers[registerX] &= self.registers[registerY];
 
-        self.registers[registerX] = self.registers[registerX] & self.registers[registerY];
+        if self.quirks.vf_reset {
+            self.registers[REG_VF] = 0;
+        }
     }
 
     // Set VX to VX XOR VY
     #[inline]
-    fn _opcode_8XY3(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let registerY = reg_y!(opcode);
+    fn _opcode_8XY3(&mut self, registerX: usize, registerY: usize) {
+        self.registers[registerX] ^= self.registers[registerY];
 
-        self.registers[registerX] = self.registers[registerX] ^ self.registers[registerY];
+        if self.quirks.vf_reset {
+            self.registers[REG_VF] = 0;
+        }
     }
 
     // Add the value of register VY to register VX, set VF to 01 if carry occurs  (otherwise 00)
     #[inline]
-    fn _opcode_8XY4(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let registerY = reg_y!(opcode);
-
+    fn _opcode_8XY4(&mut self, registerX: usize, registerY: usize) {
         let (result, carry) = self.registers[registerX].overflowing_add(self.registers[registerY]);
 
         self.registers[registerX] = result;
         self.registers[REG_VF] = if carry { 1 } else { 0 };
     }
 
-    // Subtract VY from VX, set VF if borrow occurs
+    // Subtract VY from VX, set VF to 00 if borrow occurs (otherwise 01)
     #[inline]
-    fn _opcode_8XY5(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let registerY = reg_y!(opcode);
-
+    fn _opcode_8XY5(&mut self, registerX: usize, registerY: usize) {
         let (result, borrow) = self.registers[registerX].overflowing_sub(self.registers[registerY]);
 
         self.registers[registerX] = result;
-        self.registers[REG_VF] = if borrow { 1 } else { 0 };
+        self.registers[REG_VF] = if borrow { 0 } else { 1 };
     }
 
-    // Store VX shifted right on bit in register VX, set VF to LSB prior to shift
+    // Shift VX (or VY depending on quirks) right one bit and store it in VX
+    // Set VF to LSB prior to shift
     #[inline]
-    fn _opcode_8XY6(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let registerY = reg_y!(opcode);
+    fn _opcode_8XY6(&mut self, registerX: usize, registerY: usize) {
+        let source = if self.quirks.shift_uses_vy {
+            registerY
+        } else {
+            registerX
+        };
+        let value = self.registers[source];
 
-        self.registers[REG_VF] = extract_bits!(self.registers[registerY], 0, 0x1);
-        self.registers[registerX] = self.registers[registerY] >> 1;
+        self.registers[registerX] = value >> 1;
+        self.registers[REG_VF] = extract_bits!(value, 0, 0x1);
     }
 
-    // Set VX to VY - VX, set VF if borrow occurs
+    // Set VX to VY - VX, set VF to 00 if borrow occurs (otherwise 01)
     #[inline]
-    fn _opcode_8XY7(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let registerY = reg_y!(opcode);
-
+    fn _opcode_8XY7(&mut self, registerX: usize, registerY: usize) {
         let (result, borrow) = self.registers[registerY].overflowing_sub(self.registers[registerX]);
 
         self.registers[registerX] = result;
-        self.registers[REG_VF] = if borrow { 1 } else { 0 };
+        self.registers[REG_VF] = if borrow { 0 } else { 1 };
     }
 
-    // Store VY shifted left one bit in VX, set VF to MSB prior to shift
+    // Shift VX (or VY depending on quirks) left one bit and store it in VX
+    // Set VF to MSB prior to shift
     #[inline]
-    fn _opcode_8XYE(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let registerY = reg_y!(opcode);
+    fn _opcode_8XYE(&mut self, registerX: usize, registerY: usize) {
+        let source = if self.quirks.shift_uses_vy {
+            registerY
+        } else {
+            registerX
+        };
+        let value = self.registers[source];
 
-        self.registers[REG_VF] = extract_bits!(self.registers[registerY], 7, 0x1);
-        self.registers[registerX] = self.registers[registerY] << 1;
+        self.registers[registerX] = value << 1;
+        self.registers[REG_VF] = extract_bits!(value, 7, 0x1);
     }
 
     // Skip the following instruction if VX is NOT equal to VY
     #[inline]
-    fn _opcode_9XY0(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let registerY = reg_y!(opcode);
-
-        if (self.registers[registerX] != self.registers[registerY]) {
+    fn _opcode_9XY0(&mut self, registerX: usize, registerY: usize) {
+        if self.registers[registerX] != self.registers[registerY] {
             // Skip next opcode
-            self.pc += 2;
+            self.skip_instruction();
         }
     }
 
     // Store the memory address NNN in register I
     #[inline]
-    fn _opcode_ANNN(&mut self, opcode: u16) {
-        let address = extract_bits!(opcode, 0, 0xFFF);
+    fn _opcode_ANNN(&mut self, address: u16) {
         self.index = address;
     }
 
-    // Jump to address NNN + V0
+    // Jump to address NNN + V0 (or XNN + VX depending on quirks)
     #[inline]
-    fn _opcode_BNNN(&mut self, opcode: u16) {
-        let mut address = extract_bits!(opcode, 0, 0xFFF);
-        let sum = address.checked_add(self.registers[0] as u16);
+    fn _opcode_BNNN(&mut self, address: u16) -> Result<(), Chip8ErrorKind> {
+        let register = if self.quirks.jump_uses_vx {
+            (address >> 8) as usize
+        } else {
+            REG_V0
+        };
+        let address = address + self.registers[register] as u16;
 
-        match sum {
-            Some(s) if s <= MAX_ADDRESS => address = s,
-            _ => panic!(
-                "SEGFAULT: Trying to access invalid address at {:04X}",
-                self.pc
-            ),
+        if address as usize >= self.memory.len() {
+            return Err(Chip8ErrorKind::MemoryOutOfBounds(address));
         }
 
         self.pc = address;
+        Ok(())
     }
 
     // Set VX to a random number with a mask of NN
     #[inline]
-    fn _opcode_CXNN(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let mask = extract_bits!(opcode, 0, 0xFF) as u8;
-
-        let random_number: u8 = self.rng.sample(StandardUniform);
+    fn _opcode_CXNN(&mut self, registerX: usize, mask: u8) {
+        let random_number = self.rng.next_byte();
         self.registers[registerX] = random_number & mask;
     }
 
     // Draw a sprite at postion VX, VY with N bytes of sprite data starting at I
+    // On SUPER-CHIP, DXY0 draws a 16x16 sprite made of 32 bytes
+    // On XO-CHIP, every selected plane gets its own sprite data, stored one after another
     // Set VF if any pixels are changed to unset
     #[inline]
-    fn _opcode_DXYN(&mut self, opcode: u16) {
-        //TODO: Finish opcode DXYN implementation (what da hell is a sprite?)
-        unimplemented!();
+    fn _opcode_DXYN(
+        &mut self,
+        registerX: usize,
+        registerY: usize,
+        n: usize,
+    ) -> Result<(), Chip8ErrorKind> {
+        let (width, height) = (self.display_width(), self.display_height());
+
+        // The starting position wraps around the screen, the sprite itself gets clipped or wrapped
+        // depending on quirks
+        let x = self.registers[registerX] as usize % width;
+        let y = self.registers[registerY] as usize % height;
+        let (rows, bytes_per_row) = match n {
+            0 if self.platform.has_super_chip() => (16, 2),
+            rows => (rows, 1),
+        };
+        let sprite_width = 8 * bytes_per_row;
+        let size = rows * bytes_per_row;
+
+        // Bounds checking
+        let planes = self.planes;
+        let len = size * planes.count_ones() as usize;
+        let start = self.check_range(AccessKind::Read, self.index, len)?;
+
+        self.registers[REG_VF] = 0;
+
+        let wrap = self.quirks.wrap_sprites;
+
+        let selected = (0..PLANES)
+            .map(|plane| 1 << plane)
+            .filter(|bit| planes & bit != 0);
+        for (plane, bit) in selected.enumerate() {
+            let data = start + plane * size;
+
+            for row in 0..rows {
+                let mut py = y + row;
+                if py >= height {
+                    if !wrap {
+                        break;
+                    }
+                    py -= height;
+                }
+
+                let offset = data + row * bytes_per_row;
+                let sprite = self.memory[offset..offset + bytes_per_row]
+                    .iter()
+                    .fold(0u16, |bits, &byte| (bits << 8) | byte as u16);
+
+                for col in 0..sprite_width {
+                    let mut px = x + col;
+                    if px >= width {
+                        if !wrap {
+                            break;
+                        }
+                        px -= width;
+                    }
+
+                    // Only set bits of the sprite flip a pixel
+                    if extract_bits!(sprite, sprite_width - 1 - col, 0x1) == 0 {
+                        continue;
+                    }
+
+                    let pixel = &mut self.graphics[py * width + px];
+                    if *pixel & bit != 0 {
+                        self.registers[REG_VF] = 1;
+                    }
+                    *pixel ^= bit;
+                }
+            }
+        }
+
+        Ok(())
     }
 
     // Skip the following instruction if key, corresponding to hex value in VX is pressed
     #[inline]
-    fn _opcode_EX9E(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let value = self.registers[registerX] as usize;
+    fn _opcode_EX9E(&mut self, register: usize) -> Result<(), Chip8ErrorKind> {
+        let value = self.registers[register];
 
-        if (self.keypad[value] == 1) {
-            self.pc += 2;
+        let key = self
+            .keypad
+            .get(value as usize)
+            .ok_or(Chip8ErrorKind::InvalidKey(value))?;
+
+        if *key == 1 {
+            self.skip_instruction();
         }
+
+        Ok(())
     }
 
     // Skip the following instruction if key, corresponding to hex value in VX is NOT pressed
     #[inline]
-    fn _opcode_EXA1(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        let value = self.registers[registerX] as usize;
+    fn _opcode_EXA1(&mut self, register: usize) -> Result<(), Chip8ErrorKind> {
+        let value = self.registers[register];
+
+        let key = self
+            .keypad
+            .get(value as usize)
+            .ok_or(Chip8ErrorKind::InvalidKey(value))?;
 
-        if (self.keypad[value] == 0) {
-            self.pc += 2;
+        if *key == 0 {
+            self.skip_instruction();
         }
+
+        Ok(())
+    }
+
+    // Load the 16-bit address following the instruction into I
+    #[inline]
+    fn _opcode_F000(&mut self, address: u16) {
+        self.index = address;
+    }
+
+    // Select the planes N used by drawing, clearing and scrolling
+    #[inline]
+    fn _opcode_FN01(&mut self, planes: u8) {
+        self.planes = planes & ((1 << PLANES) - 1);
+    }
+
+    // Load the 16 bytes audio pattern starting at I
+    #[inline]
+    fn _opcode_F002(&mut self) -> Result<(), Chip8ErrorKind> {
+        let start = self.check_range(AccessKind::Read, self.index, AUDIO_PATTERN_SIZE)?;
+
+        let mut pattern = [0; AUDIO_PATTERN_SIZE];
+        pattern.copy_from_slice(&self.memory[start..start + AUDIO_PATTERN_SIZE]);
+        self.audio_pattern = Some(pattern);
+
+        Ok(())
+    }
+
+    // Set the pitch the audio pattern is played at to VX
+    #[inline]
+    fn _opcode_FX3A(&mut self, register: usize) {
+        self.pitch = self.registers[register];
     }
 
     // Store current value of delay in VX
     #[inline]
-    fn _opcode_FX07(&mut self, opcode: u16) {
-        let registerX = reg_x!(opcode);
-        self.registers[registerX] = self.timer_delay;
+    fn _opcode_FX07(&mut self, register: usize) {
+        self.registers[register] = self.timer_delay;
     }
 
-    // Wait for a keypress and store the result in register VX
+    // Wait for a key to be pressed and released, then store the number of the key in register VX
+    // The actual waiting is done by the CPU state machine in poll_key_wait
     #[inline]
-    fn _opcode_FX0A(&mut self, opcode: u16) {
-        if let Some(&key) = self.keypad.iter().find(|&&k| k == 1) {
-            let register = reg_x!(opcode);
-            self.registers[register] = key;
-            return;
-        }
+    fn _opcode_FX0A(&mut self, register: usize) -> StepOutcome {
+        self.cpu_state = CpuState::WaitingForPress { register };
 
-        // Decrement to execute this instruction again next cycle
-        // Not very pretty, but everything else would be more complicated...
-        // Maybe add a flag in the future?
-        self.pc -= 2;
+        StepOutcome::WaitingForKey
+    }
+
+    // Set the delay timer to the value of register VX
+    #[inline]
+    fn _opcode_FX15(&mut self, register: usize) {
+        self.timer_delay = self.registers[register];
     }
 
     // Set the sound timer to the value of register VX
     #[inline]
-    fn _opcode_FX18(&mut self, opcode: u16) {
-        let register = reg_x!(opcode);
+    fn _opcode_FX18(&mut self, register: usize) {
         self.timer_sound = self.registers[register];
     }
 
     // Add the value in VX to register I
     #[inline]
-    fn _opcode_FX1E(&mut self, opcode: u16) {
-        let register = reg_x!(opcode);
+    fn _opcode_FX1E(&mut self, register: usize) {
         self.index = self.index.wrapping_add(self.registers[register] as u16);
     }
 
     // Set I to the memory address of the sprite data corresponding to VX
     #[inline]
-    fn _opcode_FX29(&mut self, opcode: u16) {
-        let register = reg_x!(opcode);
+    fn _opcode_FX29(&mut self, register: usize) {
         let digit = self.registers[register];
         self.index = digit as u16 * SIZE_OF_SPRITE;
     }
 
+    // Set I to the memory address of the big sprite data corresponding to VX
+    #[inline]
+    fn _opcode_FX30(&mut self, register: usize) {
+        let digit = self.registers[register] & 0xF;
+        self.index = BIG_FONT_START + digit as u16 * SIZE_OF_BIG_SPRITE;
+    }
+
     // Store the binary-coded decimal equivalent of the value stored in VX at addresses:
     // I, I + 1 and I + 2
     #[inline]
-    fn _opcode_FX33(&mut self, opcode: u16) {
-        let register = reg_x!(opcode);
+    fn _opcode_FX33(&mut self, register: usize) -> Result<(), Chip8ErrorKind> {
         let value = self.registers[register];
 
-        // Bounds checking for debugging
-        // Even though rust would panic anyways, this is nicer for debugging
-        if self.index > (MAX_ADDRESS - 2) {
-            panic!(
-                "Opcode FX33 ({:04X}): Not enough memory left! Index would write out-of-bound.",
-                self.pc
-            );
-        }
+        // Bounds checking
+        let start = self.check_range(AccessKind::Write, self.index, 3)?;
 
-        for i in 0..3 {
-            // TODO: Finish opcode FX33 implementation
-            unimplemented!();
-        }
+        self.memory[start] = value / 100;
+        self.memory[start + 1] = (value / 10) % 10;
+        self.memory[start + 2] = value % 10;
+
+        Ok(())
     }
 
-    //TODO: Finish last opcode FX65
+    // Store the values of registers V0 to VX inclusive in memory starting at address I
     #[inline]
-    fn _opcode_FX65(&mut self, opcode: u16) {
-        unimplemented!();
-    }
+    fn _opcode_FX55(&mut self, register: usize) -> Result<(), Chip8ErrorKind> {
+        // Bounds checking
+        let start = self.check_range(AccessKind::Write, self.index, register + 1)?;
 
-    //TODO: Integrate push and pop functions into code
-    // Helper function to push things on the stack with bounds-checking
-    fn stack_push(&mut self, address: u16) {
-        // Check bounds
-        if self.sp as usize >= self.stack.len() {
-            panic!("Stack overflow");
-        }
+        self.memory[start..=start + register].copy_from_slice(&self.registers[..=register]);
+        self.increment_index(register);
 
-        self.stack[self.sp as usize] = address;
-        self.sp += 1;
+        Ok(())
     }
 
-    // Helper function to pop things from the stack with bounds-checking
-    fn stack_pop(&mut self) -> u16 {
-        // Check bounds
-        self.sp = self.sp.checked_sub(1).expect("Stack underflow");
+    // Fill registers V0 to VX inclusive with the values stored in memory starting at address I
+    #[inline]
+    fn _opcode_FX65(&mut self, register: usize) -> Result<(), Chip8ErrorKind> {
+        // Bounds checking
+        let start = self.check_range(AccessKind::Read, self.index, register + 1)?;
 
-        return self.stack[self.sp as usize];
+        self.registers[..=register].copy_from_slice(&self.memory[start..=start + register]);
+        self.increment_index(register);
+
+        Ok(())
     }
-}
 
-// ===========================
-// Unit tests
-// ===========================
+    // Store the values of registers V0 to VX inclusive in the RPL user flags
+    #[inline]
+    fn _opcode_FX75(&mut self, register: usize) {
+        self.rpl[..=register].copy_from_slice(&self.registers[..=register]);
+    }
 
-// Opcode tests
-#[cfg(test)]
-mod opcode_tests {
-    use super::*;
+    // Fill registers V0 to VX inclusive with the values of the RPL user flags
+    #[inline]
+    fn _opcode_FX85(&mut self, register: usize) {
+        self.registers[..=register].copy_from_slice(&self.rpl[..=register]);
+    }
 
-    // Macro to shadow prelude with pretty_assertions
+    // Helper function to move the selected planes of the display
+    // source maps a pixel position to the index it takes its value from, None clears the pixel
+    fn scroll<F: Fn(usize, usize) -> Option<usize>>(&mut self, source: F) {
+        let (width, height) = (self.display_width(), self.display_height());
+        let planes = self.planes;
+        let before = self.graphics;
+
+        for y in 0..height {
+            for x in 0..width {
+                let moved = source(x, y).map_or(0, |index| before[index]);
+                let pixel = &mut self.graphics[y * width + x];
+                *pixel = (*pixel & !planes) | (moved & planes);
+            }
+        }
+    }
+
+    // Helper function to skip the next instruction, which is 4 bytes long for F000 NNNN
+    fn skip_instruction(&mut self) {
+        let size = self
+            .instruction_at(self.pc)
+            .map_or(2, |instruction| instruction.size());
+        self.pc = self.pc.wrapping_add(size);
+    }
+
+    // Helper function to bounds-check an access of len bytes starting at address and record it
+    fn check_range(
+        &mut self,
+        kind: AccessKind,
+        address: u16,
+        len: usize,
+    ) -> Result<usize, Chip8ErrorKind> {
+        let start = address as usize;
+        if start + len > self.memory.len() {
+            return Err(Chip8ErrorKind::MemoryOutOfBounds(address));
+        }
+
+        self.record_access(kind, address, len);
+        Ok(start)
+    }
+
+    // Helper function to remember an access for debuggers, empty ranges touch no memory
+    fn record_access(&mut self, kind: AccessKind, address: u16, len: usize) {
+        if self.trace_accesses && len > 0 {
+            self.accesses.push(MemoryAccess { kind, address, len });
+        }
+    }
+
+    // Helper function to advance I after FX55 and FX65 according to quirks
+    fn increment_index(&mut self, register: usize) {
+        match self.quirks.index_increment {
+            IndexIncrement::None => {}
+            IndexIncrement::ByX => self.index = self.index.wrapping_add(register as u16),
+            IndexIncrement::ByXPlusOne => self.index = self.index.wrapping_add(register as u16 + 1),
+        }
+    }
+
+    // Helper function to push things on the stack with bounds-checking
+    fn stack_push(&mut self, address: u16) -> Result<(), Chip8ErrorKind> {
+        // Check bounds
+        if self.sp as usize >= self.stack.len() {
+            return Err(Chip8ErrorKind::StackOverflow);
+        }
+
+        self.stack[self.sp as usize] = address;
+        self.sp += 1;
+
+        Ok(())
+    }
+
+    // Helper function to pop things from the stack with bounds-checking
+    fn stack_pop(&mut self) -> Result<u16, Chip8ErrorKind> {
+        // Check bounds
+        self.sp = self
+            .sp
+            .checked_sub(1)
+            .ok_or(Chip8ErrorKind::StackUnderflow)?;
+
+        Ok(self.stack[self.sp as usize])
+    }
+}
+
+// Registers X to Y, counting down if X > Y
+fn register_range(x: usize, y: usize) -> impl ExactSizeIterator<Item = usize> {
+    let count = x.abs_diff(y) + 1;
+
+    (0..count).map(move |offset| if x <= y { x + offset } else { x - offset })
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+// Opcode tests
+#[cfg(test)]
+mod opcode_tests {
+    use super::*;
+
+    // Macro to shadow prelude with pretty_assertions
     //TODO: Find better solution since due to the memory array, things can get very messy
     macro_rules! assert_eq {
         ($($tt:tt)*) => {
@@ -632,7 +1424,17 @@ mod opcode_tests {
                 && self.stack == other.stack
                 && self.sp == other.sp
                 && self.graphics == other.graphics
+                && self.hires == other.hires
+                && self.planes == other.planes
                 && self.keypad == other.keypad
+                && self.beeping == other.beeping
+                && self.audio_pattern == other.audio_pattern
+                && self.pitch == other.pitch
+                && self.rpl == other.rpl
+                && self.machine_calls == other.machine_calls
+                && self.cpu_state == other.cpu_state
+                && self.platform == other.platform
+                && self.quirks == other.quirks
         }
     }
 
@@ -642,10 +1444,11 @@ mod opcode_tests {
         load_opcode(0x0000, &mut chip);
 
         let mut expected: Chip8 = chip.clone();
-        chip.emulateCycle();
+        chip.emulateCycle().unwrap();
 
-        // Only pc should have changed
+        // Only pc and the call counter should have changed
         expected.pc += 2;
+        expected.machine_calls = 1;
         assert_eq!(expected, chip);
     }
 
@@ -663,7 +1466,7 @@ mod opcode_tests {
         expected.graphics.fill(0);
 
         // Run cycle
-        chip.emulateCycle();
+        chip.emulateCycle().unwrap();
 
         // Asserts
         assert_eq!(expected, chip);
@@ -687,14 +1490,13 @@ mod opcode_tests {
             expected.pc = 0x300; // Jump to return-address
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
         }
 
         #[test]
-        #[should_panic]
         fn test_00EE_underflow() {
             let mut chip = Chip8::new();
             load_opcode(0x00EE, &mut chip);
@@ -702,8 +1504,22 @@ mod opcode_tests {
             // Prepare setup
             chip.sp = 0;
 
-            // Run cycle -> should cause a panic
-            chip.emulateCycle();
+            // State must stay untouched, pc points at the faulting instruction
+            let expected = chip.clone();
+
+            // Run cycle -> should fault
+            let result = chip.emulateCycle();
+
+            // Assert
+            assert_eq!(
+                Err(Chip8Error {
+                    pc: 0x200,
+                    opcode: 0x00EE,
+                    kind: Chip8ErrorKind::StackUnderflow,
+                }),
+                result
+            );
+            assert_eq!(expected, chip);
         }
     }
 
@@ -717,7 +1533,7 @@ mod opcode_tests {
         expected.pc = 0x300;
 
         // Run cycle
-        chip.emulateCycle();
+        chip.emulateCycle().unwrap();
 
         // Assert
         assert_eq!(expected, chip);
@@ -739,14 +1555,13 @@ mod opcode_tests {
             expected.sp = 1;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
         }
 
         #[test]
-        #[should_panic]
         fn test_2NNN_overflow() {
             let mut chip = Chip8::new();
             load_opcode(0x2300, &mut chip);
@@ -754,8 +1569,22 @@ mod opcode_tests {
             // Prepare setup
             chip.sp = chip.stack.len() as u16;
 
-            // Run cycle -> should panic
-            chip.emulateCycle();
+            // State must stay untouched, pc points at the faulting instruction
+            let expected = chip.clone();
+
+            // Run cycle -> should fault
+            let result = chip.emulateCycle();
+
+            // Assert
+            assert_eq!(
+                Err(Chip8Error {
+                    pc: 0x200,
+                    opcode: 0x2300,
+                    kind: Chip8ErrorKind::StackOverflow,
+                }),
+                result
+            );
+            assert_eq!(expected, chip);
         }
     }
 
@@ -772,7 +1601,7 @@ mod opcode_tests {
             expected.pc += 4;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -788,7 +1617,7 @@ mod opcode_tests {
             expected.pc += 2;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -808,7 +1637,7 @@ mod opcode_tests {
             expected.pc += 4;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -824,7 +1653,7 @@ mod opcode_tests {
             expected.pc += 2;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -844,7 +1673,7 @@ mod opcode_tests {
             expected.pc += 4;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -862,7 +1691,7 @@ mod opcode_tests {
             expected.pc += 2;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -880,7 +1709,7 @@ mod opcode_tests {
         expected.registers[0] = 0x22;
 
         // Run cycle
-        chip.emulateCycle();
+        chip.emulateCycle().unwrap();
 
         // Assert
         assert_eq!(expected, chip);
@@ -902,7 +1731,7 @@ mod opcode_tests {
             expected.registers[0] += 0x22;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -921,7 +1750,7 @@ mod opcode_tests {
             expected.registers[0] = 0;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -941,7 +1770,7 @@ mod opcode_tests {
         expected.registers[0] = expected.registers[1];
 
         // Run cycle
-        chip.emulateCycle();
+        chip.emulateCycle().unwrap();
 
         // Assert
         assert_eq!(expected, chip);
@@ -969,7 +1798,7 @@ mod opcode_tests {
             expected.registers[0] = res;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -998,7 +1827,7 @@ mod opcode_tests {
             expected.registers[0] = res;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -1027,7 +1856,7 @@ mod opcode_tests {
             expected.registers[0] = res;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -1056,7 +1885,7 @@ mod opcode_tests {
             expected.registers[REG_VF] = vf;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -1066,9 +1895,10 @@ mod opcode_tests {
     #[test]
     fn test_8XY5() {
         let cases = [
-            (0x00, 0x00, 0x00, 0x00),
-            (0x01, 0x01, 0x00, 0x00),
-            (0x00, 0x01, 0xFF, 0x01),
+            (0x00, 0x00, 0x00, 0x01),
+            (0x01, 0x01, 0x00, 0x01),
+            (0x02, 0x01, 0x01, 0x01),
+            (0x00, 0x01, 0xFF, 0x00),
         ];
 
         for (vx, vy, res, vf) in cases {
@@ -1085,7 +1915,7 @@ mod opcode_tests {
             expected.registers[REG_VF] = vf;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -1096,12 +1926,13 @@ mod opcode_tests {
     fn test_8XY6() {
         let cases = [(0b1, 0b0, 0b1), (0b10, 0b01, 0b0)];
 
-        for (vy, res, vf) in cases {
+        for (vx, res, vf) in cases {
             let mut chip = Chip8::new();
             load_opcode(0x8016, &mut chip);
 
             // Prepare setup
-            chip.registers[1] = vy;
+            chip.registers[0] = vx;
+            chip.registers[1] = 0xFF; // VY is ignored
 
             let mut expected = chip.clone();
             expected.pc += 2;
@@ -1109,7 +1940,7 @@ mod opcode_tests {
             expected.registers[REG_VF] = vf;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -1119,9 +1950,10 @@ mod opcode_tests {
     #[test]
     fn test_8XY7() {
         let cases = [
-            (0x00, 0x00, 0x00, 0x00),
-            (0x01, 0x01, 0x00, 0x00),
-            (0x01, 0x00, 0xFF, 0x01),
+            (0x00, 0x00, 0x00, 0x01),
+            (0x01, 0x01, 0x00, 0x01),
+            (0x01, 0x02, 0x01, 0x01),
+            (0x01, 0x00, 0xFF, 0x00),
         ];
 
         for (vx, vy, res, vf) in cases {
@@ -1138,7 +1970,7 @@ mod opcode_tests {
             expected.registers[REG_VF] = vf;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -1149,12 +1981,13 @@ mod opcode_tests {
     fn test_8XYE() {
         let cases = [(0x0, 0x0, 0x0), (0xFF, 0xFE, 0x1)];
 
-        for (vy, res, vf) in cases {
+        for (vx, res, vf) in cases {
             let mut chip = Chip8::new();
             load_opcode(0x801E, &mut chip);
 
             // Prepare setup
-            chip.registers[1] = vy;
+            chip.registers[0] = vx;
+            chip.registers[1] = 0x01; // VY is ignored
 
             let mut expected = chip.clone();
             expected.pc += 2;
@@ -1162,7 +1995,7 @@ mod opcode_tests {
             expected.registers[REG_VF] = vf;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
 
             // Assert
             assert_eq!(expected, chip);
@@ -1185,10 +2018,1859 @@ mod opcode_tests {
             expected.pc += pc;
 
             // Run cycle
-            chip.emulateCycle();
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+    }
+
+    mod test_DXYN {
+        use super::*;
+
+        // Helper function to get the index of a pixel in the framebuffer
+        fn pixel(x: usize, y: usize) -> usize {
+            y * DISPLAY_WIDTH + x
+        }
+
+        #[test]
+        fn test_DXYN_draw() {
+            let mut chip = Chip8::new();
+            load_opcode(0xD012, &mut chip);
+
+            // Prepare setup
+            chip.index = 0x300;
+            chip.memory[0x300] = 0b1100_0000;
+            chip.memory[0x301] = 0b0000_0001;
+            chip.registers[0] = 4;
+            chip.registers[1] = 2;
+
+            let mut expected = chip.clone();
+            expected.pc += 2;
+            expected.graphics[pixel(4, 2)] = 1;
+            expected.graphics[pixel(5, 2)] = 1;
+            expected.graphics[pixel(11, 3)] = 1;
+            expected.registers[REG_VF] = 0;
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_DXYN_collision() {
+            let mut chip = Chip8::new();
+            load_opcode(0xD011, &mut chip);
+
+            // Prepare setup
+            chip.index = 0x300;
+            chip.memory[0x300] = 0b1010_0000;
+            chip.graphics[pixel(0, 0)] = 1;
+            chip.graphics[pixel(1, 0)] = 1;
+
+            let mut expected = chip.clone();
+            expected.pc += 2;
+            expected.graphics[pixel(0, 0)] = 0; // Set pixel gets cleared
+            expected.graphics[pixel(1, 0)] = 1; // Unaffected by a zero bit
+            expected.graphics[pixel(2, 0)] = 1;
+            expected.registers[REG_VF] = 1;
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_DXYN_no_collision_resets_vf() {
+            let mut chip = Chip8::new();
+            load_opcode(0xD011, &mut chip);
+
+            // Prepare setup
+            chip.index = 0x300;
+            chip.memory[0x300] = 0b1000_0000;
+            chip.registers[REG_VF] = 1;
+
+            let mut expected = chip.clone();
+            expected.pc += 2;
+            expected.graphics[pixel(0, 0)] = 1;
+            expected.registers[REG_VF] = 0;
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_DXYN_clipping() {
+            let mut chip = Chip8::new();
+            load_opcode(0xD012, &mut chip);
+
+            // Prepare setup
+            chip.index = 0x300;
+            chip.memory[0x300] = 0xFF;
+            chip.memory[0x301] = 0xFF;
+            chip.registers[0] = (DISPLAY_WIDTH - 2) as u8;
+            chip.registers[1] = (DISPLAY_HEIGHT - 1) as u8;
+
+            // Only the two pixels in the bottom right corner are visible
+            let mut expected = chip.clone();
+            expected.pc += 2;
+            expected.graphics[pixel(DISPLAY_WIDTH - 2, DISPLAY_HEIGHT - 1)] = 1;
+            expected.graphics[pixel(DISPLAY_WIDTH - 1, DISPLAY_HEIGHT - 1)] = 1;
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_DXYN_wrapping() {
+            let mut chip = Chip8::new();
+            load_opcode(0xD011, &mut chip);
+
+            // Prepare setup
+            chip.index = 0x300;
+            chip.memory[0x300] = 0b1000_0000;
+            chip.registers[0] = (DISPLAY_WIDTH + 3) as u8;
+            chip.registers[1] = (DISPLAY_HEIGHT + 5) as u8;
+
+            // Starting position wraps around the screen
+            let mut expected = chip.clone();
+            expected.pc += 2;
+            expected.graphics[pixel(3, 5)] = 1;
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_DXYN_out_of_bounds() {
+            let mut chip = Chip8::new();
+            load_opcode(0xD01F, &mut chip);
+
+            // Prepare setup
+            chip.index = MAX_ADDRESS - 2;
+
+            // State must stay untouched, pc points at the faulting instruction
+            let expected = chip.clone();
+
+            // Run cycle -> should fault
+            let result = chip.emulateCycle();
+
+            // Assert
+            assert_eq!(
+                Err(Chip8Error {
+                    pc: 0x200,
+                    opcode: 0xD01F,
+                    kind: Chip8ErrorKind::MemoryOutOfBounds(MAX_ADDRESS - 2),
+                }),
+                result
+            );
+            assert_eq!(expected, chip);
+        }
+    }
+
+    mod test_FX33 {
+        use super::*;
+
+        #[test]
+        fn test_FX33_normal() {
+            let cases = [
+                (0, [0, 0, 0]),
+                (7, [0, 0, 7]),
+                (42, [0, 4, 2]),
+                (255, [2, 5, 5]),
+            ];
+
+            for (vx, bcd) in cases {
+                let mut chip = Chip8::new();
+                load_opcode(0xF333, &mut chip);
+
+                // Prepare setup
+                chip.index = 0x300;
+                chip.registers[3] = vx;
+
+                let mut expected = chip.clone();
+                expected.pc += 2;
+                expected.memory[0x300..0x303].copy_from_slice(&bcd);
+
+                // Run cycle
+                chip.emulateCycle().unwrap();
+
+                // Assert
+                assert_eq!(expected, chip);
+            }
+        }
+
+        #[test]
+        fn test_FX33_out_of_bounds() {
+            let mut chip = Chip8::new();
+            load_opcode(0xF033, &mut chip);
+
+            // Prepare setup
+            chip.index = MAX_ADDRESS - 1;
+
+            // State must stay untouched, pc points at the faulting instruction
+            let expected = chip.clone();
+
+            // Run cycle -> should fault
+            let result = chip.emulateCycle();
+
+            // Assert
+            assert_eq!(
+                Err(Chip8Error {
+                    pc: 0x200,
+                    opcode: 0xF033,
+                    kind: Chip8ErrorKind::MemoryOutOfBounds(MAX_ADDRESS - 1),
+                }),
+                result
+            );
+            assert_eq!(expected, chip);
+        }
+    }
+
+    mod test_FX55 {
+        use super::*;
+
+        #[test]
+        fn test_FX55_normal() {
+            let mut chip = Chip8::new();
+            load_opcode(0xF255, &mut chip);
+
+            // Prepare setup
+            chip.index = 0x300;
+            chip.registers[0] = 0x11;
+            chip.registers[1] = 0x22;
+            chip.registers[2] = 0x33;
+            chip.registers[3] = 0x44;
+
+            // V3 must not be stored
+            let mut expected = chip.clone();
+            expected.pc += 2;
+            expected.memory[0x300..0x303].copy_from_slice(&[0x11, 0x22, 0x33]);
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_FX55_last_address() {
+            let mut chip = Chip8::new();
+            load_opcode(0xF155, &mut chip);
+
+            // Prepare setup
+            chip.index = MAX_ADDRESS - 1;
+            chip.registers[0] = 0xAB;
+            chip.registers[1] = 0xCD;
+
+            let mut expected = chip.clone();
+            expected.pc += 2;
+            expected.memory[MAX_ADDRESS as usize - 1] = 0xAB;
+            expected.memory[MAX_ADDRESS as usize] = 0xCD;
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_FX55_out_of_bounds() {
+            let mut chip = Chip8::new();
+            load_opcode(0xF255, &mut chip);
+
+            // Prepare setup
+            chip.index = MAX_ADDRESS - 1;
+
+            // State must stay untouched, pc points at the faulting instruction
+            let expected = chip.clone();
+
+            // Run cycle -> should fault
+            let result = chip.emulateCycle();
+
+            // Assert
+            assert_eq!(
+                Err(Chip8Error {
+                    pc: 0x200,
+                    opcode: 0xF255,
+                    kind: Chip8ErrorKind::MemoryOutOfBounds(MAX_ADDRESS - 1),
+                }),
+                result
+            );
+            assert_eq!(expected, chip);
+        }
+    }
+
+    mod test_FX65 {
+        use super::*;
+
+        #[test]
+        fn test_FX65_normal() {
+            let mut chip = Chip8::new();
+            load_opcode(0xF265, &mut chip);
+
+            // Prepare setup
+            chip.index = 0x300;
+            chip.memory[0x300..0x304].copy_from_slice(&[0x11, 0x22, 0x33, 0x44]);
+
+            // V3 must not be loaded
+            let mut expected = chip.clone();
+            expected.pc += 2;
+            expected.registers[0] = 0x11;
+            expected.registers[1] = 0x22;
+            expected.registers[2] = 0x33;
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_FX65_out_of_bounds() {
+            let mut chip = Chip8::new();
+            load_opcode(0xFF65, &mut chip);
+
+            // Prepare setup
+            chip.index = MAX_ADDRESS - 0xE;
+
+            // State must stay untouched, pc points at the faulting instruction
+            let expected = chip.clone();
+
+            // Run cycle -> should fault
+            let result = chip.emulateCycle();
 
             // Assert
+            assert_eq!(
+                Err(Chip8Error {
+                    pc: 0x200,
+                    opcode: 0xFF65,
+                    kind: Chip8ErrorKind::MemoryOutOfBounds(MAX_ADDRESS - 0xE),
+                }),
+                result
+            );
             assert_eq!(expected, chip);
         }
     }
+
+    // Conformance suite making sure every opcode pattern ends up in the right handler
+    mod test_dispatch {
+        use super::*;
+
+        // (opcode, setup of the chip, changes expected on top of pc += 2)
+        type Case = (u16, fn(&mut Chip8), fn(&mut Chip8));
+
+        #[test]
+        fn test_dispatch_table() {
+            let cases: [Case; 35] = [
+                (0x00E0, |c| c.graphics.fill(1), |e| e.graphics.fill(0)),
+                (
+                    0x00EE,
+                    |c| {
+                        c.stack[0] = 0x300;
+                        c.sp = 1;
+                    },
+                    |e| {
+                        e.sp = 0;
+                        e.pc = 0x300;
+                    },
+                ),
+                (0x0123, |_| {}, |e| e.machine_calls = 1),
+                (0x1456, |_| {}, |e| e.pc = 0x456),
+                (
+                    0x2456,
+                    |_| {},
+                    |e| {
+                        e.stack[0] = 0x202;
+                        e.sp = 1;
+                        e.pc = 0x456;
+                    },
+                ),
+                (0x3142, |c| c.registers[1] = 0x42, |e| e.pc += 2),
+                (0x4142, |c| c.registers[1] = 0x41, |e| e.pc += 2),
+                (
+                    0x5120,
+                    |c| {
+                        c.registers[1] = 7;
+                        c.registers[2] = 7;
+                    },
+                    |e| e.pc += 2,
+                ),
+                (0x6A42, |_| {}, |e| e.registers[0xA] = 0x42),
+                (
+                    0x7A01,
+                    |c| c.registers[0xA] = 0x41,
+                    |e| e.registers[0xA] = 0x42,
+                ),
+                (
+                    0x8AB0,
+                    |c| c.registers[0xB] = 0x42,
+                    |e| e.registers[0xA] = 0x42,
+                ),
+                (
+                    0x8AB1,
+                    |c| {
+                        c.registers[0xA] = 0x0F;
+                        c.registers[0xB] = 0xF0;
+                    },
+                    |e| e.registers[0xA] = 0xFF,
+                ),
+                (
+                    0x8AB2,
+                    |c| {
+                        c.registers[0xA] = 0x3C;
+                        c.registers[0xB] = 0x0F;
+                    },
+                    |e| e.registers[0xA] = 0x0C,
+                ),
+                (
+                    0x8AB3,
+                    |c| {
+                        c.registers[0xA] = 0xFF;
+                        c.registers[0xB] = 0x0F;
+                    },
+                    |e| e.registers[0xA] = 0xF0,
+                ),
+                (
+                    0x8AB4,
+                    |c| {
+                        c.registers[0xA] = 0xFF;
+                        c.registers[0xB] = 0x02;
+                    },
+                    |e| {
+                        e.registers[0xA] = 0x01;
+                        e.registers[REG_VF] = 1;
+                    },
+                ),
+                (
+                    0x8AB5,
+                    |c| {
+                        c.registers[0xA] = 0x05;
+                        c.registers[0xB] = 0x03;
+                    },
+                    |e| {
+                        e.registers[0xA] = 0x02;
+                        e.registers[REG_VF] = 1;
+                    },
+                ),
+                (
+                    0x8AB6,
+                    |c| c.registers[0xA] = 0x05,
+                    |e| {
+                        e.registers[0xA] = 0x02;
+                        e.registers[REG_VF] = 1;
+                    },
+                ),
+                (
+                    0x8AB7,
+                    |c| {
+                        c.registers[0xA] = 0x03;
+                        c.registers[0xB] = 0x05;
+                    },
+                    |e| {
+                        e.registers[0xA] = 0x02;
+                        e.registers[REG_VF] = 1;
+                    },
+                ),
+                (
+                    0x8ABE,
+                    |c| c.registers[0xA] = 0x81,
+                    |e| {
+                        e.registers[0xA] = 0x02;
+                        e.registers[REG_VF] = 1;
+                    },
+                ),
+                (
+                    0x9AB0,
+                    |c| {
+                        c.registers[0xA] = 1;
+                        c.registers[0xB] = 2;
+                    },
+                    |e| e.pc += 2,
+                ),
+                (0xA456, |_| {}, |e| e.index = 0x456),
+                (0xB300, |c| c.registers[0] = 0x10, |e| e.pc = 0x310),
+                (
+                    0xCA00,
+                    |c| c.registers[0xA] = 0xFF,
+                    |e| e.registers[0xA] = 0,
+                ),
+                (
+                    0xDAB1,
+                    |c| {
+                        c.index = 0x300;
+                        c.memory[0x300] = 0x80;
+                    },
+                    |e| e.graphics[0] = 1,
+                ),
+                (
+                    0xEA9E,
+                    |c| {
+                        c.registers[0xA] = 5;
+                        c.keypad[5] = 1;
+                    },
+                    |e| e.pc += 2,
+                ),
+                (0xEAA1, |c| c.registers[0xA] = 5, |e| e.pc += 2),
+                (
+                    0xFA07,
+                    |c| c.timer_delay = 0x42,
+                    |e| e.registers[0xA] = 0x42,
+                ),
+                (
+                    0xFA0A,
+                    |_| {},
+                    |e| e.cpu_state = CpuState::WaitingForPress { register: 0xA },
+                ),
+                (
+                    0xFA15,
+                    |c| c.registers[0xA] = 0x42,
+                    |e| e.timer_delay = 0x42,
+                ),
+                (
+                    0xFA18,
+                    |c| c.registers[0xA] = 0x42,
+                    |e| e.timer_sound = 0x42,
+                ),
+                (
+                    0xFA1E,
+                    |c| {
+                        c.index = 0x100;
+                        c.registers[0xA] = 0x10;
+                    },
+                    |e| e.index = 0x110,
+                ),
+                (
+                    0xFA29,
+                    |c| c.registers[0xA] = 0xA,
+                    |e| e.index = 0xA * SIZE_OF_SPRITE,
+                ),
+                (
+                    0xFA33,
+                    |c| {
+                        c.index = 0x300;
+                        c.registers[0xA] = 123;
+                    },
+                    |e| e.memory[0x300..0x303].copy_from_slice(&[1, 2, 3]),
+                ),
+                (
+                    0xF155,
+                    |c| {
+                        c.index = 0x300;
+                        c.registers[0] = 0x12;
+                        c.registers[1] = 0x34;
+                    },
+                    |e| e.memory[0x300..0x302].copy_from_slice(&[0x12, 0x34]),
+                ),
+                (
+                    0xF165,
+                    |c| {
+                        c.index = 0x300;
+                        c.memory[0x300..0x302].copy_from_slice(&[0x12, 0x34]);
+                    },
+                    |e| {
+                        e.registers[0] = 0x12;
+                        e.registers[1] = 0x34;
+                    },
+                ),
+            ];
+
+            for (opcode, setup, changes) in cases {
+                let mut chip = Chip8::new();
+                load_opcode(opcode, &mut chip);
+
+                // Prepare setup
+                setup(&mut chip);
+
+                let mut expected = chip.clone();
+                expected.pc += 2;
+                changes(&mut expected);
+
+                // Run cycle
+                chip.emulateCycle().unwrap();
+
+                // Assert
+                assert_eq!(expected, chip, "opcode {:04X}", opcode);
+            }
+        }
+
+        #[test]
+        fn test_dispatch_unknown() {
+            let opcodes = [
+                0x5121, 0x8AB8, 0x8ABF, 0x9AB1, 0xE000, 0xEAFF, 0xF000, 0xFAFF,
+            ];
+
+            for opcode in opcodes {
+                let mut chip = Chip8::new();
+                load_opcode(opcode, &mut chip);
+
+                // Run cycle -> should fault
+                let result = chip.emulateCycle();
+
+                // Assert
+                assert_eq!(
+                    Err(Chip8Error {
+                        pc: 0x200,
+                        opcode,
+                        kind: Chip8ErrorKind::UnknownOpcode,
+                    }),
+                    result
+                );
+            }
+        }
+    }
+
+    mod test_FX0A {
+        use super::*;
+
+        #[test]
+        fn test_FX0A_waits_without_fetching() {
+            let mut chip = Chip8::new();
+
+            // FX0A followed by an unknown opcode that must never be fetched
+            chip.init(&[0xF3, 0x0A, 0xFF, 0xFF]);
+
+            // Run cycles
+            assert_eq!(Ok(StepOutcome::WaitingForKey), chip.emulateCycle());
+            assert_eq!(Ok(StepOutcome::WaitingForKey), chip.emulateCycle());
+            assert_eq!(Ok(StepOutcome::WaitingForKey), chip.emulateCycle());
+
+            // Assert
+            assert!(chip.is_waiting_for_key());
+            assert_eq!(0x202, chip.pc);
+        }
+
+        #[test]
+        fn test_FX0A_press_then_release() {
+            let mut chip = Chip8::new();
+            load_opcode(0xF30A, &mut chip);
+            chip.emulateCycle().unwrap();
+
+            // Pressing alone does not finish the instruction
+            chip.press_key(0xB);
+            assert_eq!(Ok(StepOutcome::WaitingForKey), chip.emulateCycle());
+            assert_eq!(Ok(StepOutcome::WaitingForKey), chip.emulateCycle());
+            assert_eq!(0, chip.registers[3]);
+
+            // Releasing the key stores its number
+            chip.release_key(0xB);
+            assert_eq!(Ok(StepOutcome::Executed), chip.emulateCycle());
+
+            // Assert
+            assert!(!chip.is_waiting_for_key());
+            assert_eq!(0xB, chip.registers[3]);
+            assert_eq!(0x202, chip.pc);
+        }
+
+        #[test]
+        fn test_FX0A_other_key_released() {
+            let mut chip = Chip8::new();
+            load_opcode(0xF30A, &mut chip);
+            chip.emulateCycle().unwrap();
+
+            // Key 2 gets pressed first, releasing another key does not count
+            chip.press_key(0x2);
+            chip.emulateCycle().unwrap();
+            chip.press_key(0x5);
+            chip.release_key(0x5);
+            assert_eq!(Ok(StepOutcome::WaitingForKey), chip.emulateCycle());
+
+            chip.release_key(0x2);
+            assert_eq!(Ok(StepOutcome::Executed), chip.emulateCycle());
+
+            // Assert
+            assert_eq!(0x2, chip.registers[3]);
+        }
+
+        #[test]
+        fn test_FX0A_timers_keep_running() {
+            let mut chip = Chip8::new();
+            load_opcode(0xF00A, &mut chip);
+
+            // Prepare setup
+            chip.timer_delay = 5;
+
+            // Run frames while waiting
+            chip.run_frame(10).unwrap();
+            chip.run_frame(10).unwrap();
+
+            // Assert
+            assert!(chip.is_waiting_for_key());
+            assert_eq!(3, chip.timer_delay);
+        }
+    }
+
+    mod test_quirks {
+        use super::*;
+
+        // Helper function to load a single opcode into a chip with the given quirks
+        fn quirky_chip(opcode: u16, quirks: Quirks) -> Chip8 {
+            let mut chip = Chip8::with_quirks(quirks);
+            load_opcode(opcode, &mut chip);
+            chip
+        }
+
+        #[test]
+        fn test_vf_reset() {
+            for (opcode, vf) in [(0x8011, 0), (0x8012, 0), (0x8013, 0), (0x8014, 1)] {
+                let quirks = Quirks {
+                    vf_reset: true,
+                    ..Quirks::default()
+                };
+                let mut chip = quirky_chip(opcode, quirks);
+
+                // Prepare setup
+                chip.registers[0] = 0xFF;
+                chip.registers[1] = 0x01;
+                chip.registers[REG_VF] = 0x42;
+
+                // Run cycle
+                chip.emulateCycle().unwrap();
+
+                // Assert
+                assert_eq!(vf, chip.registers[REG_VF], "opcode {:04X}", opcode);
+            }
+        }
+
+        #[test]
+        fn test_shift_uses_vy() {
+            let cases = [
+                (0x8016, 0b0000_0011, 0b0000_0001, 0x1),
+                (0x801E, 0b1000_0010, 0b0000_0100, 0x1),
+            ];
+
+            for (opcode, vy, res, vf) in cases {
+                let quirks = Quirks {
+                    shift_uses_vy: true,
+                    ..Quirks::default()
+                };
+                let mut chip = quirky_chip(opcode, quirks);
+
+                // Prepare setup
+                chip.registers[0] = 0xFF;
+                chip.registers[1] = vy;
+
+                let mut expected = chip.clone();
+                expected.pc += 2;
+                expected.registers[0] = res;
+                expected.registers[REG_VF] = vf;
+
+                // Run cycle
+                chip.emulateCycle().unwrap();
+
+                // Assert
+                assert_eq!(expected, chip, "opcode {:04X}", opcode);
+            }
+        }
+
+        #[test]
+        fn test_index_increment() {
+            let cases = [
+                (IndexIncrement::None, 0x300),
+                (IndexIncrement::ByX, 0x302),
+                (IndexIncrement::ByXPlusOne, 0x303),
+            ];
+
+            for opcode in [0xF255, 0xF265] {
+                for (index_increment, index) in cases {
+                    let quirks = Quirks {
+                        index_increment,
+                        ..Quirks::default()
+                    };
+                    let mut chip = quirky_chip(opcode, quirks);
+
+                    // Prepare setup
+                    chip.index = 0x300;
+
+                    // Run cycle
+                    chip.emulateCycle().unwrap();
+
+                    // Assert
+                    assert_eq!(index, chip.index, "{:?}", index_increment);
+                }
+            }
+        }
+
+        #[test]
+        fn test_jump_uses_vx() {
+            let quirks = Quirks {
+                jump_uses_vx: true,
+                ..Quirks::default()
+            };
+            let mut chip = quirky_chip(0xB320, quirks);
+
+            // Prepare setup
+            chip.registers[0] = 0x01;
+            chip.registers[3] = 0x10;
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(0x330, chip.pc);
+        }
+
+        #[test]
+        fn test_wrap_sprites() {
+            let quirks = Quirks {
+                wrap_sprites: true,
+                ..Quirks::default()
+            };
+            let mut chip = quirky_chip(0xD012, quirks);
+
+            // Prepare setup
+            chip.index = 0x300;
+            chip.memory[0x300] = 0b1100_0000;
+            chip.memory[0x301] = 0b1100_0000;
+            chip.registers[0] = (DISPLAY_WIDTH - 1) as u8;
+            chip.registers[1] = (DISPLAY_HEIGHT - 1) as u8;
+
+            // Each corner of the screen gets one pixel
+            let mut expected = chip.clone();
+            expected.pc += 2;
+            for (x, y) in [
+                (DISPLAY_WIDTH - 1, DISPLAY_HEIGHT - 1),
+                (0, DISPLAY_HEIGHT - 1),
+                (DISPLAY_WIDTH - 1, 0),
+                (0, 0),
+            ] {
+                expected.graphics[y * DISPLAY_WIDTH + x] = 1;
+            }
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_presets() {
+            assert_eq!(Quirks::default(), Chip8::new().quirks);
+            assert_eq!(
+                Quirks::cosmac_vip(),
+                Chip8::with_quirks(Quirks::cosmac_vip()).quirks
+            );
+
+            // Presets survive a reset
+            let mut chip = Chip8::with_quirks(Quirks::xo_chip());
+            chip.init(&[]);
+            assert_eq!(Quirks::xo_chip(), chip.quirks);
+        }
+    }
+
+    mod test_CXNN {
+        use super::*;
+        use crate::rng::{VIP_INTERPRETER_SIZE, VipRng};
+
+        // Helper function to run CXNN a couple of times and collect VX
+        fn sample(chip: &mut Chip8, count: usize) -> Vec<u8> {
+            (0..count)
+                .map(|_| {
+                    chip.pc = 0x200;
+                    chip.emulateCycle().unwrap();
+                    chip.registers[0]
+                })
+                .collect()
+        }
+
+        #[test]
+        fn test_CXNN_seeded() {
+            let mut chip = Chip8::new();
+            load_opcode(0xC0FF, &mut chip);
+            chip.set_rng(Box::new(SeededRng::new(0x1234)));
+
+            // Same seed, same numbers, with any version of the crate and its dependencies
+            let expected = vec![0x5F, 0x5A, 0x8A, 0xAD, 0x80, 0x1E, 0x30, 0x82];
+
+            // Assert
+            assert_eq!(expected, sample(&mut chip, 8));
+        }
+
+        #[test]
+        fn test_CXNN_vip() {
+            let mut chip = Chip8::new();
+            load_opcode(0xC0FF, &mut chip);
+
+            // Interpreter image whose code page holds the offset of each byte
+            let mut interpreter = [0; VIP_INTERPRETER_SIZE];
+            for (offset, byte) in interpreter.iter_mut().enumerate() {
+                *byte = offset as u8;
+            }
+            chip.set_rng(Box::new(VipRng::new(&interpreter, 0x10FE)));
+
+            // R9 = 10FF reads FF, 10 + FF = 0F and R9 = 0FFF, then R9 = 1000 reads 00 and so on
+            let expected = vec![0x0F, 0x10, 0x11, 0x13, 0x16, 0x1A, 0x1F, 0x25];
+
+            // Assert
+            assert_eq!(expected, sample(&mut chip, 8));
+        }
+
+        #[test]
+        fn test_CXNN_mask() {
+            let mut chip = Chip8::new();
+            load_opcode(0xC00F, &mut chip);
+            chip.set_rng(Box::new(SeededRng::new(7)));
+
+            let mut reference = SeededRng::new(7);
+            let expected: Vec<u8> = (0..8).map(|_| reference.next_byte() & 0x0F).collect();
+
+            // Assert
+            assert_eq!(expected, sample(&mut chip, 8));
+        }
+
+        #[test]
+        fn test_CXNN_snapshot_replays() {
+            let mut chip = Chip8::new();
+            load_opcode(0xC0FF, &mut chip);
+            chip.set_rng(Box::new(SeededRng::new(42)));
+            sample(&mut chip, 3);
+
+            // A snapshot continues with the same numbers as the original
+            let mut snapshot = chip.clone();
+            assert_eq!(sample(&mut chip, 8), sample(&mut snapshot, 8));
+        }
+
+        #[test]
+        fn test_chip8_is_send() {
+            fn assert_send<T: Send>() {}
+            assert_send::<Chip8>();
+        }
+    }
+
+    mod test_super_chip {
+        use super::*;
+
+        // Helper function to load a single opcode into a SUPER-CHIP
+        fn schip(opcode: u16) -> Chip8 {
+            let mut chip = Chip8::with_config(Config {
+                platform: Platform::SuperChip,
+                quirks: Quirks::super_chip(),
+                seed: Some(0),
+            });
+            load_opcode(opcode, &mut chip);
+            chip
+        }
+
+        // Helper function to set the given pixels in the current resolution
+        fn set_pixels(chip: &mut Chip8, pixels: &[(usize, usize)]) {
+            let width = chip.display_width();
+            for &(x, y) in pixels {
+                chip.graphics[y * width + x] = 1;
+            }
+        }
+
+        #[test]
+        fn test_00FF_00FE() {
+            // Prepare setup
+            let mut chip = schip(0x00FF);
+            chip.memory[0x202..0x204].copy_from_slice(&[0x00, 0xFE]);
+            set_pixels(&mut chip, &[(1, 1)]);
+
+            // Run cycle -> hi-res with a cleared screen
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert!(chip.is_hires());
+            assert_eq!(HIRES_WIDTH * HIRES_HEIGHT, chip.framebuffer().len());
+            assert!(chip.framebuffer().iter().all(|&pixel| pixel == 0));
+
+            // Run cycle -> back to lo-res
+            set_pixels(&mut chip, &[(100, 50)]);
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert!(!chip.is_hires());
+            assert_eq!(DISPLAY_WIDTH * DISPLAY_HEIGHT, chip.framebuffer().len());
+            assert!(chip.framebuffer().iter().all(|&pixel| pixel == 0));
+        }
+
+        #[test]
+        fn test_00CN() {
+            // Prepare setup
+            let mut chip = schip(0x00C2);
+            set_pixels(&mut chip, &[(0, 0), (5, 1), (63, 31)]);
+            let mut expected = chip.clone();
+            expected.pc = 0x202;
+            expected._opcode_00E0();
+            set_pixels(&mut expected, &[(0, 2), (5, 3)]);
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_00FB() {
+            // Prepare setup
+            let mut chip = schip(0x00FB);
+            chip.hires = true;
+            set_pixels(&mut chip, &[(0, 0), (10, 63), (125, 5)]);
+            let mut expected = chip.clone();
+            expected.pc = 0x202;
+            expected._opcode_00E0();
+            set_pixels(&mut expected, &[(4, 0), (14, 63)]);
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_00FC() {
+            // Prepare setup
+            let mut chip = schip(0x00FC);
+            set_pixels(&mut chip, &[(2, 0), (10, 31), (63, 5)]);
+            let mut expected = chip.clone();
+            expected.pc = 0x202;
+            expected._opcode_00E0();
+            set_pixels(&mut expected, &[(6, 31), (59, 5)]);
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_00FD() {
+            // Prepare setup
+            let mut chip = schip(0x00FD);
+            let mut expected = chip.clone();
+            expected.cpu_state = CpuState::Exited;
+
+            // Run cycle -> exits and stays there
+            assert_eq!(Ok(StepOutcome::Exited), chip.emulateCycle());
+            assert_eq!(Ok(StepOutcome::Exited), chip.emulateCycle());
+            chip.run_frame(10).unwrap();
+
+            // Assert
+            assert!(chip.has_exited());
+            assert!(!chip.is_waiting_for_key());
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_DXY0() {
+            // Prepare setup
+            let mut chip = schip(0xD010);
+            chip.hires = true;
+            chip.index = 0x300;
+            chip.registers[0] = 120;
+            chip.registers[1] = 60;
+            // Rows alternate between the left and the right half of the sprite
+            for row in 0..16 {
+                let bytes = if row % 2 == 0 {
+                    [0xFF, 0x00]
+                } else {
+                    [0x00, 0xFF]
+                };
+                chip.memory[0x300 + row * 2..0x302 + row * 2].copy_from_slice(&bytes);
+            }
+            set_pixels(&mut chip, &[(120, 60)]);
+
+            let mut expected = chip.clone();
+            expected.pc = 0x202;
+            expected._opcode_00E0();
+            // Clipped at the right and bottom edge, only the left half of rows 0 and 2 is visible
+            // and the pixel already set gets erased
+            let mut visible: Vec<(usize, usize)> = (120..128).map(|x| (x, 62)).collect();
+            visible.extend((121..128).map(|x| (x, 60)));
+            set_pixels(&mut expected, &visible);
+            expected.registers[REG_VF] = 1;
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_DXY0_out_of_bounds() {
+            // Prepare setup, 32 bytes don't fit behind I
+            let mut chip = schip(0xD010);
+            chip.index = MAX_ADDRESS - 30;
+
+            // Run cycle -> should fault
+            let result = chip.emulateCycle();
+
+            // Assert
+            assert_eq!(
+                Err(Chip8Error {
+                    pc: 0x200,
+                    opcode: 0xD010,
+                    kind: Chip8ErrorKind::MemoryOutOfBounds(MAX_ADDRESS - 30),
+                }),
+                result
+            );
+        }
+
+        #[test]
+        fn test_FX30() {
+            // Prepare setup
+            let mut chip = schip(0xF530);
+            chip.registers[5] = 9;
+            let mut expected = chip.clone();
+            expected.pc = 0x202;
+            expected.index = BIG_FONT_START + 90;
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+            let glyph = &chip.memory[chip.index as usize..chip.index as usize + 10];
+            assert_eq!(&BIG_FONTSET[90..100], glyph);
+        }
+
+        #[test]
+        fn test_FX75_FX85() {
+            // Prepare setup
+            let mut chip = schip(0xF275);
+            chip.memory[0x202..0x204].copy_from_slice(&[0xF3, 0x85]);
+            chip.registers[..4].copy_from_slice(&[1, 2, 3, 4]);
+
+            // Run cycle -> V0-V2 saved
+            chip.emulateCycle().unwrap();
+            assert_eq!([1, 2, 3, 0], chip.rpl_flags()[..4]);
+
+            // Run cycle -> V0-V3 restored
+            chip.registers[..4].fill(0xFF);
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!([1, 2, 3, 0], chip.registers[..4]);
+
+            // Flags survive a reset
+            chip.init(&[]);
+            assert_eq!([1, 2, 3, 0], chip.rpl_flags()[..4]);
+        }
+
+        #[test]
+        fn test_chip8_platform() {
+            // SUPER-CHIP scrolling and mode switches are machine code calls on plain CHIP-8
+            for opcode in [0x00C2, 0x00FB, 0x00FC, 0x00FD, 0x00FF] {
+                let mut chip = Chip8::new();
+                load_opcode(opcode, &mut chip);
+                chip.graphics[0] = 1;
+                let mut expected = chip.clone();
+                expected.pc = 0x202;
+                expected.machine_calls = 1;
+
+                chip.emulateCycle().unwrap();
+                assert_eq!(expected, chip, "opcode {:04X}", opcode);
+            }
+
+            // The other instructions don't exist
+            for opcode in [0xF030, 0xF075, 0xF085] {
+                let mut chip = Chip8::new();
+                load_opcode(opcode, &mut chip);
+
+                let result = chip.emulateCycle().map_err(|err| err.kind);
+                assert_eq!(Err(Chip8ErrorKind::UnknownOpcode), result);
+            }
+
+            // DXY0 draws nothing
+            let mut chip = Chip8::new();
+            load_opcode(0xD000, &mut chip);
+            chip.emulateCycle().unwrap();
+            assert!(chip.framebuffer().iter().all(|&pixel| pixel == 0));
+        }
+    }
+
+    mod test_xo_chip {
+        use super::*;
+
+        // Helper function to load a program into an XO-CHIP
+        fn xo(program: &[u8]) -> Chip8 {
+            let mut chip = Chip8::with_config(Config {
+                platform: Platform::XoChip,
+                quirks: Quirks::xo_chip(),
+                seed: Some(0),
+            });
+            chip.init(program);
+            chip
+        }
+
+        #[test]
+        fn test_memory_size() {
+            let mut chip = xo(&[]);
+
+            // Assert
+            assert_eq!(0x10000, chip.memory().len());
+            assert_eq!(0x10000 - 0x200, chip.max_rom_size());
+            assert_eq!(Ok(()), chip.load_rom(&vec![0xAA; chip.max_rom_size()]));
+            assert!(chip.load_rom(&vec![0xAA; chip.max_rom_size() + 1]).is_err());
+        }
+
+        #[test]
+        fn test_F000() {
+            // Prepare setup
+            let mut chip = xo(&[0xF0, 0x00, 0xAB, 0xCD]);
+            let mut expected = chip.clone();
+            expected.pc = 0x204;
+            expected.index = 0xABCD;
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_skip_over_F000() {
+            // Prepare setup, 3000 skips as V0 is 0
+            let mut chip = xo(&[0x30, 0x00, 0xF0, 0x00, 0xAB, 0xCD]);
+            let mut expected = chip.clone();
+            expected.pc = 0x206;
+
+            // Run cycle
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_5XY2_5XY3() {
+            // Prepare setup, 5132: store V1-V3, 5312: store V3-V1, 5133: load V1-V3
+            let mut chip = xo(&[0x51, 0x32, 0x53, 0x12, 0x51, 0x33]);
+            chip.index = 0x400;
+            chip.registers[1..4].copy_from_slice(&[1, 2, 3]);
+
+            // Run cycles
+            chip.emulateCycle().unwrap();
+            assert_eq!([1, 2, 3], chip.memory[0x400..0x403]);
+            chip.emulateCycle().unwrap();
+            assert_eq!([3, 2, 1], chip.memory[0x400..0x403]);
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!([3, 2, 1], chip.registers[1..4]);
+            assert_eq!(0x400, chip.index);
+        }
+
+        #[test]
+        fn test_5XY2_out_of_bounds() {
+            // Prepare setup
+            let mut chip = xo(&[0x50, 0x22]);
+            chip.index = 0xFFFE;
+
+            // Run cycle -> should fault
+            let result = chip.emulateCycle().map_err(|err| err.kind);
+
+            // Assert
+            assert_eq!(Err(Chip8ErrorKind::MemoryOutOfBounds(0xFFFE)), result);
+        }
+
+        #[test]
+        fn test_high_memory() {
+            // Prepare setup, F000 FFF0: I = 0xFFF0, F155: store V0-V1
+            let mut chip = xo(&[0xF0, 0x00, 0xFF, 0xF0, 0xF1, 0x55]);
+            chip.registers[..2].copy_from_slice(&[0x12, 0x34]);
+
+            // Run cycles
+            chip.emulateCycle().unwrap();
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!([0x12, 0x34], chip.memory[0xFFF0..0xFFF2]);
+            assert_eq!(0xFFF2, chip.index);
+        }
+
+        #[test]
+        fn test_fetch_at_end_of_memory() {
+            // Prepare setup
+            let mut chip = xo(&[]);
+            chip.pc = 0xFFFF;
+
+            // Run cycle -> should fault
+            let result = chip.emulateCycle().map_err(|err| err.kind);
+
+            // Assert
+            assert_eq!(Err(Chip8ErrorKind::MemoryOutOfBounds(0xFFFF)), result);
+        }
+
+        #[test]
+        fn test_FN01_DXYN_planes() {
+            // Prepare setup, F301: select both planes, D001: draw one row per plane
+            let mut chip = xo(&[0xF3, 0x01, 0xD0, 0x01]);
+            chip.index = 0x300;
+            chip.memory[0x300..0x302].copy_from_slice(&[0b1100_0000, 0b1010_0000]);
+            chip.graphics[2] = 0b10;
+
+            // Run cycles
+            chip.emulateCycle().unwrap();
+            assert_eq!(3, chip.selected_planes());
+            chip.emulateCycle().unwrap();
+
+            // Assert: plane 1 gets the first byte, plane 2 the second one
+            assert_eq!([3, 1, 0, 0], chip.framebuffer()[..4]);
+            assert_eq!(1, chip.registers[REG_VF]);
+        }
+
+        #[test]
+        fn test_DXYN_second_plane_only() {
+            // Prepare setup, F201: select plane 2, D001: draw one row
+            let mut chip = xo(&[0xF2, 0x01, 0xD0, 0x01]);
+            chip.index = 0x300;
+            chip.memory[0x300] = 0b1000_0000;
+            chip.graphics[0] = 0b01;
+
+            // Run cycles
+            chip.emulateCycle().unwrap();
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(3, chip.framebuffer()[0]);
+            assert_eq!(0, chip.registers[REG_VF]);
+        }
+
+        #[test]
+        fn test_00E0_selected_planes() {
+            // Prepare setup, F201: select plane 2, 00E0: clear it
+            let mut chip = xo(&[0xF2, 0x01, 0x00, 0xE0]);
+            chip.graphics[..3].copy_from_slice(&[1, 2, 3]);
+
+            // Run cycles
+            chip.emulateCycle().unwrap();
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!([1, 0, 1], chip.framebuffer()[..3]);
+        }
+
+        #[test]
+        fn test_00DN_selected_planes() {
+            // Prepare setup, F101: select plane 1, 00D1: scroll it up one row
+            let mut chip = xo(&[0xF1, 0x01, 0x00, 0xD1]);
+            chip.graphics[DISPLAY_WIDTH] = 3;
+
+            // Run cycles
+            chip.emulateCycle().unwrap();
+            chip.emulateCycle().unwrap();
+
+            // Assert: plane 2 stays where it was
+            assert_eq!(1, chip.framebuffer()[0]);
+            assert_eq!(2, chip.framebuffer()[DISPLAY_WIDTH]);
+        }
+
+        #[test]
+        fn test_F002() {
+            // Prepare setup
+            let mut chip = xo(&[0xF0, 0x02]);
+            chip.index = 0x300;
+            for (offset, byte) in chip.memory[0x300..0x310].iter_mut().enumerate() {
+                *byte = offset as u8;
+            }
+            let mut expected = chip.clone();
+            expected.pc = 0x202;
+            expected.audio_pattern = Some(core::array::from_fn(|offset| offset as u8));
+
+            // Run cycle
+            assert_eq!(None, chip.audio_pattern());
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_F002_out_of_bounds() {
+            // Prepare setup
+            let mut chip = xo(&[0xF0, 0x02]);
+            chip.index = 0xFFF8;
+
+            // Run cycle -> should fault
+            let result = chip.emulateCycle().map_err(|err| err.kind);
+
+            // Assert
+            assert_eq!(Err(Chip8ErrorKind::MemoryOutOfBounds(0xFFF8)), result);
+        }
+
+        #[test]
+        fn test_FX3A() {
+            // Prepare setup
+            let mut chip = xo(&[0xF5, 0x3A]);
+            chip.registers[5] = 112;
+            let mut expected = chip.clone();
+            expected.pc = 0x202;
+            expected.pitch = 112;
+
+            // Run cycle
+            assert_eq!(DEFAULT_PITCH, chip.pitch());
+            chip.emulateCycle().unwrap();
+
+            // Assert
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_super_chip_platform() {
+            // XO-CHIP instructions don't exist on SUPER-CHIP
+            for program in [
+                [0x51, 0x22],
+                [0xF0, 0x00],
+                [0xF1, 0x01],
+                [0xF0, 0x02],
+                [0xF0, 0x3A],
+            ] {
+                let mut chip = Chip8::with_config(Config {
+                    platform: Platform::SuperChip,
+                    ..Config::default()
+                });
+                chip.init(&program);
+
+                let result = chip.emulateCycle().map_err(|err| err.kind);
+                assert_eq!(Err(Chip8ErrorKind::UnknownOpcode), result);
+            }
+        }
+    }
+
+    mod test_faults {
+        use super::*;
+
+        #[test]
+        fn test_BNNN_out_of_bounds() {
+            let mut chip = Chip8::new();
+            load_opcode(0xBFFF, &mut chip);
+
+            // Prepare setup
+            chip.registers[0] = 0x01;
+            let expected = chip.clone();
+
+            // Run cycle -> should fault
+            let result = chip.emulateCycle();
+
+            // Assert
+            assert_eq!(
+                Err(Chip8Error {
+                    pc: 0x200,
+                    opcode: 0xBFFF,
+                    kind: Chip8ErrorKind::MemoryOutOfBounds(0x1000),
+                }),
+                result
+            );
+            assert_eq!(expected, chip);
+        }
+
+        #[test]
+        fn test_EX9E_invalid_key() {
+            for opcode in [0xE09E, 0xE0A1] {
+                let mut chip = Chip8::new();
+                load_opcode(opcode, &mut chip);
+
+                // Prepare setup
+                chip.registers[0] = 0x10;
+                let expected = chip.clone();
+
+                // Run cycle -> should fault
+                let result = chip.emulateCycle();
+
+                // Assert
+                assert_eq!(
+                    Err(Chip8Error {
+                        pc: 0x200,
+                        opcode,
+                        kind: Chip8ErrorKind::InvalidKey(0x10),
+                    }),
+                    result
+                );
+                assert_eq!(expected, chip);
+            }
+        }
+
+        #[test]
+        fn test_fetch_out_of_bounds() {
+            let mut chip = Chip8::new();
+            chip.init(&[]);
+
+            // Prepare setup
+            chip.pc = MAX_ADDRESS;
+
+            // Run cycle -> should fault
+            let result = chip.emulateCycle();
+
+            // Assert
+            assert_eq!(
+                Err(Chip8Error {
+                    pc: MAX_ADDRESS,
+                    opcode: 0,
+                    kind: Chip8ErrorKind::MemoryOutOfBounds(MAX_ADDRESS),
+                }),
+                result
+            );
+            assert_eq!(MAX_ADDRESS, chip.pc);
+        }
+
+        #[test]
+        fn test_recover_after_fault() {
+            let mut chip = Chip8::new();
+            load_opcode(0x00EE, &mut chip);
+
+            // Fault on an empty stack
+            assert!(chip.emulateCycle().is_err());
+
+            // Fix the cause and retry the same instruction
+            chip.stack[0] = 0x300;
+            chip.sp = 1;
+            assert_eq!(Ok(StepOutcome::Executed), chip.emulateCycle());
+            assert_eq!(0x300, chip.pc);
+        }
+    }
+}
+
+// Timer tests
+#[cfg(test)]
+mod timer_tests {
+    use super::*;
+
+    // Macro to shadow prelude with pretty_assertions
+    macro_rules! assert_eq {
+        ($($tt:tt)*) => {
+            pretty_assertions::assert_eq!($($tt)*)
+        };
+    }
+
+    #[test]
+    fn test_tick_timers() {
+        let mut chip = Chip8::new();
+        chip.init(&[]);
+
+        // Prepare setup
+        chip.timer_delay = 2;
+        chip.timer_sound = 0;
+
+        // Run ticks
+        chip.tick_timers();
+        assert_eq!(1, chip.timer_delay);
+
+        chip.tick_timers();
+        assert_eq!(0, chip.timer_delay);
+
+        // Timers stop at zero
+        chip.tick_timers();
+        assert_eq!(0, chip.timer_delay);
+        assert_eq!(0, chip.timer_sound);
+    }
+
+    #[test]
+    fn test_sound_events() {
+        let mut chip = Chip8::new();
+        chip.init(&[]);
+
+        // Silent while the sound timer is zero
+        assert_eq!(None, chip.tick_timers());
+        assert!(!chip.is_beeping());
+
+        // Sounds for as many ticks as the sound timer was set to
+        chip.timer_sound = 2;
+        assert_eq!(Some(SoundEvent::Started), chip.tick_timers());
+        assert!(chip.is_beeping());
+        assert_eq!(None, chip.tick_timers());
+        assert!(chip.is_beeping());
+        assert_eq!(Some(SoundEvent::Stopped), chip.tick_timers());
+        assert!(!chip.is_beeping());
+    }
+
+    #[test]
+    fn test_run_frame_ticks_once() {
+        let mut chip = Chip8::new();
+
+        // 7001 repeated: add one to V0 per instruction
+        let program = [0x70, 0x01].repeat(20);
+        chip.init(&program);
+
+        // Prepare setup
+        chip.timer_delay = 10;
+        chip.timer_sound = 10;
+
+        // Run frame
+        let event = chip.run_frame(15).unwrap();
+
+        // Assert
+        assert_eq!(15, chip.registers[0]);
+        assert_eq!(0x200 + 2 * 15, chip.pc);
+        assert_eq!(9, chip.timer_delay);
+        assert_eq!(9, chip.timer_sound);
+        assert_eq!(Some(SoundEvent::Started), event);
+    }
+
+    #[test]
+    fn test_run_frame_sound_set_during_frame() {
+        let mut chip = Chip8::new();
+
+        // 6001: V0 = 1, F018: sound timer = V0
+        chip.init(&[0x60, 0x01, 0xF0, 0x18]);
+
+        // The beeper sounds for the frame the timer got set in
+        assert_eq!(Some(SoundEvent::Started), chip.run_frame(2).unwrap());
+        assert_eq!(0, chip.timer_sound);
+        assert_eq!(Some(SoundEvent::Stopped), chip.tick_timers());
+    }
+
+    #[test]
+    fn test_delay_loop_terminates() {
+        let mut chip = Chip8::new();
+
+        // 0x200: V0 = 5
+        // 0x202: DT = V0
+        // 0x204: V1 = DT
+        // 0x206: skip next if V1 == 0
+        // 0x208: jump to 0x204
+        // 0x20A: jump to 0x20A (halt)
+        let program = [
+            0x60, 0x05, 0xF0, 0x15, 0xF1, 0x07, 0x31, 0x00, 0x12, 0x04, 0x12, 0x0A,
+        ];
+        chip.init(&program);
+
+        // The loop must finish after the timer ran out, independent of the instruction rate
+        // (one frame to set the timer, five ticks to count it down, one frame to notice)
+        let mut frames = 0;
+        while chip.pc != 0x20A {
+            chip.run_frame(100).unwrap();
+            frames += 1;
+            assert!(frames <= 10, "delay loop did not terminate");
+        }
+
+        assert_eq!(0, chip.timer_delay);
+        assert_eq!(6, frames);
+    }
+
+    #[test]
+    fn test_run_frame_stops_at_fault() {
+        let mut chip = Chip8::new();
+
+        // 7001: V0 += 1, 00EE: return without call
+        chip.init(&[0x70, 0x01, 0x00, 0xEE, 0x70, 0x01]);
+
+        // Prepare setup
+        chip.timer_delay = 10;
+
+        // Run frame -> should fault on the second instruction
+        let result = chip.run_frame(3);
+
+        // Assert
+        assert_eq!(
+            Err(Chip8Error {
+                pc: 0x202,
+                opcode: 0x00EE,
+                kind: Chip8ErrorKind::StackUnderflow,
+            }),
+            result
+        );
+        assert_eq!(1, chip.registers[0]);
+        assert_eq!(10, chip.timer_delay);
+    }
+}
+
+// Public API tests
+#[cfg(test)]
+mod api_tests {
+    use super::*;
+
+    // Macro to shadow prelude with pretty_assertions
+    macro_rules! assert_eq {
+        ($($tt:tt)*) => {
+            pretty_assertions::assert_eq!($($tt)*)
+        };
+    }
+
+    #[test]
+    fn test_load_rom() {
+        let mut chip = Chip8::new();
+        chip.load_rom(&[0x12, 0x34]).unwrap();
+
+        // Assert
+        assert_eq!(&[0x12, 0x34], &chip.memory()[0x200..0x202]);
+        assert_eq!(&FONTSET, &chip.memory()[..FONTSET.len()]);
+        assert_eq!(0x200, chip.pc());
+    }
+
+    #[test]
+    fn test_instruction_at() {
+        let rom = [0x00, 0xC1, 0xF3, 0x30, 0xF0, 0x00, 0x12, 0x34];
+        let config = |platform| Config {
+            platform,
+            ..Config::default()
+        };
+
+        // Additions of other platforms don't get executed
+        let mut chip = Chip8::new();
+        chip.init(&rom);
+        assert_eq!(Some(Instruction::Sys(0x0C1)), chip.instruction_at(0x200));
+        assert_eq!(
+            Some(Instruction::Unknown(0xF330)),
+            chip.instruction_at(0x202)
+        );
+        assert_eq!(
+            Some(Instruction::Unknown(0xF000)),
+            chip.instruction_at(0x204)
+        );
+
+        let mut chip = Chip8::with_config(config(Platform::SuperChip));
+        chip.init(&rom);
+        assert_eq!(Some(Instruction::ScrollDown(1)), chip.instruction_at(0x200));
+        assert_eq!(Some(Instruction::LdHfVx(3)), chip.instruction_at(0x202));
+        assert_eq!(
+            Some(Instruction::Unknown(0xF000)),
+            chip.instruction_at(0x204)
+        );
+
+        let mut chip = Chip8::with_config(config(Platform::XoChip));
+        chip.init(&rom);
+        assert_eq!(
+            Some(Instruction::LdILong(0x1234)),
+            chip.instruction_at(0x204)
+        );
+        assert_eq!(None, chip.instruction_at(0xFFFF));
+    }
+
+    #[test]
+    fn test_memory_accesses() {
+        // Prepare setup
+        let mut chip = Chip8::new();
+        chip.init(&[0xA3, 0x00, 0xF2, 0x55, 0xD0, 0x13, 0xF1, 0x65, 0xF0, 0x33]);
+        let access = |kind, address, len| MemoryAccess { kind, address, len };
+
+        // Nothing is recorded unless asked for
+        chip.step().unwrap();
+        assert!(chip.memory_accesses().is_empty());
+        chip.set_access_tracing(true);
+
+        // Run cycle and assert
+        chip.step().unwrap();
+        assert_eq!(
+            &[
+                access(AccessKind::Fetch, 0x202, 2),
+                access(AccessKind::Write, 0x300, 3)
+            ],
+            chip.memory_accesses()
+        );
+        chip.step().unwrap();
+        assert_eq!(
+            &[
+                access(AccessKind::Fetch, 0x204, 2),
+                access(AccessKind::Read, 0x300, 3)
+            ],
+            chip.memory_accesses()
+        );
+        chip.step().unwrap();
+        assert_eq!(AccessKind::Read, chip.memory_accesses()[1].kind);
+
+        // Faulting accesses are not recorded
+        chip.index = MAX_ADDRESS;
+        assert!(chip.step().is_err());
+        assert_eq!(
+            &[access(AccessKind::Fetch, 0x208, 2)],
+            chip.memory_accesses()
+        );
+    }
+
+    #[test]
+    fn test_load_rom_too_large() {
+        let mut chip = Chip8::new();
+
+        // Largest ROM still fits
+        assert_eq!(Ok(()), chip.load_rom(&[0xAA; MAX_ROM_SIZE]));
+        assert_eq!(0xAA, chip.memory()[MAX_ADDRESS as usize]);
+
+        // One byte more does not
+        assert_eq!(
+            Err(LoadError::TooLarge {
+                size: MAX_ROM_SIZE + 1,
+                max: MAX_ROM_SIZE,
+            }),
+            chip.load_rom(&[0xAA; MAX_ROM_SIZE + 1])
+        );
+    }
+
+    #[test]
+    fn test_step_and_views() {
+        let mut chip = Chip8::with_config(Config {
+            quirks: Quirks::cosmac_vip(),
+            seed: Some(1),
+            ..Config::default()
+        });
+
+        // 6A42: VA = 0x42, A300: I = 0x300, 2208: call 0x208, 0208: --, F015: DT = V0
+        chip.load_rom(&[0x6A, 0x42, 0xA3, 0x00, 0x22, 0x08, 0x00, 0x00, 0xFA, 0x15])
+            .unwrap();
+
+        for _ in 0..4 {
+            assert_eq!(Ok(StepOutcome::Executed), chip.step());
+        }
+
+        // Assert
+        assert_eq!(0x42, chip.registers()[0xA]);
+        assert_eq!(0x300, chip.index());
+        assert_eq!(0x20A, chip.pc());
+        assert_eq!(1, chip.sp());
+        assert_eq!(&[0x206], chip.stack());
+        assert_eq!(0x42, chip.delay_timer());
+        assert_eq!(0, chip.sound_timer());
+        assert_eq!(Quirks::cosmac_vip(), chip.quirks());
+    }
+
+    #[test]
+    fn test_keys_and_framebuffer() {
+        let mut chip = Chip8::new();
+
+        // F029: I = sprite of V0, D005: draw it at (V0, V0)
+        chip.load_rom(&[0xF0, 0x29, 0xD0, 0x05]).unwrap();
+        chip.press_key(0x3);
+        assert_eq!(1, chip.keypad()[0x3]);
+        chip.release_key(0x3);
+        assert_eq!(0, chip.keypad()[0x3]);
+
+        chip.run_frame(2).unwrap();
+
+        // Top row of the zero glyph is 0xF0
+        assert_eq!(DISPLAY_WIDTH * DISPLAY_HEIGHT, chip.framebuffer().len());
+        assert!(chip.pixel(0, 0));
+        assert!(chip.pixel(3, 0));
+        assert!(!chip.pixel(4, 0));
+    }
+
+    #[test]
+    fn test_seeded_config_reproducible() {
+        let config = Config {
+            seed: Some(99),
+            ..Config::default()
+        };
+
+        // C0FF: V0 = random byte
+        let mut a = Chip8::with_config(config);
+        let mut b = Chip8::with_config(config);
+        a.load_rom(&[0xC0, 0xFF]).unwrap();
+        b.load_rom(&[0xC0, 0xFF]).unwrap();
+        a.step().unwrap();
+        b.step().unwrap();
+
+        // Assert
+        assert_eq!(a.registers(), b.registers());
+    }
 }
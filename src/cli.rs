@@ -0,0 +1,617 @@
+use std::path::PathBuf;
+
+use chip8::screenshot::ImageFormat;
+use chip8::{Config, Platform, Quirks};
+
+// =================================
+// Command line parsing
+// =================================
+
+pub const USAGE: &str = "\
+Usage: chip8 <command> [options]
+
+Commands:
+  run <rom>                 Load and run a ROM
+  debug <rom>               Step through a ROM or .8o program in an interactive debugger
+  disasm <rom>              Print the instructions of a ROM
+  asm <source> [-o <rom>]   Assemble a source file (default output: source.ch8)
+  octo <source> [-o <rom>] [--platform <name>]
+                            Compile an Octo program (default output: source.ch8),
+                            run also accepts .8o files directly
+  help                      Show this message
+
+Options for run:
+  --ipf <n>                 Instructions per frame (default 10)
+  --platform <name>         Instruction set: chip8, schip, xochip (default chip8)
+  --quirks <preset>         Quirk preset: cowgod, vip, chip48, schip, xochip
+                            (default cowgod, or the preset matching the platform)
+  --seed <n>                Seed for the random number generator
+  --vip-rng <file>          Generate CXNN numbers like the COSMAC VIP interpreter dumped to
+                            file (512 bytes), starting from the low 16 bits of --seed
+  --frames <n>              Stop after n frames
+  --until-pc <addr>         Stop once the program counter reaches addr
+  --frontend <name>         Frontend to use: terminal, headless (default terminal)
+  --screenshot <file>       Write the display to a .pbm or .png file when the run ends
+  --scale <n>               Size of a pixel in the screenshot (default 1)
+  --audio-out <file>        Write the sound of the run to a 16-bit PCM .wav file
+  --rpl-dir <dir>           Directory the SUPER-CHIP user flags are saved in
+                            (default $XDG_DATA_HOME/chip8/rpl)
+
+Options for debug: --ipf, --platform, --quirks, --seed and --vip-rng as for run, and
+  --gdb <[host:]port>       Wait for gdb on a TCP port instead of starting the prompt
+                            (host defaults to 127.0.0.1)
+
+Keys: 1234/QWER/ASDF/ZXCV map onto the hex keypad, Esc quits";
+
+const DEFAULT_INSTRUCTIONS_PER_FRAME: usize = 10;
+
+// Frontend the ROM is presented with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrontendKind {
+    // Half-block rendering and keyboard input in the terminal
+    Terminal,
+    // No display or input, runs as fast as possible
+    Headless,
+}
+
+// Options setting up the machine, shared by run and debug
+#[derive(Clone, Debug, PartialEq)]
+pub struct MachineOptions {
+    pub instructions_per_frame: usize,
+    pub platform: Platform,
+    // Preset given with --quirks, the platform's default quirks otherwise
+    pub quirks: Option<Quirks>,
+    pub seed: Option<u64>,
+    // Interpreter image for the VIP random source, see chip8::VipRng
+    pub vip_interpreter: Option<PathBuf>,
+}
+
+impl Default for MachineOptions {
+    fn default() -> MachineOptions {
+        MachineOptions {
+            instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+            platform: Platform::default(),
+            quirks: None,
+            seed: None,
+            vip_interpreter: None,
+        }
+    }
+}
+
+impl MachineOptions {
+    // Configuration of the chip8, the quirks follow the platform unless given explicitly
+    pub fn config(&self) -> Config {
+        Config {
+            platform: self.platform,
+            quirks: self
+                .quirks
+                .unwrap_or_else(|| self.platform.default_quirks()),
+            seed: self.seed,
+        }
+    }
+}
+
+// Options of the run command
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunOptions {
+    pub rom: PathBuf,
+    pub machine: MachineOptions,
+    pub frame_limit: Option<u64>,
+    pub until_pc: Option<u16>,
+    pub frontend: FrontendKind,
+    pub screenshot: Option<PathBuf>,
+    pub scale: usize,
+    pub audio_out: Option<PathBuf>,
+    pub rpl_dir: Option<PathBuf>,
+}
+
+// Options of the debug command
+#[derive(Clone, Debug, PartialEq)]
+pub struct DebugOptions {
+    pub rom: PathBuf,
+    pub machine: MachineOptions,
+    pub gdb: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Run(RunOptions),
+    Debug(DebugOptions),
+    Disasm(PathBuf),
+    Asm {
+        source: PathBuf,
+        output: PathBuf,
+    },
+    Octo {
+        source: PathBuf,
+        output: PathBuf,
+        platform: Platform,
+    },
+    Help,
+}
+
+// Parse the command line, excluding the program name
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = args.into_iter();
+
+    match args.next().as_deref() {
+        Some("run") => parse_run(args).map(Command::Run),
+        Some("debug") => parse_debug(args).map(Command::Debug),
+        Some("disasm") => parse_rom(args).map(Command::Disasm),
+        Some("asm") => parse_asm(args),
+        Some("octo") => parse_octo(args),
+        Some("help") | Some("-h") | Some("--help") | None => Ok(Command::Help),
+        Some(other) => Err(format!("unknown command '{}'", other)),
+    }
+}
+
+fn parse_run<I: Iterator<Item = String>>(mut args: I) -> Result<RunOptions, String> {
+    let mut rom = None;
+    let mut until_pc = None;
+    let mut options = RunOptions {
+        rom: PathBuf::new(),
+        machine: MachineOptions::default(),
+        frame_limit: None,
+        until_pc: None,
+        frontend: FrontendKind::Terminal,
+        screenshot: None,
+        scale: 1,
+        audio_out: None,
+        rpl_dir: None,
+    };
+
+    while let Some(arg) = args.next() {
+        if parse_machine_option(&arg, &mut args, &mut options.machine)? {
+            continue;
+        }
+
+        match arg.as_str() {
+            "--frames" => options.frame_limit = Some(parse_number(&arg, args.next())?),
+            "--until-pc" => until_pc = Some(parse_number(&arg, args.next())?),
+            "--frontend" => {
+                let name = value(&arg, args.next())?;
+                options.frontend = match name.as_str() {
+                    "terminal" => FrontendKind::Terminal,
+                    "headless" | "none" => FrontendKind::Headless,
+                    _ => return Err(format!("unknown frontend '{}'", name)),
+                };
+            }
+            "--screenshot" => {
+                let path = PathBuf::from(value(&arg, args.next())?);
+                if ImageFormat::from_path(&path).is_none() {
+                    return Err(format!(
+                        "screenshot '{}' must be .pbm or .png",
+                        path.display()
+                    ));
+                }
+                options.screenshot = Some(path);
+            }
+            "--scale" => {
+                options.scale = parse_number(&arg, args.next())? as usize;
+                if options.scale == 0 {
+                    return Err("scale must be at least 1".to_string());
+                }
+            }
+            "--audio-out" => options.audio_out = Some(PathBuf::from(value(&arg, args.next())?)),
+            "--rpl-dir" => options.rpl_dir = Some(PathBuf::from(value(&arg, args.next())?)),
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+            _ if rom.is_none() => rom = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    // The address space depends on the platform, which may come after the address
+    if let Some(address) = until_pc {
+        if address >= options.machine.platform.memory_size() as u64 {
+            return Err(format!("address {:#X} is out of range", address));
+        }
+        options.until_pc = Some(address as u16);
+    }
+
+    options.rom = rom.ok_or("missing ROM path")?;
+    Ok(options)
+}
+
+fn parse_debug<I: Iterator<Item = String>>(mut args: I) -> Result<DebugOptions, String> {
+    let mut rom = None;
+    let mut options = DebugOptions {
+        rom: PathBuf::new(),
+        machine: MachineOptions::default(),
+        gdb: None,
+    };
+
+    while let Some(arg) = args.next() {
+        if parse_machine_option(&arg, &mut args, &mut options.machine)? {
+            continue;
+        }
+
+        match arg.as_str() {
+            "--gdb" => {
+                let address = value(&arg, args.next())?;
+                options.gdb = Some(if address.parse::<u16>().is_ok() {
+                    format!("127.0.0.1:{}", address)
+                } else {
+                    address
+                });
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+            _ if rom.is_none() => rom = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    options.rom = rom.ok_or("missing ROM path")?;
+    Ok(options)
+}
+
+// Parse an option of the machine into options, false if arg is not one of them
+fn parse_machine_option<I: Iterator<Item = String>>(
+    arg: &str,
+    args: &mut I,
+    options: &mut MachineOptions,
+) -> Result<bool, String> {
+    match arg {
+        "--ipf" => options.instructions_per_frame = parse_number(arg, args.next())? as usize,
+        "--platform" => {
+            let name = value(arg, args.next())?;
+            options.platform =
+                Platform::from_name(&name).ok_or_else(|| format!("unknown platform '{}'", name))?;
+        }
+        "--quirks" => {
+            let name = value(arg, args.next())?;
+            options.quirks = Some(
+                Quirks::preset(&name).ok_or_else(|| format!("unknown quirk preset '{}'", name))?,
+            );
+        }
+        "--seed" => options.seed = Some(parse_number(arg, args.next())?),
+        "--vip-rng" => options.vip_interpreter = Some(PathBuf::from(value(arg, args.next())?)),
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+// Commands that only take a ROM path
+fn parse_rom<I: Iterator<Item = String>>(mut args: I) -> Result<PathBuf, String> {
+    let rom = args.next().ok_or("missing ROM path")?;
+
+    match args.next() {
+        _ if rom.starts_with("--") => Err(format!("unknown option '{}'", rom)),
+        Some(arg) => Err(format!("unexpected argument '{}'", arg)),
+        None => Ok(PathBuf::from(rom)),
+    }
+}
+
+fn parse_asm<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut source = None;
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(PathBuf::from(value(&arg, args.next())?)),
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ if source.is_none() => source = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    let source: PathBuf = source.ok_or("missing source path")?;
+    let output = output.unwrap_or_else(|| source.with_extension("ch8"));
+    Ok(Command::Asm { source, output })
+}
+
+fn parse_octo<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut source = None;
+    let mut output = None;
+    let mut platform = Platform::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(PathBuf::from(value(&arg, args.next())?)),
+            "--platform" => {
+                let name = value(&arg, args.next())?;
+                platform = Platform::from_name(&name)
+                    .ok_or_else(|| format!("unknown platform '{}'", name))?;
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ if source.is_none() => source = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    let source: PathBuf = source.ok_or("missing source path")?;
+    let output = output.unwrap_or_else(|| source.with_extension("ch8"));
+    Ok(Command::Octo {
+        source,
+        output,
+        platform,
+    })
+}
+
+// Helper function to get the value following an option
+fn value(option: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("missing value for '{}'", option))
+}
+
+// Helper function to parse a decimal or 0x-prefixed hexadecimal option value
+fn parse_number(option: &str, text: Option<String>) -> Result<u64, String> {
+    let text = value(option, text)?;
+
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+
+    parsed.map_err(|_| format!("invalid number '{}' for '{}'", text, option))
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper function to parse a command line given as a single string
+    fn parse(line: &str) -> Result<Command, String> {
+        parse_args(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_run_defaults() {
+        let expected = RunOptions {
+            rom: PathBuf::from("game.ch8"),
+            machine: MachineOptions::default(),
+            frame_limit: None,
+            until_pc: None,
+            frontend: FrontendKind::Terminal,
+            screenshot: None,
+            scale: 1,
+            audio_out: None,
+            rpl_dir: None,
+        };
+
+        assert_eq!(Ok(Command::Run(expected)), parse("run game.ch8"));
+    }
+
+    #[test]
+    fn test_run_options() {
+        let expected = RunOptions {
+            rom: PathBuf::from("game.ch8"),
+            machine: MachineOptions {
+                instructions_per_frame: 20,
+                platform: Platform::Chip8,
+                quirks: Some(Quirks::cosmac_vip()),
+                seed: Some(0x1234),
+                vip_interpreter: None,
+            },
+            frame_limit: Some(600),
+            until_pc: None,
+            frontend: FrontendKind::Headless,
+            screenshot: None,
+            scale: 1,
+            audio_out: None,
+            rpl_dir: None,
+        };
+
+        assert_eq!(
+            Ok(Command::Run(expected)),
+            parse("run --ipf 20 --quirks vip game.ch8 --seed 0x1234 --frames 600 --frontend none")
+        );
+    }
+
+    #[test]
+    fn test_run_headless() {
+        let expected = RunOptions {
+            rom: PathBuf::from("test.ch8"),
+            machine: MachineOptions::default(),
+            frame_limit: Some(120),
+            until_pc: Some(0x3DC),
+            frontend: FrontendKind::Headless,
+            screenshot: Some(PathBuf::from("out.png")),
+            scale: 4,
+            audio_out: Some(PathBuf::from("out.wav")),
+            rpl_dir: None,
+        };
+
+        assert_eq!(
+            Ok(Command::Run(expected)),
+            parse(
+                "run test.ch8 --frontend headless --frames 120 --until-pc 0x3DC \
+                 --screenshot out.png --scale 4 --audio-out out.wav"
+            )
+        );
+    }
+
+    #[test]
+    fn test_run_platform() {
+        let Ok(Command::Run(options)) = parse("run game.ch8 --platform schip --rpl-dir flags")
+        else {
+            panic!("schip run not parsed");
+        };
+
+        // Quirks follow the platform unless given explicitly
+        assert_eq!(Platform::SuperChip, options.machine.platform);
+        assert_eq!(Quirks::super_chip(), options.machine.config().quirks);
+        assert_eq!(Some(PathBuf::from("flags")), options.rpl_dir);
+
+        let Ok(Command::Run(options)) = parse("run game.ch8 --quirks vip --platform schip") else {
+            panic!("schip run with quirks not parsed");
+        };
+        assert_eq!(Quirks::cosmac_vip(), options.machine.config().quirks);
+        // XO-CHIP addresses reach up to 0xFFFF
+        let Ok(Command::Run(options)) = parse("run game.ch8 --until-pc 0xFFFE --platform xochip")
+        else {
+            panic!("xochip run not parsed");
+        };
+        assert_eq!(Some(0xFFFE), options.until_pc);
+    }
+
+    #[test]
+    fn test_debug() {
+        let expected = DebugOptions {
+            rom: PathBuf::from("game.ch8"),
+            machine: MachineOptions {
+                instructions_per_frame: 20,
+                platform: Platform::SuperChip,
+                quirks: None,
+                seed: Some(7),
+                vip_interpreter: None,
+            },
+            gdb: None,
+        };
+        assert_eq!(Quirks::super_chip(), expected.machine.config().quirks);
+
+        assert_eq!(
+            Ok(Command::Debug(expected.clone())),
+            parse("debug game.ch8 --ipf 20 --platform schip --seed 7")
+        );
+        assert_eq!(
+            Ok(Command::Debug(DebugOptions {
+                gdb: Some("127.0.0.1:1234".to_string()),
+                ..expected.clone()
+            })),
+            parse("debug game.ch8 --ipf 20 --platform schip --seed 7 --gdb 1234")
+        );
+        assert_eq!(
+            Ok(Command::Debug(DebugOptions {
+                gdb: Some("0.0.0.0:1234".to_string()),
+                ..expected
+            })),
+            parse("debug game.ch8 --ipf 20 --platform schip --seed 7 --gdb 0.0.0.0:1234")
+        );
+        let Ok(Command::Debug(options)) = parse("debug game.ch8 --quirks vip --platform schip")
+        else {
+            panic!("schip debug with quirks not parsed");
+        };
+        assert_eq!(Quirks::cosmac_vip(), options.machine.config().quirks);
+        let Ok(Command::Debug(options)) = parse("debug game.ch8 --vip-rng vip.bin --seed 0x10FE")
+        else {
+            panic!("debug with the VIP random source not parsed");
+        };
+        assert_eq!(
+            Some(PathBuf::from("vip.bin")),
+            options.machine.vip_interpreter
+        );
+        assert_eq!(
+            Err("unknown quirk preset 'nes'".to_string()),
+            parse("debug game.ch8 --quirks nes")
+        );
+        assert_eq!(Err("missing ROM path".to_string()), parse("debug"));
+        assert_eq!(
+            Err("unknown option '--frames'".to_string()),
+            parse("debug game.ch8 --frames 10")
+        );
+    }
+
+    #[test]
+    fn test_disasm() {
+        assert_eq!(
+            Ok(Command::Disasm(PathBuf::from("game.ch8"))),
+            parse("disasm game.ch8")
+        );
+        assert_eq!(Err("missing ROM path".to_string()), parse("disasm"));
+        assert_eq!(
+            Err("unexpected argument 'b.ch8'".to_string()),
+            parse("disasm a.ch8 b.ch8")
+        );
+        assert_eq!(
+            Err("unknown option '--ipf'".to_string()),
+            parse("disasm --ipf 10")
+        );
+    }
+
+    #[test]
+    fn test_asm() {
+        assert_eq!(
+            Ok(Command::Asm {
+                source: PathBuf::from("src.s"),
+                output: PathBuf::from("out.ch8"),
+            }),
+            parse("asm src.s -o out.ch8")
+        );
+        assert_eq!(
+            Ok(Command::Asm {
+                source: PathBuf::from("game.s"),
+                output: PathBuf::from("game.ch8"),
+            }),
+            parse("asm game.s")
+        );
+        assert_eq!(
+            Err("missing source path".to_string()),
+            parse("asm -o out.ch8")
+        );
+        assert_eq!(
+            Err("missing value for '-o'".to_string()),
+            parse("asm src.s -o")
+        );
+    }
+
+    #[test]
+    fn test_octo() {
+        assert_eq!(
+            Ok(Command::Octo {
+                source: PathBuf::from("game.8o"),
+                output: PathBuf::from("game.ch8"),
+                platform: Platform::Chip8,
+            }),
+            parse("octo game.8o")
+        );
+        assert_eq!(
+            Ok(Command::Octo {
+                source: PathBuf::from("game.8o"),
+                output: PathBuf::from("out.ch8"),
+                platform: Platform::XoChip,
+            }),
+            parse("octo --platform xochip game.8o -o out.ch8")
+        );
+        assert_eq!(
+            Err("unknown platform 'nes'".to_string()),
+            parse("octo game.8o --platform nes")
+        );
+    }
+
+    #[test]
+    fn test_help() {
+        assert_eq!(Ok(Command::Help), parse(""));
+        assert_eq!(Ok(Command::Help), parse("help"));
+        assert_eq!(Ok(Command::Help), parse("--help"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            ("launch game.ch8", "unknown command 'launch'"),
+            ("run", "missing ROM path"),
+            ("run game.ch8 other.ch8", "unexpected argument 'other.ch8'"),
+            ("run game.ch8 --ipf", "missing value for '--ipf'"),
+            ("run game.ch8 --ipf ten", "invalid number 'ten' for '--ipf'"),
+            ("run game.ch8 --quirks nes", "unknown quirk preset 'nes'"),
+            ("run game.ch8 --frontend sdl", "unknown frontend 'sdl'"),
+            ("run game.ch8 --platform nes", "unknown platform 'nes'"),
+            ("run game.ch8 --turbo", "unknown option '--turbo'"),
+            (
+                "run game.ch8 --until-pc 0x1000",
+                "address 0x1000 is out of range",
+            ),
+            (
+                "run game.ch8 --until-pc 0x10000 --platform xochip",
+                "address 0x10000 is out of range",
+            ),
+            (
+                "run game.ch8 --screenshot a.bmp",
+                "screenshot 'a.bmp' must be .pbm or .png",
+            ),
+            ("run game.ch8 --scale 0", "scale must be at least 1"),
+            (
+                "run game.ch8 --audio-out",
+                "missing value for '--audio-out'",
+            ),
+        ];
+
+        for (line, error) in cases {
+            assert_eq!(Err(error.to_string()), parse(line), "{}", line);
+        }
+    }
+}
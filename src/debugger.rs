@@ -0,0 +1,1038 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+use crate::chip8::{AccessKind, Chip8, StepOutcome};
+use crate::error::Chip8Error;
+use crate::expr::{Expr, Template};
+use crate::instruction::Instruction;
+
+// =================================
+// Debugger
+// =================================
+
+// The debugger owns the chip8 and drives it one cycle at a time, ticking the timers after every
+// `instructions_per_frame` executed instructions like the frame loop of the frontends would.
+// All commands that run more than a single instruction take a limit, so a ROM spinning in an
+// endless loop hands control back eventually.
+//
+// Breakpoints count how often execution reaches them before evaluating their condition, and
+// logpoints, breakpoints with a message, collect their message instead of stopping.
+//
+// Watchpoints are checked after every instruction against the memory accesses the chip8 records
+// and the register values seen after the previous instruction, so they stop right after the
+// instruction that triggered them.
+
+/// Bytes per row of [`hexdump`]
+pub const HEXDUMP_WIDTH: usize = 16;
+
+/// Why the debugger handed control back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    /// Step, step-over or step-out finished
+    Stepped,
+    /// Execution reached a breakpoint, the instruction at it has not been executed yet
+    Breakpoint(u16),
+    /// Instruction that was just executed triggered a watchpoint
+    Watchpoint(WatchHit),
+    /// FX0A is blocking until a key gets pressed and released
+    WaitingForKey,
+    /// Program ended with 00FD
+    Exited,
+    /// Instruction limit ran out before anything else stopped execution
+    Limit,
+    /// ROM faulted, the program counter points at the faulting instruction
+    Fault(Chip8Error),
+}
+
+/// Settings of a breakpoint, the default one always stops
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// Only stop, or log, if the condition evaluates to non-zero
+    pub condition: Option<Expr>,
+    /// Collect this message instead of stopping, see [`Debugger::take_log`]
+    pub log: Option<Template>,
+    /// Times execution reached the breakpoint, `hits` in conditions and messages
+    pub hits: u64,
+}
+
+/// Register of a chip8 that can be watched
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    /// General purpose register V0-VF
+    V(u8),
+    /// Index register
+    I,
+    /// Delay timer
+    Dt,
+    /// Sound timer
+    St,
+}
+
+impl Register {
+    /// Look up a register by name: v0-vf, i, dt or st, case-insensitive
+    pub fn from_name(name: &str) -> Option<Register> {
+        let name = name.to_ascii_lowercase();
+
+        match name.as_str() {
+            "i" => Some(Register::I),
+            "dt" => Some(Register::Dt),
+            "st" => Some(Register::St),
+            _ => {
+                let digit = name.strip_prefix('v').filter(|digit| digit.len() == 1)?;
+                u8::from_str_radix(digit, 16).ok().map(Register::V)
+            }
+        }
+    }
+
+    /// Current value of the register
+    pub fn read(self, chip: &Chip8) -> u16 {
+        match self {
+            Register::V(x) => chip.registers()[x as usize & 0xF] as u16,
+            Register::I => chip.index(),
+            Register::Dt => chip.delay_timer() as u16,
+            Register::St => chip.sound_timer() as u16,
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{:X}", x),
+            Register::I => write!(f, "I"),
+            Register::Dt => write!(f, "DT"),
+            Register::St => write!(f, "ST"),
+        }
+    }
+}
+
+/// Memory accesses a watchpoint triggers on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchAccess {
+    /// Reads, including instruction fetches
+    Read,
+    /// Writes, even if they store the value already there
+    Write,
+    /// Reads and writes
+    ReadWrite,
+}
+
+/// What a watchpoint observes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Watchpoint {
+    /// Accesses of the memory from `start` to `end` inclusive
+    Memory {
+        start: u16,
+        end: u16,
+        access: WatchAccess,
+    },
+    /// Changes of a register, only changes to `value` if given
+    ///
+    /// Timers also change when they are ticked after an instruction.
+    Register {
+        register: Register,
+        value: Option<u16>,
+    },
+}
+
+/// Location a watchpoint saw being accessed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchTarget {
+    /// First watched memory address of the access
+    Memory(u16),
+    /// Register that changed
+    Register(Register),
+}
+
+/// Report of a triggered watchpoint
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    /// Number of the watchpoint, as returned by [`Debugger::add_watchpoint`]
+    pub id: usize,
+    /// Address of the instruction that triggered the watchpoint
+    pub pc: u16,
+    /// Instruction that triggered the watchpoint
+    pub instruction: Instruction,
+    /// What was accessed
+    pub target: WatchTarget,
+    /// How it was accessed, register changes are writes
+    pub kind: AccessKind,
+    /// Value before the instruction, the same as `new` for reads
+    pub old: u16,
+    /// Value after the instruction
+    pub new: u16,
+}
+
+// Watchpoint with the values seen after the previous instruction, to tell the old value of
+// writes and changes
+struct Watch {
+    watchpoint: Watchpoint,
+    // Watched memory, or the single value of the watched register
+    last: Vec<u16>,
+}
+
+/// Debugger around a chip8 with breakpoints, watchpoints and stepping
+pub struct Debugger {
+    chip: Chip8,
+    breakpoints: BTreeMap<u16, Breakpoint>,
+    // Messages of the logpoints reached since the host last took them
+    log: Vec<String>,
+    watches: BTreeMap<usize, Watch>,
+    next_watch: usize,
+    instructions_per_frame: usize,
+    // Instructions executed since the timers were last ticked
+    cycles: usize,
+    // Program counter the last run ran out of its limit at, its breakpoint is still due
+    limit_pc: Option<u16>,
+}
+
+impl Debugger {
+    /// Create a debugger around a chip8 with its ROM already loaded
+    pub fn new(mut chip: Chip8, instructions_per_frame: usize) -> Debugger {
+        chip.set_access_tracing(true);
+
+        Debugger {
+            chip,
+            breakpoints: BTreeMap::new(),
+            log: Vec::new(),
+            watches: BTreeMap::new(),
+            next_watch: 1,
+            instructions_per_frame: instructions_per_frame.max(1),
+            cycles: 0,
+            limit_pc: None,
+        }
+    }
+
+    /// Debugged chip8
+    pub fn chip(&self) -> &Chip8 {
+        &self.chip
+    }
+
+    /// Debugged chip8, e.g. to press keys
+    pub fn chip_mut(&mut self) -> &mut Chip8 {
+        &mut self.chip
+    }
+
+    /// Give back the debugged chip8
+    pub fn into_chip(self) -> Chip8 {
+        self.chip
+    }
+
+    /// Stop before executing the instruction at `address`, returns false if already set
+    pub fn set_breakpoint(&mut self, address: u16) -> bool {
+        if self.breakpoints.contains_key(&address) {
+            return false;
+        }
+
+        self.breakpoints.insert(address, Breakpoint::default());
+        true
+    }
+
+    /// Set a conditional breakpoint or logpoint at `address`, returns the one it replaces
+    pub fn insert_breakpoint(
+        &mut self,
+        address: u16,
+        breakpoint: Breakpoint,
+    ) -> Option<Breakpoint> {
+        self.breakpoints.insert(address, breakpoint)
+    }
+
+    /// Remove the breakpoint at `address`, returns false if there was none
+    pub fn clear_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.remove(&address).is_some()
+    }
+
+    /// Addresses and settings of all breakpoints in ascending order
+    pub fn breakpoints(&self) -> impl Iterator<Item = (u16, &Breakpoint)> + '_ {
+        self.breakpoints
+            .iter()
+            .map(|(&address, breakpoint)| (address, breakpoint))
+    }
+
+    /// Messages of the logpoints reached since the last call
+    pub fn take_log(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.log)
+    }
+
+    /// Add a watchpoint, returns its number or None if it covers memory beyond the address space
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> Option<usize> {
+        if let Watchpoint::Memory { start, end, .. } = watchpoint
+            && (start > end || end as usize >= self.chip.memory().len())
+        {
+            return None;
+        }
+
+        let id = self.next_watch;
+        self.next_watch += 1;
+        self.watches.insert(
+            id,
+            Watch {
+                watchpoint,
+                last: Vec::new(),
+            },
+        );
+        Some(id)
+    }
+
+    /// Remove a watchpoint by number, returns false if there was none
+    pub fn remove_watchpoint(&mut self, id: usize) -> bool {
+        self.watches.remove(&id).is_some()
+    }
+
+    /// Numbers and watchpoints in ascending order
+    pub fn watchpoints(&self) -> impl Iterator<Item = (usize, Watchpoint)> + '_ {
+        self.watches
+            .iter()
+            .map(|(&id, watch)| (id, watch.watchpoint))
+    }
+
+    /// Execute a single instruction, ignoring a breakpoint at the program counter
+    pub fn step(&mut self) -> Stop {
+        self.step_n(1)
+    }
+
+    /// Execute `count` instructions, stopping at breakpoints reached after the first one
+    pub fn step_n(&mut self, count: u64) -> Stop {
+        if count == 0 {
+            return Stop::Stepped;
+        }
+
+        let mut executed = 0;
+        self.run_until(count, |_| {
+            executed += 1;
+            executed == count
+        })
+    }
+
+    /// Execute one instruction, or a whole 2NNN subroutine call until it returns
+    pub fn step_over(&mut self, limit: u64) -> Stop {
+        let pc = self.chip.pc();
+        let Some(Instruction::Call(_)) = self.chip.instruction_at(pc) else {
+            return self.step();
+        };
+
+        let sp = self.chip.sp();
+        let next = pc.wrapping_add(2);
+        self.run_until(limit, |chip| chip.pc() == next && chip.sp() == sp)
+    }
+
+    /// Run until the 00EE returning from the current subroutine has been executed
+    ///
+    /// Outside of a subroutine this runs like [`Debugger::resume`].
+    pub fn step_out(&mut self, limit: u64) -> Stop {
+        let sp = self.chip.sp();
+        self.run_until(limit, |chip| chip.sp() < sp)
+    }
+
+    /// Run until a breakpoint is hit, the program stops or `limit` instructions were executed
+    pub fn resume(&mut self, limit: u64) -> Stop {
+        self.run_until(limit, |_| false)
+    }
+
+    // Execute up to limit instructions until done returns true after one of them
+    // A breakpoint at the starting address is skipped, otherwise execution could never leave it,
+    // unless the previous run ran out of its limit right before it
+    fn run_until(&mut self, limit: u64, mut done: impl FnMut(&Chip8) -> bool) -> Stop {
+        // The chip8 may have been changed since the last run
+        self.remember_watched();
+        let limit_pc = self.limit_pc.take();
+
+        for executed in 0..limit {
+            let pc = self.chip.pc();
+            if (executed > 0 || limit_pc == Some(pc)) && self.reached_breakpoint(pc) {
+                return Stop::Breakpoint(pc);
+            }
+
+            // FX0A completes once the key is released, with pc already past it
+            let executing = if self.chip.is_waiting_for_key() {
+                pc.wrapping_sub(2)
+            } else {
+                pc
+            };
+            let instruction = if self.watches.is_empty() {
+                None
+            } else {
+                self.chip.instruction_at(executing)
+            };
+
+            match self.chip.step() {
+                Ok(StepOutcome::Executed) => {}
+                Ok(StepOutcome::WaitingForKey) => return Stop::WaitingForKey,
+                Ok(StepOutcome::Exited) => return Stop::Exited,
+                Err(error) => return Stop::Fault(error),
+            }
+
+            self.cycles += 1;
+            if self.cycles == self.instructions_per_frame {
+                self.cycles = 0;
+                self.chip.tick_timers();
+            }
+
+            if let Some(instruction) = instruction
+                && let Some(hit) = self.check_watches(executing, instruction)
+            {
+                return Stop::Watchpoint(hit);
+            }
+
+            if done(&self.chip) {
+                return Stop::Stepped;
+            }
+        }
+
+        self.limit_pc = Some(self.chip.pc());
+        Stop::Limit
+    }
+
+    // Helper function to count a hit of the breakpoint at pc, true if execution has to stop
+    fn reached_breakpoint(&mut self, pc: u16) -> bool {
+        let Some(breakpoint) = self.breakpoints.get_mut(&pc) else {
+            return false;
+        };
+
+        breakpoint.hits += 1;
+        let hits = breakpoint.hits;
+        if let Some(condition) = &breakpoint.condition
+            && condition.eval(&self.chip, hits) == 0
+        {
+            return false;
+        }
+
+        match &breakpoint.log {
+            Some(message) => {
+                self.log.push(message.format(&self.chip, hits));
+                false
+            }
+            None => true,
+        }
+    }
+
+    // Helper function to store the current values of everything watched
+    fn remember_watched(&mut self) {
+        for watch in self.watches.values_mut() {
+            watch.last = match watch.watchpoint {
+                Watchpoint::Memory { start, end, .. } => self.chip.memory()
+                    [start as usize..=end as usize]
+                    .iter()
+                    .map(|&byte| byte as u16)
+                    .collect(),
+                Watchpoint::Register { register, .. } => vec![register.read(&self.chip)],
+            };
+        }
+    }
+
+    // Helper function to find the first watchpoint triggered by the instruction at pc, the
+    // remembered values of all watchpoints are updated
+    fn check_watches(&mut self, pc: u16, instruction: Instruction) -> Option<WatchHit> {
+        let mut first = None;
+
+        for (&id, watch) in self.watches.iter_mut() {
+            let hit = |target, kind, old, new| WatchHit {
+                id,
+                pc,
+                instruction,
+                target,
+                kind,
+                old,
+                new,
+            };
+
+            match watch.watchpoint {
+                Watchpoint::Memory { start, end, access } => {
+                    for accessed in self.chip.memory_accesses() {
+                        let from = accessed.address.max(start) as usize;
+                        let to = (accessed.address as usize + accessed.len - 1).min(end as usize);
+                        if from > to {
+                            continue;
+                        }
+
+                        let memory = self.chip.memory();
+                        let offset = from - start as usize;
+                        let triggers = match accessed.kind {
+                            AccessKind::Fetch | AccessKind::Read => access != WatchAccess::Write,
+                            AccessKind::Write => access != WatchAccess::Read,
+                        };
+                        if triggers && first.is_none() {
+                            let (old, new) = (watch.last[offset], memory[from] as u16);
+                            let old = if accessed.kind == AccessKind::Write {
+                                old
+                            } else {
+                                new
+                            };
+                            first = Some(hit(
+                                WatchTarget::Memory(from as u16),
+                                accessed.kind,
+                                old,
+                                new,
+                            ));
+                        }
+
+                        for (address, last) in (from..=to).zip(&mut watch.last[offset..]) {
+                            *last = memory[address] as u16;
+                        }
+                    }
+                }
+                Watchpoint::Register { register, value } => {
+                    let (old, new) = (watch.last[0], register.read(&self.chip));
+                    if old != new && value.is_none_or(|value| value == new) && first.is_none() {
+                        first = Some(hit(
+                            WatchTarget::Register(register),
+                            AccessKind::Write,
+                            old,
+                            new,
+                        ));
+                    }
+                    watch.last[0] = new;
+                }
+            }
+        }
+
+        first
+    }
+
+    /// Hexdump of the memory rows around the index register
+    pub fn dump_index(&self) -> String {
+        let memory = self.chip.memory();
+        let row = self.chip.index() as usize / HEXDUMP_WIDTH * HEXDUMP_WIDTH;
+        let start = row.saturating_sub(HEXDUMP_WIDTH).min(memory.len());
+
+        hexdump(memory, start, 3 * HEXDUMP_WIDTH)
+    }
+}
+
+/// Registers, timers and the stack of a chip8, one group per line
+pub fn dump_state(chip: &Chip8) -> String {
+    let registers: Vec<String> = chip
+        .registers()
+        .iter()
+        .enumerate()
+        .map(|(i, value)| format!("V{:X}={:02X}", i, value))
+        .collect();
+    let stack: Vec<String> = chip
+        .stack()
+        .iter()
+        .take(chip.sp() as usize)
+        .map(|address| format!("{:04X}", address))
+        .collect();
+
+    format!(
+        "{}\nPC={:04X} I={:04X} SP={} DT={} ST={}\nStack: [{}]",
+        registers.join(" "),
+        chip.pc(),
+        chip.index(),
+        chip.sp(),
+        chip.delay_timer(),
+        chip.sound_timer(),
+        stack.join(" ")
+    )
+}
+
+/// Hexdump of `length` bytes of memory from `start`, cut off at the end of memory
+///
+/// Each row starts with its address, e.g. `0200: 00 E0 A2 2A`.
+pub fn hexdump(memory: &[u8], start: usize, length: usize) -> String {
+    let end = start.saturating_add(length).min(memory.len());
+    let mut dump = String::new();
+
+    for (row, bytes) in memory[start.min(end)..end]
+        .chunks(HEXDUMP_WIDTH)
+        .enumerate()
+    {
+        if row > 0 {
+            dump.push('\n');
+        }
+
+        let _ = write!(dump, "{:04X}:", start + row * HEXDUMP_WIDTH);
+        for byte in bytes {
+            let _ = write!(dump, " {:02X}", byte);
+        }
+    }
+
+    dump
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::chip8::Config;
+    use crate::platform::Platform;
+
+    // Macro to shadow prelude with pretty_assertions
+    macro_rules! assert_eq {
+        ($($tt:tt)*) => {
+            pretty_assertions::assert_eq!($($tt)*)
+        };
+    }
+
+    // Helper function to create a debugger for an assembly program
+    fn new_debugger(source: &str) -> Debugger {
+        let mut chip = Chip8::with_config(Config {
+            seed: Some(0),
+            ..Config::default()
+        });
+        chip.init(&assemble(source).unwrap());
+
+        Debugger::new(chip, 10)
+    }
+
+    const PROGRAM: &str = "
+        start:
+            LD V0, 1        ; 0200
+            CALL outer      ; 0202
+            LD V1, 2        ; 0204
+            JP start        ; 0206
+        outer:
+            CALL inner      ; 0208
+            ADD V0, 1       ; 020A
+            RET             ; 020C
+        inner:
+            ADD V0, 2       ; 020E
+            RET             ; 0210
+    ";
+
+    #[test]
+    fn test_step() {
+        // Prepare setup
+        let mut debugger = new_debugger(PROGRAM);
+
+        // Step into the call
+        assert_eq!(Stop::Stepped, debugger.step());
+        assert_eq!(Stop::Stepped, debugger.step());
+
+        // Assert
+        assert_eq!(0x208, debugger.chip().pc());
+        assert_eq!(1, debugger.chip().sp());
+    }
+
+    #[test]
+    fn test_step_n() {
+        // Prepare setup
+        let mut debugger = new_debugger(PROGRAM);
+        debugger.set_breakpoint(0x200);
+        debugger.set_breakpoint(0x20E);
+
+        // The breakpoint at the start is stepped off, the one inside the call stops
+        assert_eq!(Stop::Breakpoint(0x20E), debugger.step_n(5));
+        assert_eq!(0x20E, debugger.chip().pc());
+
+        // Without a breakpoint in the way all instructions are executed
+        assert_eq!(Stop::Stepped, debugger.step_n(3));
+        assert_eq!(0x20C, debugger.chip().pc());
+        assert_eq!(Stop::Stepped, debugger.step_n(0));
+        assert_eq!(0x20C, debugger.chip().pc());
+    }
+
+    #[test]
+    fn test_step_over() {
+        // Prepare setup
+        let mut debugger = new_debugger(PROGRAM);
+        debugger.step();
+
+        // Step over the call, including the nested one
+        assert_eq!(Stop::Stepped, debugger.step_over(100));
+
+        // Assert
+        assert_eq!(0x204, debugger.chip().pc());
+        assert_eq!(4, debugger.chip().registers()[0]);
+
+        // Anything else is a single step
+        assert_eq!(Stop::Stepped, debugger.step_over(100));
+        assert_eq!(0x206, debugger.chip().pc());
+    }
+
+    #[test]
+    fn test_step_over_breakpoint() {
+        // Prepare setup
+        let mut debugger = new_debugger(PROGRAM);
+        debugger.step();
+        debugger.set_breakpoint(0x20E);
+
+        // Breakpoints inside the call still stop
+        assert_eq!(Stop::Breakpoint(0x20E), debugger.step_over(100));
+
+        // Calls that take too long hand control back too
+        let mut slow = new_debugger(PROGRAM);
+        slow.step();
+        assert_eq!(Stop::Limit, slow.step_over(2));
+        assert_eq!(0x20E, slow.chip().pc());
+    }
+
+    #[test]
+    fn test_step_out() {
+        // Prepare setup
+        let mut debugger = new_debugger(PROGRAM);
+        debugger.set_breakpoint(0x20E);
+        assert_eq!(Stop::Breakpoint(0x20E), debugger.resume(100));
+
+        // Return from inner into outer
+        assert_eq!(Stop::Stepped, debugger.step_out(100));
+        assert_eq!(0x20A, debugger.chip().pc());
+
+        // Return from outer into the main loop
+        assert_eq!(Stop::Stepped, debugger.step_out(100));
+        assert_eq!(0x204, debugger.chip().pc());
+        assert_eq!(0, debugger.chip().sp());
+    }
+
+    #[test]
+    fn test_resume() {
+        // Prepare setup
+        let mut debugger = new_debugger(PROGRAM);
+        assert!(debugger.set_breakpoint(0x206));
+        assert!(!debugger.set_breakpoint(0x206));
+        debugger.set_breakpoint(0x20C);
+
+        // Run cycle
+        assert_eq!(Stop::Breakpoint(0x20C), debugger.resume(100));
+        assert_eq!(Stop::Breakpoint(0x206), debugger.resume(100));
+        assert_eq!(Stop::Breakpoint(0x20C), debugger.resume(100));
+
+        // Assert
+        let addresses: Vec<u16> = debugger.breakpoints().map(|(address, _)| address).collect();
+        assert_eq!(vec![0x206, 0x20C], addresses);
+        assert!(debugger.clear_breakpoint(0x20C));
+        assert!(!debugger.clear_breakpoint(0x20C));
+        assert_eq!(Stop::Limit, debugger.resume(2));
+
+        // The limit ran out right before a breakpoint
+        assert_eq!(0x206, debugger.chip().pc());
+        assert_eq!(Stop::Breakpoint(0x206), debugger.resume(100));
+    }
+
+    #[test]
+    fn test_stops() {
+        let mut waiting = new_debugger("LD V0, K");
+        assert_eq!(Stop::WaitingForKey, waiting.resume(100));
+
+        let mut faulting = new_debugger("RET");
+        let Stop::Fault(error) = faulting.resume(100) else {
+            panic!("RET with an empty stack did not fault");
+        };
+        assert_eq!(0x200, error.pc);
+
+        // Faults don't move the program counter, so they repeat
+        assert_eq!(Stop::Fault(error), faulting.step());
+
+        let mut chip = Chip8::with_config(Config {
+            platform: Platform::SuperChip,
+            ..Config::default()
+        });
+        chip.init(&assemble("EXIT").unwrap());
+        assert_eq!(Stop::Exited, Debugger::new(chip, 10).resume(100));
+    }
+
+    #[test]
+    fn test_timers() {
+        // Prepare setup
+        let mut debugger = new_debugger("LD V0, 30\nLD DT, V0\nloop: JP loop");
+
+        // Run cycle
+        debugger.resume(2 + 10 * 5);
+
+        // Timers are ticked every 10 instructions
+        assert_eq!(25, debugger.chip().delay_timer());
+    }
+
+    #[test]
+    fn test_conditional_breakpoints() {
+        // Prepare setup
+        let mut debugger = new_debugger(
+            "
+            loop:
+            ADD V3, 1       ; 0200
+            LD I, 0x300     ; 0202
+            LD [I], V3      ; 0204
+            JP loop         ; 0206
+            ",
+        );
+        let condition = |source| Some(Expr::parse(source).unwrap());
+        debugger.insert_breakpoint(
+            0x204,
+            Breakpoint {
+                condition: condition("v3 == 5 || hits == 7"),
+                ..Breakpoint::default()
+            },
+        );
+
+        // Run cycle
+        assert_eq!(Stop::Breakpoint(0x204), debugger.resume(100));
+        assert_eq!(5, debugger.chip().registers()[3]);
+        assert_eq!(Stop::Breakpoint(0x204), debugger.resume(100));
+        assert_eq!(7, debugger.chip().registers()[3]);
+
+        // Memory written in the previous iteration
+        debugger.insert_breakpoint(
+            0x204,
+            Breakpoint {
+                condition: condition("[i + 3] == 10"),
+                ..Breakpoint::default()
+            },
+        );
+        assert_eq!(Stop::Breakpoint(0x204), debugger.resume(100));
+        assert_eq!(11, debugger.chip().registers()[3]);
+
+        // Hits are counted even if the condition fails
+        let (_, breakpoint) = debugger.breakpoints().next().unwrap();
+        assert_eq!(4, breakpoint.hits);
+    }
+
+    #[test]
+    fn test_logpoints() {
+        // Prepare setup
+        let mut debugger = new_debugger("loop:\nADD V3, 1\nLD I, 0x300\nJP loop");
+        debugger.insert_breakpoint(
+            0x202,
+            Breakpoint {
+                condition: Some(Expr::parse("v3 % 2 == 0").unwrap()),
+                log: Some(Template::parse("v3={v3} hit {hits}").unwrap()),
+                ..Breakpoint::default()
+            },
+        );
+
+        // Logpoints don't stop
+        assert_eq!(Stop::Limit, debugger.resume(12));
+
+        // Assert
+        assert_eq!(vec!["v3=2 hit 2", "v3=4 hit 4"], debugger.take_log());
+        assert!(debugger.take_log().is_empty());
+    }
+
+    #[test]
+    fn test_watch_writes() {
+        // Prepare setup
+        let mut debugger = new_debugger(
+            "
+            LD I, 0x300     ; 0200
+            LD V0, 0x42     ; 0202
+            LD V1, 123      ; 0204
+            LD [I], V1      ; 0206
+            LD B, V1        ; 0208
+            LD V2, [I]      ; 020A
+            ",
+        );
+        let write = |start, end| Watchpoint::Memory {
+            start,
+            end,
+            access: WatchAccess::Write,
+        };
+        assert_eq!(Some(1), debugger.add_watchpoint(write(0x301, 0x302)));
+        assert_eq!(Some(2), debugger.add_watchpoint(write(0x300, 0x300)));
+
+        // FX55 hits the first watchpoint that covers any written byte
+        let hit = WatchHit {
+            id: 1,
+            pc: 0x206,
+            instruction: Instruction::LdIVx(1),
+            target: WatchTarget::Memory(0x301),
+            kind: AccessKind::Write,
+            old: 0,
+            new: 123,
+        };
+        assert_eq!(Stop::Watchpoint(hit), debugger.resume(100));
+
+        // Old values are the ones written before
+        assert!(debugger.remove_watchpoint(1));
+        let hit = WatchHit {
+            id: 2,
+            pc: 0x208,
+            instruction: Instruction::LdBVx(1),
+            target: WatchTarget::Memory(0x300),
+            kind: AccessKind::Write,
+            old: 0x42,
+            new: 1,
+        };
+        assert_eq!(Stop::Watchpoint(hit), debugger.resume(100));
+
+        // Reads don't trigger write watchpoints
+        assert_eq!(Stop::Limit, debugger.resume(1));
+        assert_eq!(0x20C, debugger.chip().pc());
+    }
+
+    #[test]
+    fn test_watch_reads() {
+        // Prepare setup
+        let mut debugger = new_debugger(
+            "
+            LD I, sprite    ; 0200
+            DRW V0, V0, 2   ; 0202
+            loop:
+            JP loop         ; 0204
+            sprite:
+            DB 0xFF, 0x81   ; 0206
+            ",
+        );
+        let read = |start, end| Watchpoint::Memory {
+            start,
+            end,
+            access: WatchAccess::Read,
+        };
+        debugger.add_watchpoint(read(0x207, 0x207));
+        debugger.add_watchpoint(Watchpoint::Memory {
+            start: 0x204,
+            end: 0x205,
+            access: WatchAccess::ReadWrite,
+        });
+
+        // Sprite data read by DXYN
+        let Stop::Watchpoint(hit) = debugger.resume(100) else {
+            panic!("sprite read did not trigger");
+        };
+        assert_eq!((1, 0x202), (hit.id, hit.pc));
+        assert_eq!(WatchTarget::Memory(0x207), hit.target);
+        assert_eq!((AccessKind::Read, 0x81, 0x81), (hit.kind, hit.old, hit.new));
+
+        // Instruction fetches
+        let Stop::Watchpoint(hit) = debugger.resume(100) else {
+            panic!("fetch did not trigger");
+        };
+        assert_eq!((2, 0x204), (hit.id, hit.pc));
+        assert_eq!(WatchTarget::Memory(0x204), hit.target);
+        assert_eq!((AccessKind::Fetch, 0x12), (hit.kind, hit.new));
+
+        // Watched memory has to exist
+        assert_eq!(None, debugger.add_watchpoint(read(0xFFF, 0x1000)));
+        assert_eq!(None, debugger.add_watchpoint(read(0x301, 0x300)));
+    }
+
+    #[test]
+    fn test_watch_empty_sprite() {
+        // Prepare setup
+        let mut debugger = new_debugger(
+            "
+            LD I, 0         ; 0200
+            DRW V0, V0, 0   ; 0202
+            loop:
+            JP loop         ; 0204
+            ",
+        );
+        debugger.add_watchpoint(Watchpoint::Memory {
+            start: 0x000,
+            end: 0x00F,
+            access: WatchAccess::ReadWrite,
+        });
+
+        // A sprite without rows reads nothing, not even at I
+        assert_eq!(Stop::Limit, debugger.resume(10));
+        assert_eq!(0x204, debugger.chip().pc());
+    }
+
+    #[test]
+    fn test_watch_registers() {
+        // Prepare setup
+        let mut debugger = new_debugger(
+            "
+            loop:
+            ADD V3, 1       ; 0200
+            LD I, 0x300     ; 0202
+            JP loop         ; 0204
+            ",
+        );
+        let watch = |register, value| Watchpoint::Register { register, value };
+        debugger.add_watchpoint(watch(Register::V(3), Some(5)));
+        debugger.add_watchpoint(watch(Register::I, None));
+
+        // I changes only once
+        let hit = WatchHit {
+            id: 2,
+            pc: 0x202,
+            instruction: Instruction::LdI(0x300),
+            target: WatchTarget::Register(Register::I),
+            kind: AccessKind::Write,
+            old: 0,
+            new: 0x300,
+        };
+        assert_eq!(Stop::Watchpoint(hit), debugger.resume(100));
+
+        // V3 changes to 5 in the fifth iteration
+        let Stop::Watchpoint(hit) = debugger.resume(100) else {
+            panic!("register change did not trigger");
+        };
+        assert_eq!((1, 0x200), (hit.id, hit.pc));
+        assert_eq!((4, 5), (hit.old, hit.new));
+        assert_eq!(Stop::Limit, debugger.resume(100));
+    }
+
+    #[test]
+    fn test_watch_timers() {
+        // Prepare setup
+        let mut debugger = new_debugger("LD V0, 3\nLD DT, V0\nloop: JP loop");
+        debugger.add_watchpoint(Watchpoint::Register {
+            register: Register::Dt,
+            value: Some(0),
+        });
+
+        // Run cycle
+        let Stop::Watchpoint(hit) = debugger.resume(100) else {
+            panic!("timer did not trigger");
+        };
+
+        // Timers are ticked every 10 instructions
+        assert_eq!((1, 0), (hit.old, hit.new));
+        assert_eq!(Instruction::Jp(0x204), hit.instruction);
+    }
+
+    #[test]
+    fn test_register_names() {
+        assert_eq!(Some(Register::V(0xA)), Register::from_name("va"));
+        assert_eq!(Some(Register::V(0xF)), Register::from_name("VF"));
+        assert_eq!(Some(Register::I), Register::from_name("i"));
+        assert_eq!(Some(Register::Dt), Register::from_name("DT"));
+        assert_eq!(Some(Register::St), Register::from_name("st"));
+        assert_eq!(None, Register::from_name("v10"));
+        assert_eq!(None, Register::from_name("pc"));
+        assert_eq!(
+            "VA I DT ST",
+            format!(
+                "{} {} {} {}",
+                Register::V(0xA),
+                Register::I,
+                Register::Dt,
+                Register::St
+            )
+        );
+    }
+
+    #[test]
+    fn test_dump_state() {
+        // Prepare setup
+        let mut debugger = new_debugger(PROGRAM);
+        debugger.set_breakpoint(0x20E);
+        debugger.resume(100);
+
+        // Assert
+        let expected = "\
+V0=01 V1=00 V2=00 V3=00 V4=00 V5=00 V6=00 V7=00 V8=00 V9=00 VA=00 VB=00 VC=00 VD=00 VE=00 VF=00
+PC=020E I=0000 SP=2 DT=0 ST=0
+Stack: [0204 020A]";
+        assert_eq!(expected, dump_state(debugger.chip()));
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let memory: Vec<u8> = (0..=0x25).collect();
+
+        let expected = "\
+0010: 10 11 12 13 14 15 16 17 18 19 1A 1B 1C 1D 1E 1F
+0020: 20 21 22 23 24 25";
+        assert_eq!(expected, hexdump(&memory, 0x10, 0x100));
+        assert_eq!("0004: 04 05", hexdump(&memory, 4, 2));
+        assert_eq!("", hexdump(&memory, 0x100, 2));
+    }
+
+    #[test]
+    fn test_dump_index() {
+        // Prepare setup
+        let mut debugger = new_debugger("LD I, 0x215\nDB 0xAB");
+        debugger.step();
+
+        // Rows before, at and after I
+        let dump = debugger.dump_index();
+        let rows: Vec<&str> = dump.lines().map(|row| &row[..5]).collect();
+        assert_eq!(vec!["0200:", "0210:", "0220:"], rows);
+        assert!(dump.starts_with("0200: A2 15 AB 00"));
+    }
+}
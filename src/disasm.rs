@@ -0,0 +1,137 @@
+use std::fmt;
+
+use crate::instruction::{self, Instruction};
+
+// =================================
+// ROM disassembly
+// =================================
+
+// Listings are a linear sweep over the ROM, so sprite data shows up as instructions where it
+// happens to decode. Every line still assembles back to exactly its bytes: encodings that are
+// no instruction become DW and a trailing odd byte DB.
+
+// Column the address and raw bytes comment starts at
+const COMMENT_COLUMN: usize = 24;
+
+/// One instruction, or leftover byte, of a disassembly listing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Line<'a> {
+    /// Address the bytes are loaded to
+    pub address: u16,
+    /// Raw bytes of the instruction
+    pub bytes: &'a [u8],
+    /// Decoded instruction, None for a single byte at the end of the ROM
+    pub instruction: Option<Instruction>,
+}
+
+/// Disassemble a whole ROM loaded at `origin`
+pub fn disassemble(rom: &[u8], origin: u16) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+
+    while offset < rom.len() {
+        let instruction = instruction::decode_at(rom, offset);
+        let size = instruction.map_or(1, |instruction| instruction.size() as usize);
+
+        lines.push(Line {
+            address: origin.wrapping_add(offset as u16),
+            bytes: &rom[offset..offset + size],
+            instruction,
+        });
+        offset += size;
+    }
+
+    lines
+}
+
+// Mnemonic first and the address and bytes as comment, so listings can be assembled again
+impl fmt::Display for Line<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mnemonic = match self.instruction {
+            Some(instruction) => instruction.to_string(),
+            None => format!("DB {:#04X}", self.bytes[0]),
+        };
+        let bytes: String = self
+            .bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+
+        write!(
+            f,
+            "{:<width$}; {:04X}: {}",
+            mnemonic,
+            self.address,
+            bytes,
+            width = COMMENT_COLUMN
+        )
+    }
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Macro to shadow prelude with pretty_assertions
+    macro_rules! assert_eq {
+        ($($tt:tt)*) => {
+            pretty_assertions::assert_eq!($($tt)*)
+        };
+    }
+
+    #[test]
+    fn test_disassemble() {
+        // Prepare setup
+        let rom = [
+            0x00, 0xE0, // CLS
+            0x6A, 0x42, // LD VA, 0x42
+            0xF0, 0x00, 0x12, 0x34, // LD I, LONG 0x1234
+            0x51, 0x21, // no instruction
+            0xFF, // odd byte
+        ];
+
+        // Run disassembly
+        let listing: Vec<String> = disassemble(&rom, 0x200)
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+
+        // Assert
+        let expected = [
+            "CLS                     ; 0200: 00E0",
+            "LD VA, 0x42             ; 0202: 6A42",
+            "LD I, LONG 0x1234       ; 0204: F0001234",
+            "DW 0x5121               ; 0208: 5121",
+            "DB 0xFF                 ; 020A: FF",
+        ];
+        assert_eq!(expected.to_vec(), listing);
+    }
+
+    #[test]
+    fn test_disassemble_lines() {
+        let rom = [0x12, 0x00, 0xF0, 0x00];
+        let lines = disassemble(&rom, 0x200);
+
+        // A long load cut off by the end of the ROM is plain data
+        assert_eq!(
+            vec![
+                Line {
+                    address: 0x200,
+                    bytes: &rom[..2],
+                    instruction: Some(Instruction::Jp(0x200)),
+                },
+                Line {
+                    address: 0x202,
+                    bytes: &rom[2..],
+                    instruction: Some(Instruction::Unknown(0xF000)),
+                },
+            ],
+            lines
+        );
+        assert!(disassemble(&[], 0x200).is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt;
+
+// =================================
+// Errors raised by the emulator
+// =================================
+
+/// Kind of fault a ROM caused
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8ErrorKind {
+    /// Opcode is not part of the instruction set
+    UnknownOpcode,
+    /// More than 16 nested subroutine calls
+    StackOverflow,
+    /// Return without a matching subroutine call
+    StackUnderflow,
+    /// Instruction tried to access memory beyond the address space, holds the offending address
+    MemoryOutOfBounds(u16),
+    /// Key instruction referenced a key outside of 0x0-0xF, holds the offending value
+    InvalidKey(u8),
+}
+
+/// Fault raised by a single CPU cycle
+///
+/// `pc` points at the faulting instruction, so the cycle can be retried once the cause is fixed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chip8Error {
+    /// Address of the faulting instruction
+    pub pc: u16,
+    /// Faulting opcode, 0 if it could not be fetched
+    pub opcode: u16,
+    /// What went wrong
+    pub kind: Chip8ErrorKind,
+}
+
+/// Error raised when loading a ROM
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    /// ROM does not fit into memory
+    TooLarge { size: usize, max: usize },
+}
+
+/// Error raised when assembling or compiling a source file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    /// Line of the error, starting at 1
+    pub line: usize,
+    /// Column of the error in characters, starting at 1
+    pub column: usize,
+    /// What went wrong
+    pub message: String,
+}
+
+/// Error raised when parsing a debugger expression
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExprError {
+    /// Column of the error in characters, starting at 1
+    pub column: usize,
+    /// What went wrong
+    pub message: String,
+}
+
+impl fmt::Display for Chip8ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8ErrorKind::UnknownOpcode => write!(f, "unknown opcode"),
+            Chip8ErrorKind::StackOverflow => write!(f, "stack overflow"),
+            Chip8ErrorKind::StackUnderflow => write!(f, "stack underflow"),
+            Chip8ErrorKind::MemoryOutOfBounds(address) => {
+                write!(f, "memory access out of bounds at {:04X}", address)
+            }
+            Chip8ErrorKind::InvalidKey(key) => write!(f, "invalid key {:02X}", key),
+        }
+    }
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (opcode {:04X} at {:04X})",
+            self.kind, self.opcode, self.pc
+        )
+    }
+}
+
+impl std::error::Error for Chip8Error {}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::TooLarge { size, max } => {
+                write!(f, "ROM is {} bytes, at most {} bytes fit", size, max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for ExprError {}
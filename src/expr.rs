@@ -0,0 +1,588 @@
+use std::fmt;
+
+use crate::chip8::Chip8;
+use crate::debugger::Register;
+use crate::error::ExprError;
+
+// =================================
+// Debugger expressions
+// =================================
+
+// Conditions of breakpoints and the values printed by logpoints, e.g.
+//
+//     v3 == 0x10 && i > 0x300 && [i + 2] != 0
+//
+// Operands are numbers (decimal, 0x hexadecimal or 0b binary), the registers v0-vf, i, dt, st,
+// sp and pc, `hits` for the number of times the breakpoint was reached and `[address]` for a
+// byte of memory. Operators and their precedence are the ones of Rust, comparisons and logical
+// operators give 1 or 0. Evaluation never fails: arithmetic wraps, division by zero gives 0 and
+// memory beyond the address space reads as 0.
+
+/// Parsed debugger expression
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expr {
+    source: String,
+    node: Node,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Node {
+    Number(i64),
+    Register(Register),
+    Sp,
+    Pc,
+    Hits,
+    Memory(Box<Node>),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UnaryOp {
+    Neg,
+    Not,
+    BitNot,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinaryOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    BitAnd,
+    BitXor,
+    BitOr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+// Binary operators with their precedence, longer ones first so they are matched first
+const BINARY_OPS: [(&str, u8, BinaryOp); 18] = [
+    ("||", 1, BinaryOp::Or),
+    ("&&", 2, BinaryOp::And),
+    ("==", 3, BinaryOp::Eq),
+    ("!=", 3, BinaryOp::Ne),
+    ("<=", 3, BinaryOp::Le),
+    (">=", 3, BinaryOp::Ge),
+    ("<<", 7, BinaryOp::Shl),
+    (">>", 7, BinaryOp::Shr),
+    ("<", 3, BinaryOp::Lt),
+    (">", 3, BinaryOp::Gt),
+    ("|", 4, BinaryOp::BitOr),
+    ("^", 5, BinaryOp::BitXor),
+    ("&", 6, BinaryOp::BitAnd),
+    ("+", 8, BinaryOp::Add),
+    ("-", 8, BinaryOp::Sub),
+    ("*", 9, BinaryOp::Mul),
+    ("/", 9, BinaryOp::Div),
+    ("%", 9, BinaryOp::Rem),
+];
+
+// Punctuation besides the binary operators
+const PUNCTUATION: [&str; 6] = ["!", "~", "(", ")", "[", "]"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TokenKind {
+    Number(i64),
+    Name(String),
+    Symbol(&'static str),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+impl Expr {
+    /// Parse an expression
+    pub fn parse(source: &str) -> Result<Expr, ExprError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            end: source.chars().count() + 1,
+        };
+
+        let node = parser.expression(0)?;
+        if let Some(token) = parser.peek() {
+            return Err(error(token.column, format!("unexpected {}", token.kind)));
+        }
+
+        Ok(Expr {
+            source: source.trim().to_string(),
+            node,
+        })
+    }
+
+    /// Evaluate the expression on the state of a chip8, `hits` is the value of `hits`
+    pub fn eval(&self, chip: &Chip8, hits: u64) -> i64 {
+        eval(&self.node, chip, hits)
+    }
+}
+
+// The source the expression was parsed from
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn eval(node: &Node, chip: &Chip8, hits: u64) -> i64 {
+    match node {
+        Node::Number(value) => *value,
+        Node::Register(register) => register.read(chip) as i64,
+        Node::Sp => chip.sp() as i64,
+        Node::Pc => chip.pc() as i64,
+        Node::Hits => hits as i64,
+        Node::Memory(address) => {
+            let address = eval(address, chip, hits);
+            usize::try_from(address)
+                .ok()
+                .and_then(|address| chip.memory().get(address))
+                .map_or(0, |&byte| byte as i64)
+        }
+        Node::Unary(op, operand) => {
+            let value = eval(operand, chip, hits);
+            match op {
+                UnaryOp::Neg => value.wrapping_neg(),
+                UnaryOp::Not => (value == 0) as i64,
+                UnaryOp::BitNot => !value,
+            }
+        }
+        // Short-circuiting
+        Node::Binary(BinaryOp::And, lhs, rhs) => {
+            (eval(lhs, chip, hits) != 0 && eval(rhs, chip, hits) != 0) as i64
+        }
+        Node::Binary(BinaryOp::Or, lhs, rhs) => {
+            (eval(lhs, chip, hits) != 0 || eval(rhs, chip, hits) != 0) as i64
+        }
+        Node::Binary(op, lhs, rhs) => {
+            let (lhs, rhs) = (eval(lhs, chip, hits), eval(rhs, chip, hits));
+            let shift = u32::try_from(rhs).unwrap_or(u32::MAX);
+
+            match op {
+                BinaryOp::Mul => lhs.wrapping_mul(rhs),
+                BinaryOp::Div => lhs.checked_div(rhs).unwrap_or(0),
+                BinaryOp::Rem => lhs.checked_rem(rhs).unwrap_or(0),
+                BinaryOp::Add => lhs.wrapping_add(rhs),
+                BinaryOp::Sub => lhs.wrapping_sub(rhs),
+                BinaryOp::Shl => lhs.checked_shl(shift).unwrap_or(0),
+                BinaryOp::Shr => lhs.checked_shr(shift).unwrap_or(0),
+                BinaryOp::BitAnd => lhs & rhs,
+                BinaryOp::BitXor => lhs ^ rhs,
+                BinaryOp::BitOr => lhs | rhs,
+                BinaryOp::Eq => (lhs == rhs) as i64,
+                BinaryOp::Ne => (lhs != rhs) as i64,
+                BinaryOp::Lt => (lhs < rhs) as i64,
+                BinaryOp::Le => (lhs <= rhs) as i64,
+                BinaryOp::Gt => (lhs > rhs) as i64,
+                BinaryOp::Ge => (lhs >= rhs) as i64,
+                BinaryOp::And | BinaryOp::Or => unreachable!("handled above"),
+            }
+        }
+    }
+}
+
+// Precedence climbing parser
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    // Column just behind the source, for errors at its end
+    end: usize,
+}
+
+impl<'a> Parser<'a> {
+    // Parse operators binding tighter than min_precedence, left-associative
+    fn expression(&mut self, min_precedence: u8) -> Result<Node, ExprError> {
+        let mut lhs = self.unary()?;
+
+        while let Some((precedence, op)) = self.binary_op()
+            && precedence > min_precedence
+        {
+            self.position += 1;
+            let rhs = self.expression(precedence)?;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn binary_op(&self) -> Option<(u8, BinaryOp)> {
+        let TokenKind::Symbol(symbol) = self.peek()?.kind else {
+            return None;
+        };
+
+        BINARY_OPS
+            .iter()
+            .find(|(text, ..)| *text == symbol)
+            .map(|&(_, precedence, op)| (precedence, op))
+    }
+
+    fn unary(&mut self) -> Result<Node, ExprError> {
+        let Some(token) = self.next() else {
+            return Err(error(self.end, "missing value".to_string()));
+        };
+
+        let op = match token.kind {
+            TokenKind::Symbol("-") => UnaryOp::Neg,
+            TokenKind::Symbol("!") => UnaryOp::Not,
+            TokenKind::Symbol("~") => UnaryOp::BitNot,
+            _ => return self.primary(token),
+        };
+
+        Ok(Node::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self, token: &'a Token) -> Result<Node, ExprError> {
+        match &token.kind {
+            TokenKind::Number(value) => Ok(Node::Number(*value)),
+            TokenKind::Name(name) => match name.to_ascii_lowercase().as_str() {
+                "sp" => Ok(Node::Sp),
+                "pc" => Ok(Node::Pc),
+                "hits" => Ok(Node::Hits),
+                _ => Register::from_name(name)
+                    .map(Node::Register)
+                    .ok_or_else(|| error(token.column, format!("unknown name '{}'", name))),
+            },
+            TokenKind::Symbol(open @ ("(" | "[")) => {
+                let inner = self.expression(0)?;
+                let close = if *open == "(" { ")" } else { "]" };
+
+                match self.next() {
+                    Some(Token {
+                        kind: TokenKind::Symbol(symbol),
+                        ..
+                    }) if *symbol == close => {}
+                    _ => {
+                        return Err(error(
+                            token.column,
+                            format!("'{}' without '{}'", open, close),
+                        ));
+                    }
+                }
+
+                Ok(match *open {
+                    "(" => inner,
+                    _ => Node::Memory(Box::new(inner)),
+                })
+            }
+            kind => Err(error(token.column, format!("unexpected {}", kind))),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position)?;
+        self.position += 1;
+        Some(token)
+    }
+}
+
+// Helper function to split an expression into tokens
+fn tokenize(source: &str) -> Result<Vec<Token>, ExprError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        let column = index + 1;
+
+        if c.is_whitespace() {
+            index += 1;
+            continue;
+        }
+
+        if c.is_ascii_alphanumeric() || c == '_' {
+            let start = index;
+            while index < chars.len()
+                && (chars[index].is_ascii_alphanumeric() || chars[index] == '_')
+            {
+                index += 1;
+            }
+            let word: String = chars[start..index].iter().collect();
+
+            let kind = if c.is_ascii_digit() {
+                TokenKind::Number(
+                    parse_number(&word)
+                        .ok_or_else(|| error(column, format!("invalid number '{}'", word)))?,
+                )
+            } else {
+                TokenKind::Name(word)
+            };
+            tokens.push(Token { kind, column });
+            continue;
+        }
+
+        let rest: String = chars[index..].iter().take(2).collect();
+        let symbol = BINARY_OPS
+            .iter()
+            .map(|(text, ..)| *text)
+            .chain(PUNCTUATION)
+            .find(|text| rest.starts_with(text))
+            .ok_or_else(|| error(column, format!("unexpected character '{}'", c)))?;
+
+        tokens.push(Token {
+            kind: TokenKind::Symbol(symbol),
+            column,
+        });
+        index += symbol.len();
+    }
+
+    Ok(tokens)
+}
+
+// Helper function to parse a decimal, 0x hexadecimal or 0b binary number
+fn parse_number(text: &str) -> Option<i64> {
+    let lower = text.to_ascii_lowercase();
+
+    if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+fn error(column: usize, message: String) -> ExprError {
+    ExprError { column, message }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Number(value) => write!(f, "number {}", value),
+            TokenKind::Name(name) => write!(f, "'{}'", name),
+            TokenKind::Symbol(symbol) => write!(f, "'{}'", symbol),
+        }
+    }
+}
+
+// =================================
+// Log messages
+// =================================
+
+/// Message printed by a logpoint, with `{expression}` placeholders
+///
+/// Values are printed in decimal, `{expression:x}` or `{expression:X}` print them in hex.
+/// `{{` and `}}` stand for literal braces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Decimal(Expr),
+    LowerHex(Expr),
+    UpperHex(Expr),
+}
+
+impl Template {
+    /// Parse a message, error columns count from the start of the message
+    pub fn parse(source: &str) -> Result<Template, ExprError> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.chars().enumerate().peekable();
+
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|&(_, c)| c == '{').is_some() => text.push('{'),
+                '}' if chars.next_if(|&(_, c)| c == '}').is_some() => text.push('}'),
+                '{' => {
+                    let mut inner = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '}')) => break,
+                            Some((_, c)) => inner.push(c),
+                            None => return Err(error(index + 1, "'{' without '}'".to_string())),
+                        }
+                    }
+
+                    let (expression, format) = inner.rsplit_once(':').unwrap_or((&inner, ""));
+                    let expr = Expr::parse(expression).map_err(|err| ExprError {
+                        column: err.column + index + 1,
+                        ..err
+                    })?;
+
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(match format {
+                        "" => Part::Decimal(expr),
+                        "x" => Part::LowerHex(expr),
+                        "X" => Part::UpperHex(expr),
+                        _ => return Err(error(index + 1, format!("unknown format '{}'", format))),
+                    });
+                }
+                '}' => return Err(error(index + 1, "'}' without '{'".to_string())),
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(Template {
+            source: source.to_string(),
+            parts,
+        })
+    }
+
+    /// Fill in the placeholders from the state of a chip8, `hits` is the value of `hits`
+    pub fn format(&self, chip: &Chip8, hits: u64) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Decimal(expr) => expr.eval(chip, hits).to_string(),
+                Part::LowerHex(expr) => format!("{:x}", expr.eval(chip, hits)),
+                Part::UpperHex(expr) => format!("{:X}", expr.eval(chip, hits)),
+            })
+            .collect()
+    }
+}
+
+// The source the message was parsed from
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Macro to shadow prelude with pretty_assertions
+    macro_rules! assert_eq {
+        ($($tt:tt)*) => {
+            pretty_assertions::assert_eq!($($tt)*)
+        };
+    }
+
+    // Helper function to create a chip8 in a known state
+    fn chip() -> Chip8 {
+        // V3 = 0x10, I = 0x310, [0x312] = 7, DT = 7 and inside a subroutine call
+        let mut rom = vec![0; 0x120];
+        rom[..10].copy_from_slice(&[0x63, 0x10, 0xA3, 0x10, 0x60, 0x07, 0xF0, 0x15, 0x22, 0x0A]);
+        rom[0x112] = 7;
+
+        let mut chip = Chip8::new();
+        chip.init(&rom);
+        for _ in 0..5 {
+            chip.step().unwrap();
+        }
+        chip
+    }
+
+    // Helper function to parse and evaluate an expression
+    fn eval(source: &str) -> i64 {
+        Expr::parse(source).unwrap().eval(&chip(), 3)
+    }
+
+    #[test]
+    fn test_eval() {
+        let cases = [
+            ("v3 == 0x10 && i > 0x300 && [i + 2] != 0", 1),
+            ("v3 == 0x10 && [i + 3] != 0", 0),
+            ("V3 + i * 2", 0x10 + 0x310 * 2),
+            ("(v3 + 1) * 2", 0x22),
+            ("1 + 2 << 3", 24),
+            ("v0 & 3 == 3", 1),
+            ("1 || 0 && 0", 1),
+            ("-1 < 0", 1),
+            ("!0 + ~0", 0),
+            ("10 / 3 + 10 % 3 - 0b11", 1),
+            ("7 / 0 + 7 % 0", 0),
+            ("1 << 64", 0),
+            ("[0x10000] + [-1]", 0),
+            ("sp == 1 && pc == 0x20A", 1),
+            ("dt + st", 7),
+            ("hits >= 3", 1),
+            ("[i + 2] >= 7 >= 1", 1),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(expected, eval(source), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |column, message: &str| {
+            Err(ExprError {
+                column,
+                message: message.to_string(),
+            })
+        };
+
+        let cases = [
+            ("", error(1, "missing value")),
+            ("v3 ==", error(6, "missing value")),
+            ("v3 = 1", error(4, "unexpected character '='")),
+            ("v16 == 1", error(1, "unknown name 'v16'")),
+            ("[i + 2", error(1, "'[' without ']'")),
+            ("(1 + 2", error(1, "'(' without ')'")),
+            ("1 2", error(3, "unexpected number 2")),
+            ("0x1g", error(1, "invalid number '0x1g'")),
+            ("1 + )", error(5, "unexpected ')'")),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(expected, Expr::parse(source), "{}", source);
+        }
+        assert_eq!("v3 == 1", Expr::parse("  v3 == 1 ").unwrap().to_string());
+    }
+
+    #[test]
+    fn test_template() {
+        let template =
+            Template::parse("V3={v3} I={i:X} i={i:x} [I+2]={[i + 2]} {{{hits}}}").unwrap();
+
+        assert_eq!("V3=16 I=310 i=310 [I+2]=7 {3}", template.format(&chip(), 3));
+        assert_eq!(
+            "V3={v3} I={i:X} i={i:x} [I+2]={[i + 2]} {{{hits}}}",
+            template.to_string()
+        );
+
+        let error = |column, message: &str| {
+            Err(ExprError {
+                column,
+                message: message.to_string(),
+            })
+        };
+        assert_eq!(error(5, "'{' without '}'"), Template::parse("x = {v0"));
+        assert_eq!(error(3, "'}' without '{'"), Template::parse("x }"));
+        assert_eq!(
+            error(5, "unknown format 'd'"),
+            Template::parse("x = {v0:d}")
+        );
+        assert_eq!(
+            error(10, "unknown name 'v16'"),
+            Template::parse("x = {1 + v16}")
+        );
+    }
+}
@@ -0,0 +1,37 @@
+pub mod terminal;
+
+use std::io;
+
+use chip8::{Chip8, SoundEvent};
+
+// =================================
+// Frontends presenting a running ROM
+// =================================
+
+pub trait Frontend {
+    // Feed host input into the chip8, returns false if the user asked to quit
+    fn poll_input(&mut self, chip: &mut Chip8) -> io::Result<bool>;
+
+    // Present the state of the chip8 after a frame
+    fn present(&mut self, chip: &Chip8, sound: Option<SoundEvent>) -> io::Result<()>;
+
+    // Whether frames should be paced to the timer frequency
+    fn realtime(&self) -> bool;
+}
+
+// Frontend without any display or input, runs as fast as possible
+pub struct HeadlessFrontend;
+
+impl Frontend for HeadlessFrontend {
+    fn poll_input(&mut self, _chip: &mut Chip8) -> io::Result<bool> {
+        Ok(true)
+    }
+
+    fn present(&mut self, _chip: &Chip8, _sound: Option<SoundEvent>) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn realtime(&self) -> bool {
+        false
+    }
+}
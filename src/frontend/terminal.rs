@@ -0,0 +1,339 @@
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{cursor, execute, queue, style, terminal};
+
+use chip8::{Chip8, SoundEvent};
+
+use crate::frontend::Frontend;
+
+// Terminals without release events only report presses and auto-repeats, so a key counts as
+// released once it has not been reported for this long. Has to exceed the delay before the first
+// auto-repeat, commonly 250-660 ms, or a held key would be released and pressed again.
+const KEY_RELEASE_TIMEOUT: Duration = Duration::from_millis(700);
+
+// =================================
+// Terminal frontend
+// =================================
+
+// Draws the display with Unicode half-blocks, two pixels per character cell
+pub struct TerminalFrontend {
+    stdout: Stdout,
+    // Rows currently on screen, only changed rows get redrawn
+    screen: Vec<String>,
+    keys: KeyState,
+    // Whether the terminal reports key releases
+    release_events: bool,
+}
+
+impl TerminalFrontend {
+    // Put the terminal into raw mode on an alternate screen
+    pub fn new() -> io::Result<TerminalFrontend> {
+        let mut stdout = io::stdout();
+
+        terminal::enable_raw_mode()?;
+        execute!(
+            stdout,
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            terminal::Clear(terminal::ClearType::All)
+        )?;
+
+        let release_events = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if release_events {
+            execute!(
+                stdout,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        Ok(TerminalFrontend {
+            stdout,
+            screen: Vec::new(),
+            keys: KeyState::default(),
+            release_events,
+        })
+    }
+}
+
+impl Drop for TerminalFrontend {
+    fn drop(&mut self) {
+        // Best effort, there is nothing left to do if restoring the terminal fails
+        if self.release_events {
+            let _ = execute!(self.stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(self.stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl Frontend for TerminalFrontend {
+    fn poll_input(&mut self, chip: &mut Chip8) -> io::Result<bool> {
+        let now = Instant::now();
+
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+
+            if is_quit(&key) {
+                return Ok(false);
+            }
+
+            let KeyCode::Char(c) = key.code else {
+                continue;
+            };
+            let Some(chip_key) = map_key(c) else {
+                continue;
+            };
+
+            match key.kind {
+                KeyEventKind::Press | KeyEventKind::Repeat => self.keys.press(chip_key, now),
+                KeyEventKind::Release => self.keys.release(chip_key),
+            }
+        }
+
+        if !self.release_events {
+            self.keys.expire(now, KEY_RELEASE_TIMEOUT);
+        }
+
+        for key in 0..16 {
+            if self.keys.is_pressed(key) {
+                chip.press_key(key);
+            } else {
+                chip.release_key(key);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn present(&mut self, chip: &Chip8, sound: Option<SoundEvent>) -> io::Result<()> {
+        let rows = render_rows(
+            chip.framebuffer(),
+            chip.display_width(),
+            chip.display_height(),
+        );
+
+        // Switching resolution leaves parts of the old picture behind
+        if rows.len() != self.screen.len() {
+            queue!(self.stdout, terminal::Clear(terminal::ClearType::All))?;
+            self.screen.clear();
+        }
+
+        for (line, row) in rows.iter().enumerate() {
+            if self.screen.get(line) != Some(row) {
+                queue!(
+                    self.stdout,
+                    cursor::MoveTo(0, line as u16),
+                    style::Print(row)
+                )?;
+            }
+        }
+        self.screen = rows;
+
+        // Ring the terminal bell when the beeper starts
+        if sound == Some(SoundEvent::Started) {
+            queue!(self.stdout, style::Print('\x07'))?;
+        }
+
+        self.stdout.flush()
+    }
+
+    fn realtime(&self) -> bool {
+        true
+    }
+}
+
+// Escape or Ctrl+C quits
+fn is_quit(key: &KeyEvent) -> bool {
+    key.kind != KeyEventKind::Release
+        && (key.code == KeyCode::Esc
+            || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)))
+}
+
+// Map the left hand side of a QWERTY keyboard onto the hex keypad
+// 1 2 3 4    1 2 3 C
+// Q W E R    4 5 6 D
+// A S D F    7 8 9 E
+// Z X C V    A 0 B F
+fn map_key(c: char) -> Option<u8> {
+    let key = match c.to_ascii_lowercase() {
+        '1' => 0x1,
+        '2' => 0x2,
+        '3' => 0x3,
+        '4' => 0xC,
+        'q' => 0x4,
+        'w' => 0x5,
+        'e' => 0x6,
+        'r' => 0xD,
+        'a' => 0x7,
+        's' => 0x8,
+        'd' => 0x9,
+        'f' => 0xE,
+        'z' => 0xA,
+        'x' => 0x0,
+        'c' => 0xB,
+        'v' => 0xF,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+// Render a framebuffer to text rows, each character covers two vertically adjacent pixels
+fn render_rows(framebuffer: &[u8], width: usize, height: usize) -> Vec<String> {
+    (0..height)
+        .step_by(2)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    let top = framebuffer[y * width + x] != 0;
+                    let bottom = y + 1 < height && framebuffer[(y + 1) * width + x] != 0;
+
+                    match (top, bottom) {
+                        (false, false) => ' ',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (true, true) => '█',
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Pressed keys and when they were last reported
+#[derive(Default)]
+struct KeyState {
+    last_seen: [Option<Instant>; 16],
+}
+
+impl KeyState {
+    fn press(&mut self, key: u8, now: Instant) {
+        self.last_seen[key as usize] = Some(now);
+    }
+
+    fn release(&mut self, key: u8) {
+        self.last_seen[key as usize] = None;
+    }
+
+    // Release all keys that have not been reported within the timeout
+    fn expire(&mut self, now: Instant, timeout: Duration) {
+        for seen in self.last_seen.iter_mut() {
+            if seen.is_some_and(|at| now.duration_since(at) >= timeout) {
+                *seen = None;
+            }
+        }
+    }
+
+    fn is_pressed(&self, key: u8) -> bool {
+        self.last_seen[key as usize].is_some()
+    }
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chip8::chip8::{HIRES_HEIGHT, HIRES_WIDTH};
+
+    #[test]
+    fn test_map_key() {
+        let layout = "1234qwerasdfzxcv";
+        let keypad = [
+            0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+        ];
+
+        for (c, key) in layout.chars().zip(keypad) {
+            assert_eq!(Some(key), map_key(c), "{}", c);
+            assert_eq!(Some(key), map_key(c.to_ascii_uppercase()), "{}", c);
+        }
+
+        assert_eq!(None, map_key('5'));
+        assert_eq!(None, map_key('p'));
+    }
+
+    #[test]
+    fn test_render_rows() {
+        // 2x4 framebuffer: top, bottom, both and no pixel in the first column
+        let framebuffer = [
+            1, 0, //
+            0, 0, //
+            0, 1, //
+            1, 1, //
+        ];
+
+        let rows = render_rows(&framebuffer, 2, 4);
+
+        assert_eq!(vec!["▀ ".to_string(), "▄█".to_string()], rows);
+    }
+
+    #[test]
+    fn test_render_rows_full_display() {
+        let framebuffer = [0; HIRES_WIDTH * HIRES_HEIGHT];
+
+        let rows = render_rows(&framebuffer, HIRES_WIDTH, HIRES_HEIGHT);
+
+        assert_eq!(HIRES_HEIGHT / 2, rows.len());
+        assert!(rows.iter().all(|row| row.chars().count() == HIRES_WIDTH));
+    }
+
+    #[test]
+    fn test_key_state_release() {
+        let mut keys = KeyState::default();
+        let now = Instant::now();
+
+        keys.press(0xA, now);
+        assert!(keys.is_pressed(0xA));
+
+        keys.release(0xA);
+        assert!(!keys.is_pressed(0xA));
+    }
+
+    #[test]
+    fn test_key_state_expire() {
+        let mut keys = KeyState::default();
+        let start = Instant::now();
+        let timeout = Duration::from_millis(100);
+
+        keys.press(0x1, start);
+        keys.press(0x2, start + Duration::from_millis(80));
+
+        // Only the key without a recent report gets released
+        keys.expire(start + Duration::from_millis(120), timeout);
+        assert!(!keys.is_pressed(0x1));
+        assert!(keys.is_pressed(0x2));
+    }
+
+    #[test]
+    fn test_key_state_auto_repeat() {
+        let mut keys = KeyState::default();
+        let start = Instant::now();
+        let first_repeat = start + Duration::from_millis(300);
+
+        // Held key stays pressed until the first auto-repeat arrives
+        keys.press(0x5, start);
+        keys.expire(first_repeat - Duration::from_millis(1), KEY_RELEASE_TIMEOUT);
+        assert!(keys.is_pressed(0x5));
+
+        keys.press(0x5, first_repeat);
+        keys.expire(
+            first_repeat + Duration::from_millis(30),
+            KEY_RELEASE_TIMEOUT,
+        );
+        assert!(keys.is_pressed(0x5));
+
+        // Released once the reports stop
+        keys.expire(first_repeat + KEY_RELEASE_TIMEOUT, KEY_RELEASE_TIMEOUT);
+        assert!(!keys.is_pressed(0x5));
+    }
+}
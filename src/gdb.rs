@@ -0,0 +1,803 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+use crate::chip8::Chip8;
+use crate::debugger::{Debugger, Stop, WatchAccess, WatchTarget, Watchpoint};
+use crate::error::Chip8ErrorKind;
+
+// =================================
+// GDB remote serial protocol stub
+// =================================
+
+// Serves a single gdb or lldb connection over TCP. There is no CHIP-8 architecture in gdb, so the
+// registers are described by target.xml: V0-VF, I, PC, SP, DT and ST in this order, the 16-bit
+// ones little-endian. Target memory is the whole address space of the chip8.
+//
+// Breakpoints (Z0/Z1) and watchpoints (Z2-Z4) map onto the ones of the debugger. `s` executes a
+// single instruction and `c` runs in slices, looking for the interrupt gdb sends on Ctrl-C in
+// between. As gdb has no notion of a keypad, `monitor press <key>` and `monitor release <key>`
+// operate it.
+
+/// Largest packet the stub accepts, announced to gdb in `qSupported`
+pub const PACKET_SIZE: usize = 0x1000;
+
+// Byte gdb sends to interrupt a running target
+const INTERRUPT: u8 = 0x03;
+
+// Instructions continue runs between looking for an interrupt
+const RUN_SLICE: u64 = 10_000;
+
+// Time to wait for an interrupt while the program waits for a key
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// Registers in the order of the g packet: V0-VF, I, PC, SP, DT and ST
+const REGISTER_COUNT: usize = 21;
+const REGISTER_I: usize = 16;
+const REGISTER_PC: usize = 17;
+const REGISTER_SP: usize = 18;
+const REGISTER_DT: usize = 19;
+const REGISTER_ST: usize = 20;
+
+const ERROR: &str = "E01";
+const SIGINT: &str = "S02";
+const SIGILL: &str = "S04";
+const SIGTRAP: &str = "S05";
+const SIGSEGV: &str = "S0b";
+
+/// Serve gdb on `stream` until it detaches, kills the target or disconnects
+pub fn serve(debugger: &mut Debugger, stream: TcpStream) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut session = Session {
+        debugger,
+        writer: stream.try_clone()?,
+        reader: BufReader::new(stream),
+        acknowledge: true,
+        watches: BTreeMap::new(),
+        last_stop: SIGTRAP.to_string(),
+        closed: false,
+    };
+
+    while !session.closed {
+        let Some(packet) = session.read_packet()? else {
+            return Ok(());
+        };
+
+        if let Some(reply) = session.handle(&packet)? {
+            session.send_packet(&reply)?;
+        }
+    }
+
+    Ok(())
+}
+
+// State of a connection
+struct Session<'a> {
+    debugger: &'a mut Debugger,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    // Packets are acknowledged until gdb asks for QStartNoAckMode
+    acknowledge: bool,
+    // Watchpoints set by gdb by debugger number, with their name in stop replies, address, length
+    watches: BTreeMap<usize, (&'static str, u16, u16)>,
+    // Reply to ?
+    last_stop: String,
+    closed: bool,
+}
+
+impl Session<'_> {
+    // Next packet without framing, None once gdb disconnected
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            // Skips acknowledgements and interrupts of a target that already stopped
+            let mut byte = [0];
+            if self.reader.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            if byte[0] != b'$' {
+                continue;
+            }
+
+            let mut data = Vec::new();
+            self.reader.read_until(b'#', &mut data)?;
+            if data.pop() != Some(b'#') {
+                return Ok(None);
+            }
+            let mut checksum = [0; 2];
+            self.reader.read_exact(&mut checksum)?;
+
+            let valid = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|text| u8::from_str_radix(text, 16).ok())
+                .is_some_and(|checksum| checksum == checksum_of(&data));
+            if self.acknowledge {
+                self.writer.write_all(if valid { b"+" } else { b"-" })?;
+            }
+            if valid {
+                return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+            }
+        }
+    }
+
+    fn send_packet(&mut self, data: &str) -> io::Result<()> {
+        let mut escaped = Vec::with_capacity(data.len());
+        for byte in data.bytes() {
+            if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+                escaped.extend([b'}', byte ^ 0x20]);
+            } else {
+                escaped.push(byte);
+            }
+        }
+
+        let mut packet = vec![b'$'];
+        packet.extend(&escaped);
+        packet.extend(format!("#{:02x}", checksum_of(&escaped)).bytes());
+        self.writer.write_all(&packet)?;
+        self.writer.flush()
+    }
+
+    // Reply to a packet, None if gdb expects none
+    fn handle(&mut self, packet: &str) -> io::Result<Option<String>> {
+        let args = packet.get(1..).unwrap_or("");
+
+        let reply = match packet.bytes().next() {
+            Some(b'?') => Some(self.last_stop.clone()),
+            Some(b'g') => Some(self.read_registers()),
+            Some(b'G') => self.write_registers(args),
+            Some(b'p') => self.read_register(args),
+            Some(b'P') => self.write_register(args),
+            Some(b'm') => self.read_memory(args),
+            Some(b'M') => self.write_memory(args),
+            Some(b'Z') => self.update_point(args, true),
+            Some(b'z') => self.update_point(args, false),
+            Some(b's') if self.jump(args) => {
+                let stop = self.debugger.step();
+                Some(self.stopped(stop))
+            }
+            Some(b'c') if self.jump(args) => {
+                let stop = self.resume()?;
+                Some(stop)
+            }
+            Some(b's' | b'c') => None,
+            Some(b'H') => Some("OK".to_string()),
+            Some(b'q' | b'Q') => Some(self.query(packet)),
+            Some(b'D') => {
+                self.closed = true;
+                Some("OK".to_string())
+            }
+            Some(b'k') => {
+                self.closed = true;
+                return Ok(None);
+            }
+            // Anything else is unsupported, which gdb expects as an empty reply
+            _ => Some(String::new()),
+        };
+
+        Ok(Some(reply.unwrap_or_else(|| ERROR.to_string())))
+    }
+
+    // Helper function to continue at the optional address of s and c, false if it is invalid
+    fn jump(&mut self, args: &str) -> bool {
+        if args.is_empty() {
+            return true;
+        }
+
+        match parse_address(self.debugger.chip(), args) {
+            Some(address) => {
+                self.debugger.chip_mut().set_pc(address);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Run until something stops the program or gdb interrupts it
+    fn resume(&mut self) -> io::Result<String> {
+        loop {
+            match self.debugger.resume(RUN_SLICE) {
+                Stop::Limit => {}
+                Stop::WaitingForKey => thread::sleep(KEY_POLL_INTERVAL),
+                stop => return Ok(self.stopped(stop)),
+            }
+
+            if self.interrupted()? {
+                self.last_stop = SIGINT.to_string();
+                return Ok(SIGINT.to_string());
+            }
+        }
+    }
+
+    // Helper function to look for an interrupt without blocking
+    fn interrupted(&mut self) -> io::Result<bool> {
+        loop {
+            if self.reader.buffer().is_empty() {
+                self.reader.get_ref().set_nonblocking(true)?;
+                let filled = self.reader.fill_buf().map(|buffer| buffer.len());
+                self.reader.get_ref().set_nonblocking(false)?;
+
+                match filled {
+                    Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                    Ok(_) => {}
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                    Err(err) => return Err(err),
+                }
+            }
+
+            // Late acknowledgements may come first, packets wait until the target stopped
+            match self.reader.buffer()[0] {
+                INTERRUPT => {
+                    self.reader.consume(1);
+                    return Ok(true);
+                }
+                b'+' | b'-' => self.reader.consume(1),
+                _ => return Ok(false),
+            }
+        }
+    }
+
+    // Helper function to turn a stop into a stop reply and remember it for ?
+    fn stopped(&mut self, stop: Stop) -> String {
+        let reply = match stop {
+            Stop::Watchpoint(hit) => match (self.watches.get(&hit.id), hit.target) {
+                (Some((name, ..)), WatchTarget::Memory(address)) => {
+                    format!("T05{}:{:x};", name, address)
+                }
+                _ => SIGTRAP.to_string(),
+            },
+            Stop::Exited => "W00".to_string(),
+            Stop::Fault(error) => match error.kind {
+                Chip8ErrorKind::UnknownOpcode | Chip8ErrorKind::InvalidKey(_) => SIGILL,
+                _ => SIGSEGV,
+            }
+            .to_string(),
+            Stop::Stepped | Stop::Breakpoint(_) | Stop::WaitingForKey | Stop::Limit => {
+                SIGTRAP.to_string()
+            }
+        };
+
+        self.last_stop = reply.clone();
+        reply
+    }
+
+    fn read_registers(&self) -> String {
+        let chip = self.debugger.chip();
+
+        (0..REGISTER_COUNT)
+            .map(|register| encode_register(register, register_value(chip, register)))
+            .collect()
+    }
+
+    fn write_registers(&mut self, args: &str) -> Option<String> {
+        let bytes = decode_hex(args)?;
+        if bytes.len() != (0..REGISTER_COUNT).map(register_size).sum() {
+            return None;
+        }
+
+        let mut values = Vec::with_capacity(REGISTER_COUNT);
+        let mut offset = 0;
+        for register in 0..REGISTER_COUNT {
+            let size = register_size(register);
+            values.push(decode_register(&bytes[offset..offset + size]));
+            offset += size;
+        }
+
+        let chip = self.debugger.chip_mut();
+        if values[REGISTER_SP] != chip.sp() {
+            return None;
+        }
+        for (register, value) in values.into_iter().enumerate() {
+            set_register(chip, register, value);
+        }
+        Some("OK".to_string())
+    }
+
+    fn read_register(&self, args: &str) -> Option<String> {
+        let register = usize::from_str_radix(args, 16).ok()?;
+        if register >= REGISTER_COUNT {
+            return None;
+        }
+
+        let value = register_value(self.debugger.chip(), register);
+        Some(encode_register(register, value))
+    }
+
+    fn write_register(&mut self, args: &str) -> Option<String> {
+        let (register, value) = args.split_once('=')?;
+        let register = usize::from_str_radix(register, 16).ok()?;
+        let bytes = decode_hex(value)?;
+        if register >= REGISTER_COUNT || bytes.len() != register_size(register) {
+            return None;
+        }
+
+        // The stack pointer only follows calls and returns
+        let chip = self.debugger.chip_mut();
+        let value = decode_register(&bytes);
+        if register == REGISTER_SP && value != chip.sp() {
+            return None;
+        }
+        set_register(chip, register, value);
+        Some("OK".to_string())
+    }
+
+    // Memory beyond the address space is cut off, like a partial read
+    fn read_memory(&self, args: &str) -> Option<String> {
+        let (address, length) = args.split_once(',')?;
+        let memory = self.debugger.chip().memory();
+        let start = parse_address(self.debugger.chip(), address)? as usize;
+        let length = usize::from_str_radix(length, 16).ok()?.min(PACKET_SIZE / 2);
+
+        let end = start.saturating_add(length).min(memory.len());
+        Some(encode_hex(&memory[start..end]))
+    }
+
+    fn write_memory(&mut self, args: &str) -> Option<String> {
+        let (range, data) = args.split_once(':')?;
+        let (address, length) = range.split_once(',')?;
+        let start = parse_address(self.debugger.chip(), address)? as usize;
+        let bytes = decode_hex(data)?;
+        if usize::from_str_radix(length, 16).ok()? != bytes.len() {
+            return None;
+        }
+
+        let memory = self.debugger.chip_mut().memory_mut();
+        memory
+            .get_mut(start..start + bytes.len())?
+            .copy_from_slice(&bytes);
+        Some("OK".to_string())
+    }
+
+    // Insert or remove a breakpoint or watchpoint, e.g. Z2,300,2 watches writes of 0300-0301
+    fn update_point(&mut self, args: &str, insert: bool) -> Option<String> {
+        let mut fields = args.split(',');
+        let (kind, address, length) = (fields.next()?, fields.next()?, fields.next()?);
+        let address = parse_address(self.debugger.chip(), address)?;
+        let length = u16::from_str_radix(length, 16).ok()?;
+
+        let name = match kind {
+            "0" | "1" => {
+                if insert {
+                    self.debugger.set_breakpoint(address);
+                } else {
+                    self.debugger.clear_breakpoint(address);
+                }
+                return Some("OK".to_string());
+            }
+            "2" => "watch",
+            "3" => "rwatch",
+            "4" => "awatch",
+            _ => return Some(String::new()),
+        };
+
+        if !insert {
+            let id = self
+                .watches
+                .iter()
+                .find(|&(_, &watch)| watch == (name, address, length))
+                .map(|(&id, _)| id);
+            if let Some(id) = id {
+                self.watches.remove(&id);
+                self.debugger.remove_watchpoint(id);
+            }
+            return Some("OK".to_string());
+        }
+
+        let access = match kind {
+            "2" => WatchAccess::Write,
+            "3" => WatchAccess::Read,
+            _ => WatchAccess::ReadWrite,
+        };
+        let end = address.checked_add(length.checked_sub(1)?)?;
+        let id = self.debugger.add_watchpoint(Watchpoint::Memory {
+            start: address,
+            end,
+            access,
+        })?;
+        self.watches.insert(id, (name, address, length));
+        Some("OK".to_string())
+    }
+
+    fn query(&mut self, packet: &str) -> String {
+        if packet.starts_with("qSupported") {
+            return format!(
+                "PacketSize={:x};QStartNoAckMode+;qXfer:features:read+",
+                PACKET_SIZE
+            );
+        }
+        if packet == "QStartNoAckMode" {
+            self.acknowledge = false;
+            return "OK".to_string();
+        }
+        if packet == "qAttached" {
+            return "1".to_string();
+        }
+        if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            return read_target_xml(range).unwrap_or_else(|| ERROR.to_string());
+        }
+        if let Some(command) = packet.strip_prefix("qRcmd,") {
+            return match decode_hex(command).and_then(|command| String::from_utf8(command).ok()) {
+                Some(command) => encode_hex(self.monitor(&command).as_bytes()),
+                None => ERROR.to_string(),
+            };
+        }
+
+        String::new()
+    }
+
+    // Run a monitor command, returns its output
+    fn monitor(&mut self, command: &str) -> String {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let key = match words.as_slice() {
+            [_, key] => u8::from_str_radix(key, 16).ok().filter(|&key| key <= 0xF),
+            _ => None,
+        };
+
+        match (words.first(), key) {
+            (Some(&"press"), Some(key)) => {
+                self.debugger.chip_mut().press_key(key);
+                format!("Key {:X} down\n", key)
+            }
+            (Some(&"release"), Some(key)) => {
+                self.debugger.chip_mut().release_key(key);
+                format!("Key {:X} up\n", key)
+            }
+            _ => "Commands: press <key>, release <key> with keys 0-F\n".to_string(),
+        }
+    }
+}
+
+// Helper function to slice the target description for qXfer, range is offset,length
+fn read_target_xml(range: &str) -> Option<String> {
+    let (offset, length) = range.split_once(',')?;
+    let offset = usize::from_str_radix(offset, 16).ok()?;
+    let length = usize::from_str_radix(length, 16).ok()?;
+
+    let xml = target_xml();
+    let chunk = xml.get(offset.min(xml.len())..)?;
+    if chunk.len() > length {
+        Some(format!("m{}", chunk.get(..length)?))
+    } else {
+        Some(format!("l{}", chunk))
+    }
+}
+
+fn target_xml() -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\"?>\n\
+         <!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n\
+         <target version=\"1.0\">\n\
+         <feature name=\"org.chip8.core\">\n",
+    );
+
+    for register in 0..REGISTER_COUNT {
+        let (name, kind) = match register {
+            REGISTER_I => ("i".to_string(), "data_ptr"),
+            REGISTER_PC => ("pc".to_string(), "code_ptr"),
+            REGISTER_SP => ("sp".to_string(), "uint8"),
+            REGISTER_DT => ("dt".to_string(), "uint8"),
+            REGISTER_ST => ("st".to_string(), "uint8"),
+            _ => (format!("v{:x}", register), "uint8"),
+        };
+        let _ = writeln!(
+            xml,
+            "<reg name=\"{}\" bitsize=\"{}\" type=\"{}\" regnum=\"{}\"/>",
+            name,
+            register_size(register) * 8,
+            kind,
+            register
+        );
+    }
+
+    xml.push_str("</feature>\n</target>\n");
+    xml
+}
+
+fn register_size(register: usize) -> usize {
+    match register {
+        REGISTER_I | REGISTER_PC => 2,
+        _ => 1,
+    }
+}
+
+fn register_value(chip: &Chip8, register: usize) -> u16 {
+    match register {
+        REGISTER_I => chip.index(),
+        REGISTER_PC => chip.pc(),
+        REGISTER_SP => chip.sp(),
+        REGISTER_DT => chip.delay_timer() as u16,
+        REGISTER_ST => chip.sound_timer() as u16,
+        _ => chip.registers()[register] as u16,
+    }
+}
+
+// The stack pointer can't be set, values are truncated to the size of the register
+fn set_register(chip: &mut Chip8, register: usize, value: u16) {
+    match register {
+        REGISTER_I => chip.set_index(value),
+        REGISTER_PC => chip.set_pc(value),
+        REGISTER_SP => {}
+        REGISTER_DT => chip.set_timers(value as u8, chip.sound_timer()),
+        REGISTER_ST => chip.set_timers(chip.delay_timer(), value as u8),
+        _ => chip.registers_mut()[register] = value as u8,
+    }
+}
+
+fn encode_register(register: usize, value: u16) -> String {
+    encode_hex(&value.to_le_bytes()[..register_size(register)])
+}
+
+fn decode_register(bytes: &[u8]) -> u16 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| value << 8 | byte as u16)
+}
+
+fn parse_address(chip: &Chip8, text: &str) -> Option<u16> {
+    let address = usize::from_str_radix(text, 16).ok()?;
+    if address < chip.memory().len() {
+        Some(address as u16)
+    } else {
+        None
+    }
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.is_ascii() || !text.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&text[index..index + 2], 16).ok())
+        .collect()
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::chip8::Config;
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    // Macro to shadow prelude with pretty_assertions
+    macro_rules! assert_eq {
+        ($($tt:tt)*) => {
+            pretty_assertions::assert_eq!($($tt)*)
+        };
+    }
+
+    // Minimal gdb side of the protocol
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+        acknowledge: bool,
+    }
+
+    impl Client {
+        // Send a packet and return the reply
+        fn request(&mut self, data: &str) -> String {
+            let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+            self.writer.write_all(packet.as_bytes()).unwrap();
+            if self.acknowledge {
+                assert_eq!(b'+', self.read_byte());
+            }
+            if data == "k" {
+                return String::new();
+            }
+
+            assert_eq!(b'$', self.read_byte());
+            let mut reply = Vec::new();
+            self.reader.read_until(b'#', &mut reply).unwrap();
+            reply.pop();
+            let mut checksum = [0; 2];
+            self.reader.read_exact(&mut checksum).unwrap();
+            assert_eq!(format!("{:02x}", checksum_of(&reply)).as_bytes(), &checksum);
+            if self.acknowledge {
+                self.writer.write_all(b"+").unwrap();
+            }
+            if data == "QStartNoAckMode" {
+                self.acknowledge = false;
+            }
+
+            String::from_utf8(reply).unwrap()
+        }
+
+        fn read_byte(&mut self) -> u8 {
+            let mut byte = [0];
+            self.reader.read_exact(&mut byte).unwrap();
+            byte[0]
+        }
+    }
+
+    // Helper function to serve a debugger for an assembly program on a loopback port
+    fn connect(source: &str) -> (Client, JoinHandle<Debugger>) {
+        let mut chip = Chip8::with_config(Config {
+            seed: Some(0),
+            ..Config::default()
+        });
+        chip.init(&assemble(source).unwrap());
+        let mut debugger = Debugger::new(chip, 10);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve(&mut debugger, stream).unwrap();
+            debugger
+        });
+
+        let stream = TcpStream::connect(address).unwrap();
+        let client = Client {
+            reader: BufReader::new(stream.try_clone().unwrap()),
+            writer: stream,
+            acknowledge: true,
+        };
+        (client, server)
+    }
+
+    #[test]
+    fn test_registers_and_memory() {
+        // Prepare setup
+        let (mut gdb, server) = connect("LD V3, 0x2A\nLD I, 0x300\nCALL inner\ninner: JP inner");
+        assert_eq!(
+            "PacketSize=1000;QStartNoAckMode+;qXfer:features:read+",
+            gdb.request("qSupported:multiprocess+;swbreak+")
+        );
+        assert_eq!("OK", gdb.request("QStartNoAckMode"));
+        assert_eq!("S05", gdb.request("?"));
+
+        // Target description in chunks
+        let mut xml = String::new();
+        loop {
+            let chunk = gdb.request(&format!(
+                "qXfer:features:read:target.xml:{:x},40",
+                xml.len()
+            ));
+            xml.push_str(&chunk[1..]);
+            if chunk.starts_with('l') {
+                break;
+            }
+        }
+        assert_eq!(target_xml(), xml);
+        assert!(xml.contains("<reg name=\"pc\" bitsize=\"16\" type=\"code_ptr\" regnum=\"17\"/>"));
+
+        // Run cycle
+        for _ in 0..3 {
+            assert_eq!("S05", gdb.request("s"));
+        }
+
+        // Registers
+        let expected = format!("0000002a{}0003060201{}", "00".repeat(12), "0000");
+        assert_eq!(expected, gdb.request("g"));
+        assert_eq!("2a", gdb.request("p3"));
+        assert_eq!("0003", gdb.request("p10"));
+        assert_eq!("01", gdb.request("p12"));
+        assert_eq!("OK", gdb.request("P10=1003"));
+        assert_eq!("OK", gdb.request("P13=3c"));
+        assert_eq!("E01", gdb.request("P12=00"));
+        assert_eq!("E01", gdb.request("p15"));
+
+        // Memory
+        assert_eq!("632aa300", gdb.request("m200,4"));
+        assert_eq!("OK", gdb.request("M310,3:abcdef"));
+        assert_eq!("abcdef00", gdb.request("m310,4"));
+        assert_eq!("0000", gdb.request("mffe,10"));
+        assert_eq!("E01", gdb.request("m1000,1"));
+        assert_eq!("E01", gdb.request("Mffe,3:000000"));
+        assert_eq!("", gdb.request("vMustReplyEmpty"));
+        assert_eq!("", gdb.request("k"));
+
+        // Assert
+        let chip = server.join().unwrap().into_chip();
+        assert_eq!(0x310, chip.index());
+        assert_eq!(0x3C, chip.delay_timer());
+        assert_eq!(&[0xAB, 0xCD, 0xEF], &chip.memory()[0x310..0x313]);
+    }
+
+    #[test]
+    fn test_breakpoints_and_watchpoints() {
+        // Prepare setup
+        let (mut gdb, server) = connect(
+            "
+            loop:
+            ADD V0, 1       ; 0200
+            LD I, 0x300     ; 0202
+            LD [I], V0      ; 0204
+            LD V1, [I]      ; 0206
+            JP loop         ; 0208
+            ",
+        );
+
+        // Breakpoints
+        assert_eq!("OK", gdb.request("Z0,206,2"));
+        assert_eq!("S05", gdb.request("c"));
+        assert_eq!("0602", gdb.request("p11"));
+        assert_eq!("OK", gdb.request("z0,206,2"));
+        assert_eq!("OK", gdb.request("Z1,202,2"));
+        assert_eq!("S05", gdb.request("c"));
+        assert_eq!("0202", gdb.request("p11"));
+        assert_eq!("OK", gdb.request("z1,202,2"));
+
+        // Watchpoints stop after the access
+        assert_eq!("OK", gdb.request("Z2,300,1"));
+        assert_eq!("T05watch:300;", gdb.request("c"));
+        assert_eq!("0602", gdb.request("p11"));
+        assert_eq!("OK", gdb.request("Z3,300,1"));
+        assert_eq!("T05rwatch:300;", gdb.request("c"));
+        assert_eq!("OK", gdb.request("z2,300,1"));
+        assert_eq!("OK", gdb.request("z3,300,1"));
+        assert_eq!("OK", gdb.request("Z4,2ff,2"));
+        assert_eq!("T05awatch:300;", gdb.request("c"));
+        assert_eq!("T05awatch:300;", gdb.request("c"));
+        assert_eq!("T05awatch:300;", gdb.request("?"));
+        assert_eq!("OK", gdb.request("z4,2ff,2"));
+        assert_eq!("E01", gdb.request("Z2,fff,2"));
+        assert_eq!("", gdb.request("Z5,300,1"));
+
+        // Continue at another address, then interrupt the endless loop
+        gdb.writer.write_all(b"$c208#").unwrap();
+        gdb.writer
+            .write_all(format!("{:02x}", checksum_of(b"c208")).as_bytes())
+            .unwrap();
+        assert_eq!(b'+', gdb.read_byte());
+        thread::sleep(Duration::from_millis(20));
+        gdb.writer.write_all(&[INTERRUPT]).unwrap();
+        assert_eq!(b'$', gdb.read_byte());
+        assert_eq!(b'S', gdb.read_byte());
+        gdb.reader.read_until(b'#', &mut Vec::new()).unwrap();
+        gdb.reader.read_exact(&mut [0; 2]).unwrap();
+        gdb.writer.write_all(b"+").unwrap();
+        assert_eq!("OK", gdb.request("D"));
+
+        // Assert
+        let debugger = server.join().unwrap();
+        assert_eq!(0, debugger.breakpoints().count());
+        assert_eq!(0, debugger.watchpoints().count());
+    }
+
+    #[test]
+    fn test_keys_and_faults() {
+        // Prepare setup
+        let (mut gdb, server) = connect("LD V5, K\nLD F, V5\nDW 0xFFFF");
+        let monitor = |command: &str| format!("qRcmd,{}", encode_hex(command.as_bytes()));
+
+        // Run cycle
+        assert_eq!("S05", gdb.request("s"));
+        assert_eq!(
+            encode_hex(b"Key 7 down\n"),
+            gdb.request(&monitor("press 7"))
+        );
+        assert_eq!("S05", gdb.request("s"));
+        assert_eq!(
+            encode_hex(b"Key 7 up\n"),
+            gdb.request(&monitor("release 7"))
+        );
+        assert_eq!(
+            encode_hex(b"Commands: press <key>, release <key> with keys 0-F\n"),
+            gdb.request(&monitor("press 10"))
+        );
+        assert_eq!("S04", gdb.request("c"));
+        assert_eq!("07", gdb.request("p5"));
+        assert_eq!("", gdb.request("k"));
+
+        // Assert
+        let chip = server.join().unwrap().into_chip();
+        assert_eq!(0x204, chip.pc());
+    }
+}
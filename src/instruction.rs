@@ -0,0 +1,466 @@
+use std::fmt;
+
+// =================================
+// Decoded instructions
+// =================================
+
+// Registers are given by their index 0x0-0xF, nibbles and bytes as plain numbers
+
+/// A decoded instruction of CHIP-8, SUPER-CHIP or XO-CHIP
+///
+/// Decoding does not depend on the platform, every encoding of all three instruction sets maps
+/// to its instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    /// 0NNN: call machine language subroutine at NNN
+    Sys(u16),
+    /// 00E0: clear the screen
+    Cls,
+    /// 00EE: return from subroutine
+    Ret,
+    /// 00CN: scroll down N rows (SUPER-CHIP)
+    ScrollDown(u8),
+    /// 00DN: scroll up N rows (XO-CHIP)
+    ScrollUp(u8),
+    /// 00FB: scroll right 4 pixels (SUPER-CHIP)
+    ScrollRight,
+    /// 00FC: scroll left 4 pixels (SUPER-CHIP)
+    ScrollLeft,
+    /// 00FD: exit the interpreter (SUPER-CHIP)
+    Exit,
+    /// 00FE: switch to lo-res mode (SUPER-CHIP)
+    Low,
+    /// 00FF: switch to hi-res mode (SUPER-CHIP)
+    High,
+    /// 1NNN: jump to NNN
+    Jp(u16),
+    /// 2NNN: call subroutine at NNN
+    Call(u16),
+    /// 3XNN: skip if VX == NN
+    SeByte(u8, u8),
+    /// 4XNN: skip if VX != NN
+    SneByte(u8, u8),
+    /// 5XY0: skip if VX == VY
+    SeReg(u8, u8),
+    /// 5XY2: store VX to VY in memory at I (XO-CHIP)
+    Save(u8, u8),
+    /// 5XY3: load VX to VY from memory at I (XO-CHIP)
+    Load(u8, u8),
+    /// 6XNN: VX = NN
+    Ld(u8, u8),
+    /// 7XNN: VX += NN
+    AddByte(u8, u8),
+    /// 8XY0: VX = VY
+    LdReg(u8, u8),
+    /// 8XY1: VX |= VY
+    Or(u8, u8),
+    /// 8XY2: VX &= VY
+    And(u8, u8),
+    /// 8XY3: VX ^= VY
+    Xor(u8, u8),
+    /// 8XY4: VX += VY with carry in VF
+    AddReg(u8, u8),
+    /// 8XY5: VX -= VY with not borrow in VF
+    Sub(u8, u8),
+    /// 8XY6: shift right with the shifted out bit in VF
+    Shr(u8, u8),
+    /// 8XY7: VX = VY - VX with not borrow in VF
+    Subn(u8, u8),
+    /// 8XYE: shift left with the shifted out bit in VF
+    Shl(u8, u8),
+    /// 9XY0: skip if VX != VY
+    SneReg(u8, u8),
+    /// ANNN: I = NNN
+    LdI(u16),
+    /// BNNN: jump to NNN + V0 (or XNN + VX depending on quirks)
+    JpV0(u16),
+    /// CXNN: VX = random byte & NN
+    Rnd(u8, u8),
+    /// DXYN: draw N rows of sprite data from I at VX, VY
+    Drw { x: u8, y: u8, n: u8 },
+    /// EX9E: skip if key VX is pressed
+    Skp(u8),
+    /// EXA1: skip if key VX is not pressed
+    Sknp(u8),
+    /// F000 NNNN: I = NNNN, the address is the word following the opcode (XO-CHIP)
+    LdILong(u16),
+    /// FN01: select drawing planes N (XO-CHIP)
+    Plane(u8),
+    /// F002: load the audio pattern from I (XO-CHIP)
+    Audio,
+    /// FX07: VX = delay timer
+    LdVxDt(u8),
+    /// FX0A: wait for a key and store it in VX
+    LdVxK(u8),
+    /// FX15: delay timer = VX
+    LdDtVx(u8),
+    /// FX18: sound timer = VX
+    LdStVx(u8),
+    /// FX1E: I += VX
+    AddIVx(u8),
+    /// FX29: I = small font sprite of VX
+    LdFVx(u8),
+    /// FX30: I = big font sprite of VX (SUPER-CHIP)
+    LdHfVx(u8),
+    /// FX33: store BCD of VX at I
+    LdBVx(u8),
+    /// FX3A: audio pitch = VX (XO-CHIP)
+    Pitch(u8),
+    /// FX55: store V0 to VX in memory at I
+    LdIVx(u8),
+    /// FX65: load V0 to VX from memory at I
+    LdVxI(u8),
+    /// FX75: store V0 to VX in the RPL user flags (SUPER-CHIP)
+    LdRVx(u8),
+    /// FX85: load V0 to VX from the RPL user flags (SUPER-CHIP)
+    LdVxR(u8),
+    /// Encoding that is no instruction of any platform
+    Unknown(u16),
+}
+
+impl Instruction {
+    /// Size of the instruction in bytes, 4 for `F000 NNNN` and 2 otherwise
+    pub fn size(&self) -> u16 {
+        match self {
+            Instruction::LdILong(_) => 4,
+            _ => 2,
+        }
+    }
+
+    /// First word of the encoding, `F000` for [`Instruction::LdILong`]
+    pub fn opcode(&self) -> u16 {
+        let xy = |x: u8, y: u8| (x as u16) << 8 | (y as u16) << 4;
+        let xnn = |x: u8, nn: u8| (x as u16) << 8 | nn as u16;
+
+        match *self {
+            Instruction::Sys(addr) => addr & 0xFFF,
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::ScrollDown(n) => 0x00C0 | (n & 0xF) as u16,
+            Instruction::ScrollUp(n) => 0x00D0 | (n & 0xF) as u16,
+            Instruction::ScrollRight => 0x00FB,
+            Instruction::ScrollLeft => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::Low => 0x00FE,
+            Instruction::High => 0x00FF,
+            Instruction::Jp(addr) => 0x1000 | (addr & 0xFFF),
+            Instruction::Call(addr) => 0x2000 | (addr & 0xFFF),
+            Instruction::SeByte(x, nn) => 0x3000 | xnn(x & 0xF, nn),
+            Instruction::SneByte(x, nn) => 0x4000 | xnn(x & 0xF, nn),
+            Instruction::SeReg(x, y) => 0x5000 | xy(x & 0xF, y & 0xF),
+            Instruction::Save(x, y) => 0x5002 | xy(x & 0xF, y & 0xF),
+            Instruction::Load(x, y) => 0x5003 | xy(x & 0xF, y & 0xF),
+            Instruction::Ld(x, nn) => 0x6000 | xnn(x & 0xF, nn),
+            Instruction::AddByte(x, nn) => 0x7000 | xnn(x & 0xF, nn),
+            Instruction::LdReg(x, y) => 0x8000 | xy(x & 0xF, y & 0xF),
+            Instruction::Or(x, y) => 0x8001 | xy(x & 0xF, y & 0xF),
+            Instruction::And(x, y) => 0x8002 | xy(x & 0xF, y & 0xF),
+            Instruction::Xor(x, y) => 0x8003 | xy(x & 0xF, y & 0xF),
+            Instruction::AddReg(x, y) => 0x8004 | xy(x & 0xF, y & 0xF),
+            Instruction::Sub(x, y) => 0x8005 | xy(x & 0xF, y & 0xF),
+            Instruction::Shr(x, y) => 0x8006 | xy(x & 0xF, y & 0xF),
+            Instruction::Subn(x, y) => 0x8007 | xy(x & 0xF, y & 0xF),
+            Instruction::Shl(x, y) => 0x800E | xy(x & 0xF, y & 0xF),
+            Instruction::SneReg(x, y) => 0x9000 | xy(x & 0xF, y & 0xF),
+            Instruction::LdI(addr) => 0xA000 | (addr & 0xFFF),
+            Instruction::JpV0(addr) => 0xB000 | (addr & 0xFFF),
+            Instruction::Rnd(x, nn) => 0xC000 | xnn(x & 0xF, nn),
+            Instruction::Drw { x, y, n } => 0xD000 | xy(x & 0xF, y & 0xF) | (n & 0xF) as u16,
+            Instruction::Skp(x) => 0xE09E | xnn(x & 0xF, 0),
+            Instruction::Sknp(x) => 0xE0A1 | xnn(x & 0xF, 0),
+            Instruction::LdILong(_) => 0xF000,
+            Instruction::Plane(n) => 0xF001 | xnn(n & 0xF, 0),
+            Instruction::Audio => 0xF002,
+            Instruction::LdVxDt(x) => 0xF007 | xnn(x & 0xF, 0),
+            Instruction::LdVxK(x) => 0xF00A | xnn(x & 0xF, 0),
+            Instruction::LdDtVx(x) => 0xF015 | xnn(x & 0xF, 0),
+            Instruction::LdStVx(x) => 0xF018 | xnn(x & 0xF, 0),
+            Instruction::AddIVx(x) => 0xF01E | xnn(x & 0xF, 0),
+            Instruction::LdFVx(x) => 0xF029 | xnn(x & 0xF, 0),
+            Instruction::LdHfVx(x) => 0xF030 | xnn(x & 0xF, 0),
+            Instruction::LdBVx(x) => 0xF033 | xnn(x & 0xF, 0),
+            Instruction::Pitch(x) => 0xF03A | xnn(x & 0xF, 0),
+            Instruction::LdIVx(x) => 0xF055 | xnn(x & 0xF, 0),
+            Instruction::LdVxI(x) => 0xF065 | xnn(x & 0xF, 0),
+            Instruction::LdRVx(x) => 0xF075 | xnn(x & 0xF, 0),
+            Instruction::LdVxR(x) => 0xF085 | xnn(x & 0xF, 0),
+            Instruction::Unknown(opcode) => opcode,
+        }
+    }
+}
+
+/// Decode a single opcode
+///
+/// `F000` decodes to [`Instruction::LdILong`] with address 0, as its address is stored in the
+/// following word; use [`decode_at`] to decode from memory.
+pub fn decode(opcode: u16) -> Instruction {
+    let x = ((opcode >> 8) & 0xF) as u8;
+    let y = ((opcode >> 4) & 0xF) as u8;
+    let n = (opcode & 0xF) as u8;
+    let nn = (opcode & 0xFF) as u8;
+    let nnn = opcode & 0xFFF;
+
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00E0 => Instruction::Cls,
+            0x00EE => Instruction::Ret,
+            0x00C0..=0x00CF => Instruction::ScrollDown(n),
+            0x00D0..=0x00DF => Instruction::ScrollUp(n),
+            0x00FB => Instruction::ScrollRight,
+            0x00FC => Instruction::ScrollLeft,
+            0x00FD => Instruction::Exit,
+            0x00FE => Instruction::Low,
+            0x00FF => Instruction::High,
+            _ => Instruction::Sys(nnn),
+        },
+        0x1000 => Instruction::Jp(nnn),
+        0x2000 => Instruction::Call(nnn),
+        0x3000 => Instruction::SeByte(x, nn),
+        0x4000 => Instruction::SneByte(x, nn),
+        0x5000 => match n {
+            0x0 => Instruction::SeReg(x, y),
+            0x2 => Instruction::Save(x, y),
+            0x3 => Instruction::Load(x, y),
+            _ => Instruction::Unknown(opcode),
+        },
+        0x6000 => Instruction::Ld(x, nn),
+        0x7000 => Instruction::AddByte(x, nn),
+        0x8000 => match n {
+            0x0 => Instruction::LdReg(x, y),
+            0x1 => Instruction::Or(x, y),
+            0x2 => Instruction::And(x, y),
+            0x3 => Instruction::Xor(x, y),
+            0x4 => Instruction::AddReg(x, y),
+            0x5 => Instruction::Sub(x, y),
+            0x6 => Instruction::Shr(x, y),
+            0x7 => Instruction::Subn(x, y),
+            0xE => Instruction::Shl(x, y),
+            _ => Instruction::Unknown(opcode),
+        },
+        0x9000 => match n {
+            0x0 => Instruction::SneReg(x, y),
+            _ => Instruction::Unknown(opcode),
+        },
+        0xA000 => Instruction::LdI(nnn),
+        0xB000 => Instruction::JpV0(nnn),
+        0xC000 => Instruction::Rnd(x, nn),
+        0xD000 => Instruction::Drw { x, y, n },
+        0xE000 => match nn {
+            0x9E => Instruction::Skp(x),
+            0xA1 => Instruction::Sknp(x),
+            _ => Instruction::Unknown(opcode),
+        },
+        _ => match nn {
+            0x00 if x == 0 => Instruction::LdILong(0),
+            0x01 => Instruction::Plane(x),
+            0x02 if x == 0 => Instruction::Audio,
+            0x07 => Instruction::LdVxDt(x),
+            0x0A => Instruction::LdVxK(x),
+            0x15 => Instruction::LdDtVx(x),
+            0x18 => Instruction::LdStVx(x),
+            0x1E => Instruction::AddIVx(x),
+            0x29 => Instruction::LdFVx(x),
+            0x30 => Instruction::LdHfVx(x),
+            0x33 => Instruction::LdBVx(x),
+            0x3A => Instruction::Pitch(x),
+            0x55 => Instruction::LdIVx(x),
+            0x65 => Instruction::LdVxI(x),
+            0x75 => Instruction::LdRVx(x),
+            0x85 => Instruction::LdVxR(x),
+            _ => Instruction::Unknown(opcode),
+        },
+    }
+}
+
+/// Decode the instruction stored at `address`, None if its opcode exceeds memory
+///
+/// `F000` without a following word decodes to [`Instruction::Unknown`].
+pub fn decode_at(memory: &[u8], address: usize) -> Option<Instruction> {
+    let word = |address: usize| {
+        let bytes = memory.get(address..address.checked_add(2)?)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    };
+
+    let opcode = word(address)?;
+    Some(match decode(opcode) {
+        Instruction::LdILong(_) => match word(address + 2) {
+            Some(target) => Instruction::LdILong(target),
+            None => Instruction::Unknown(opcode),
+        },
+        instruction => instruction,
+    })
+}
+
+// Mnemonics follow Cowgod's technical reference, extended for SUPER-CHIP and XO-CHIP
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Instruction::Sys(addr) => write!(f, "SYS {:#05X}", addr),
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::ScrollDown(n) => write!(f, "SCD {}", n),
+            Instruction::ScrollUp(n) => write!(f, "SCU {}", n),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::Low => write!(f, "LOW"),
+            Instruction::High => write!(f, "HIGH"),
+            Instruction::Jp(addr) => write!(f, "JP {:#05X}", addr),
+            Instruction::Call(addr) => write!(f, "CALL {:#05X}", addr),
+            Instruction::SeByte(x, nn) => write!(f, "SE V{:X}, {:#04X}", x, nn),
+            Instruction::SneByte(x, nn) => write!(f, "SNE V{:X}, {:#04X}", x, nn),
+            Instruction::SeReg(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::Save(x, y) => write!(f, "SAVE V{:X} - V{:X}", x, y),
+            Instruction::Load(x, y) => write!(f, "LOAD V{:X} - V{:X}", x, y),
+            Instruction::Ld(x, nn) => write!(f, "LD V{:X}, {:#04X}", x, nn),
+            Instruction::AddByte(x, nn) => write!(f, "ADD V{:X}, {:#04X}", x, nn),
+            Instruction::LdReg(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::AddReg(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Sub(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::Shr(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::Subn(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::Shl(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SneReg(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LdI(addr) => write!(f, "LD I, {:#05X}", addr),
+            Instruction::JpV0(addr) => write!(f, "JP V0, {:#05X}", addr),
+            Instruction::Rnd(x, nn) => write!(f, "RND V{:X}, {:#04X}", x, nn),
+            Instruction::Drw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::Skp(x) => write!(f, "SKP V{:X}", x),
+            Instruction::Sknp(x) => write!(f, "SKNP V{:X}", x),
+            Instruction::LdILong(addr) => write!(f, "LD I, LONG {:#06X}", addr),
+            Instruction::Plane(n) => write!(f, "PLANE {}", n),
+            Instruction::Audio => write!(f, "AUDIO"),
+            Instruction::LdVxDt(x) => write!(f, "LD V{:X}, DT", x),
+            Instruction::LdVxK(x) => write!(f, "LD V{:X}, K", x),
+            Instruction::LdDtVx(x) => write!(f, "LD DT, V{:X}", x),
+            Instruction::LdStVx(x) => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddIVx(x) => write!(f, "ADD I, V{:X}", x),
+            Instruction::LdFVx(x) => write!(f, "LD F, V{:X}", x),
+            Instruction::LdHfVx(x) => write!(f, "LD HF, V{:X}", x),
+            Instruction::LdBVx(x) => write!(f, "LD B, V{:X}", x),
+            Instruction::Pitch(x) => write!(f, "PITCH V{:X}", x),
+            Instruction::LdIVx(x) => write!(f, "LD [I], V{:X}", x),
+            Instruction::LdVxI(x) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::LdRVx(x) => write!(f, "LD R, V{:X}", x),
+            Instruction::LdVxR(x) => write!(f, "LD V{:X}, R", x),
+            Instruction::Unknown(opcode) => write!(f, "DW {:#06X}", opcode),
+        }
+    }
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Macro to shadow prelude with pretty_assertions
+    macro_rules! assert_eq {
+        ($($tt:tt)*) => {
+            pretty_assertions::assert_eq!($($tt)*)
+        };
+    }
+
+    #[test]
+    fn test_decode_and_display() {
+        let cases = [
+            (0x0123, Instruction::Sys(0x123), "SYS 0x123"),
+            (0x00E0, Instruction::Cls, "CLS"),
+            (0x00EE, Instruction::Ret, "RET"),
+            (0x00C4, Instruction::ScrollDown(4), "SCD 4"),
+            (0x00DA, Instruction::ScrollUp(10), "SCU 10"),
+            (0x00FB, Instruction::ScrollRight, "SCR"),
+            (0x00FC, Instruction::ScrollLeft, "SCL"),
+            (0x00FD, Instruction::Exit, "EXIT"),
+            (0x00FE, Instruction::Low, "LOW"),
+            (0x00FF, Instruction::High, "HIGH"),
+            (0x1456, Instruction::Jp(0x456), "JP 0x456"),
+            (0x2045, Instruction::Call(0x045), "CALL 0x045"),
+            (0x3A42, Instruction::SeByte(0xA, 0x42), "SE VA, 0x42"),
+            (0x4A05, Instruction::SneByte(0xA, 0x05), "SNE VA, 0x05"),
+            (0x5AB0, Instruction::SeReg(0xA, 0xB), "SE VA, VB"),
+            (0x51F2, Instruction::Save(0x1, 0xF), "SAVE V1 - VF"),
+            (0x5313, Instruction::Load(0x3, 0x1), "LOAD V3 - V1"),
+            (0x6A42, Instruction::Ld(0xA, 0x42), "LD VA, 0x42"),
+            (0x7AFF, Instruction::AddByte(0xA, 0xFF), "ADD VA, 0xFF"),
+            (0x8AB0, Instruction::LdReg(0xA, 0xB), "LD VA, VB"),
+            (0x8AB1, Instruction::Or(0xA, 0xB), "OR VA, VB"),
+            (0x8AB2, Instruction::And(0xA, 0xB), "AND VA, VB"),
+            (0x8AB3, Instruction::Xor(0xA, 0xB), "XOR VA, VB"),
+            (0x8AB4, Instruction::AddReg(0xA, 0xB), "ADD VA, VB"),
+            (0x8AB5, Instruction::Sub(0xA, 0xB), "SUB VA, VB"),
+            (0x8AB6, Instruction::Shr(0xA, 0xB), "SHR VA, VB"),
+            (0x8AB7, Instruction::Subn(0xA, 0xB), "SUBN VA, VB"),
+            (0x8ABE, Instruction::Shl(0xA, 0xB), "SHL VA, VB"),
+            (0x9AB0, Instruction::SneReg(0xA, 0xB), "SNE VA, VB"),
+            (0xA300, Instruction::LdI(0x300), "LD I, 0x300"),
+            (0xB300, Instruction::JpV0(0x300), "JP V0, 0x300"),
+            (0xCA0F, Instruction::Rnd(0xA, 0x0F), "RND VA, 0x0F"),
+            (
+                0xDAB5,
+                Instruction::Drw {
+                    x: 0xA,
+                    y: 0xB,
+                    n: 5,
+                },
+                "DRW VA, VB, 5",
+            ),
+            (0xEA9E, Instruction::Skp(0xA), "SKP VA"),
+            (0xEAA1, Instruction::Sknp(0xA), "SKNP VA"),
+            (0xF000, Instruction::LdILong(0), "LD I, LONG 0x0000"),
+            (0xF301, Instruction::Plane(3), "PLANE 3"),
+            (0xF002, Instruction::Audio, "AUDIO"),
+            (0xFA07, Instruction::LdVxDt(0xA), "LD VA, DT"),
+            (0xFA0A, Instruction::LdVxK(0xA), "LD VA, K"),
+            (0xFA15, Instruction::LdDtVx(0xA), "LD DT, VA"),
+            (0xFA18, Instruction::LdStVx(0xA), "LD ST, VA"),
+            (0xFA1E, Instruction::AddIVx(0xA), "ADD I, VA"),
+            (0xFA29, Instruction::LdFVx(0xA), "LD F, VA"),
+            (0xFA30, Instruction::LdHfVx(0xA), "LD HF, VA"),
+            (0xFA33, Instruction::LdBVx(0xA), "LD B, VA"),
+            (0xFA3A, Instruction::Pitch(0xA), "PITCH VA"),
+            (0xFA55, Instruction::LdIVx(0xA), "LD [I], VA"),
+            (0xFA65, Instruction::LdVxI(0xA), "LD VA, [I]"),
+            (0xF775, Instruction::LdRVx(0x7), "LD R, V7"),
+            (0xF785, Instruction::LdVxR(0x7), "LD V7, R"),
+        ];
+
+        for (opcode, instruction, text) in cases {
+            assert_eq!(instruction, decode(opcode), "{:04X}", opcode);
+            assert_eq!(opcode, instruction.opcode(), "{:04X}", opcode);
+            assert_eq!(text, instruction.to_string(), "{:04X}", opcode);
+        }
+    }
+
+    #[test]
+    fn test_decode_unknown() {
+        let opcodes = [
+            0x5121, 0x8AB8, 0x8ABF, 0x9AB1, 0xE000, 0xEAFF, 0xF100, 0xF102, 0xFAFF,
+        ];
+
+        for opcode in opcodes {
+            assert_eq!(Instruction::Unknown(opcode), decode(opcode));
+            assert_eq!(opcode, decode(opcode).opcode());
+        }
+        assert_eq!("DW 0x5121", Instruction::Unknown(0x5121).to_string());
+    }
+
+    #[test]
+    fn test_decode_at() {
+        let memory = [0x00, 0xE0, 0xF0, 0x00, 0x12, 0x34, 0xF0, 0x00];
+
+        assert_eq!(Some(Instruction::Cls), decode_at(&memory, 0));
+        assert_eq!(Some(Instruction::LdILong(0x1234)), decode_at(&memory, 2));
+        assert_eq!(4, Instruction::LdILong(0x1234).size());
+
+        // Long load without its address, and no opcode at all
+        assert_eq!(Some(Instruction::Unknown(0xF000)), decode_at(&memory, 6));
+        assert_eq!(None, decode_at(&memory, 7));
+        assert_eq!(None, decode_at(&memory, usize::MAX));
+    }
+}
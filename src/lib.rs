@@ -0,0 +1,29 @@
+//! CHIP-8 emulator library
+//!
+//! The [`Chip8`] virtual machine is created from a [`Config`], loaded with [`Chip8::load_rom`]
+//! and driven by the host either instruction by instruction with [`Chip8::step`] or frame by
+//! frame with [`Chip8::run_frame`].
+
+pub mod asm;
+pub mod audio;
+pub mod chip8;
+pub mod debugger;
+pub mod disasm;
+pub mod error;
+pub mod expr;
+pub mod gdb;
+pub mod instruction;
+pub mod octo;
+pub mod platform;
+pub mod quirks;
+pub mod rng;
+pub mod screenshot;
+
+pub use audio::{Beeper, BeeperConfig, SampleRing};
+pub use chip8::{Chip8, Config, SoundEvent, StepOutcome};
+pub use debugger::{Debugger, Stop};
+pub use error::{AsmError, Chip8Error, Chip8ErrorKind, ExprError, LoadError};
+pub use instruction::{Instruction, decode};
+pub use platform::Platform;
+pub use quirks::{IndexIncrement, Quirks};
+pub use rng::{RandomSource, SeededRng, VipRng};
@@ -1,7 +1,404 @@
-mod chip8;
+mod cli;
+mod frontend;
+mod repl;
+mod rpl;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use std::{env, fs, thread};
+
+use chip8::chip8::{PROGRAM_START, TIMER_FREQUENCY};
+use chip8::rng::VIP_INTERPRETER_SIZE;
+use chip8::screenshot::{self, ImageFormat, Palette};
+use chip8::{AsmError, Debugger, Platform, asm, audio, debugger, disasm, gdb, octo};
+use chip8::{Beeper, BeeperConfig, Chip8, Chip8Error, SoundEvent, StepOutcome, VipRng};
+
+use crate::cli::{Command, DebugOptions, FrontendKind, MachineOptions, RunOptions};
+use crate::frontend::terminal::TerminalFrontend;
+use crate::frontend::{Frontend, HeadlessFrontend};
+
+// Exit codes
+const EXIT_FAULT: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_LOAD: u8 = 3;
+const EXIT_FRONTEND: u8 = 4;
+const EXIT_STOP_NOT_REACHED: u8 = 5;
+const EXIT_OUTPUT: u8 = 6;
+const EXIT_ASSEMBLY: u8 = 7;
 
 //TODO: Add panic handler
 
-fn main() {
-    println!("Hello, world!");
+fn main() -> ExitCode {
+    match cli::parse_args(env::args().skip(1)) {
+        Ok(Command::Run(options)) => run(&options),
+        Ok(Command::Debug(options)) => debug(&options),
+        Ok(Command::Disasm(path)) => disasm(&path),
+        Ok(Command::Asm { source, output }) => build(&source, &output, asm::assemble),
+        Ok(Command::Octo {
+            source,
+            output,
+            platform,
+        }) => build(&source, &output, |text| octo::compile(text, platform)),
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("Error: {}\n\n{}", err, cli::USAGE);
+            ExitCode::from(EXIT_USAGE)
+        }
+    }
+}
+
+// Load a ROM and run it until the frame limit or stop address is reached, the user quits or
+// the ROM faults
+fn run(options: &RunOptions) -> ExitCode {
+    let rom = match read_program(&options.rom, options.machine.platform) {
+        Ok(rom) => rom,
+        Err(code) => return code,
+    };
+
+    let mut chip = match new_chip(&options.machine, &options.rom, &rom) {
+        Ok(chip) => chip,
+        Err(code) => return code,
+    };
+
+    // Flags are only worth keeping for programs that can actually use them
+    let rpl_path = options
+        .machine
+        .platform
+        .has_super_chip()
+        .then(|| options.rpl_dir.clone().or_else(rpl::default_dir))
+        .flatten()
+        .map(|dir| rpl::flags_path(&dir, &rom));
+    if let Some(path) = &rpl_path {
+        match rpl::load(path) {
+            Ok(flags) => chip.set_rpl_flags(flags),
+            Err(err) => eprintln!("Could not read RPL flags {}: {}", path.display(), err),
+        }
+    }
+    let saved_flags = *chip.rpl_flags();
+
+    let mut track = options.audio_out.as_ref().map(|_| AudioTrack::new());
+
+    // The frontend has to be gone before reporting, so the terminal is restored
+    let result = match create_frontend(options.frontend) {
+        Ok(mut frontend) => run_frames(&mut chip, frontend.as_mut(), options, track.as_mut()),
+        Err(err) => Err(RunError::Frontend(err)),
+    };
+
+    // Outputs are written on faults too, they are most useful for bug reports
+    if let Some(path) = &options.screenshot
+        && let Err(err) = save_screenshot(&chip, path, options.scale)
+    {
+        eprintln!("Could not write screenshot {}: {}", path.display(), err);
+        return ExitCode::from(EXIT_OUTPUT);
+    }
+
+    if let (Some(path), Some(track)) = (&options.audio_out, &track)
+        && let Err(err) = track.save(path)
+    {
+        eprintln!("Could not write audio {}: {}", path.display(), err);
+        return ExitCode::from(EXIT_OUTPUT);
+    }
+
+    if let Some(path) = &rpl_path
+        && *chip.rpl_flags() != saved_flags
+        && let Err(err) = rpl::save(path, chip.rpl_flags())
+    {
+        eprintln!("Could not write RPL flags {}: {}", path.display(), err);
+        return ExitCode::from(EXIT_OUTPUT);
+    }
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(RunError::Fault { frames, error }) => {
+            eprintln!("ROM fault after {} frames: {}", frames, error);
+            eprintln!("{}", debugger::dump_state(&chip));
+            ExitCode::from(EXIT_FAULT)
+        }
+        Err(RunError::Frontend(err)) => {
+            eprintln!("Frontend error: {}", err);
+            ExitCode::from(EXIT_FRONTEND)
+        }
+        Err(RunError::StopNotReached(address)) => {
+            eprintln!("Stop address {:03X} was not reached", address);
+            ExitCode::from(EXIT_STOP_NOT_REACHED)
+        }
+    }
+}
+
+// Load a ROM and hand it to the debugger prompt on stdin and stdout
+fn debug(options: &DebugOptions) -> ExitCode {
+    let rom = match read_program(&options.rom, options.machine.platform) {
+        Ok(rom) => rom,
+        Err(code) => return code,
+    };
+
+    let chip = match new_chip(&options.machine, &options.rom, &rom) {
+        Ok(chip) => chip,
+        Err(code) => return code,
+    };
+
+    let mut debugger = Debugger::new(chip, options.machine.instructions_per_frame);
+    let result = match &options.gdb {
+        Some(address) => serve_gdb(&mut debugger, address),
+        None => repl::run(&mut debugger, io::stdin().lock(), &mut io::stdout().lock()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Frontend error: {}", err);
+            ExitCode::from(EXIT_FRONTEND)
+        }
+    }
+}
+
+// Wait for a single gdb connection and serve it until gdb detaches
+fn serve_gdb(debugger: &mut Debugger, address: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    eprintln!(
+        "Waiting for gdb on {}, use: target remote {}",
+        address, address
+    );
+
+    let (stream, peer) = listener.accept()?;
+    eprintln!("gdb connected from {}", peer);
+    gdb::serve(debugger, stream)
+}
+
+// Helper function to read a ROM, Octo programs are compiled for the platform they are run on
+fn read_program(path: &Path, platform: Platform) -> Result<Vec<u8>, ExitCode> {
+    if path.extension().is_some_and(|ext| ext == "8o") {
+        return compile_source(path, |text| octo::compile(text, platform));
+    }
+
+    fs::read(path).map_err(|err| {
+        eprintln!("Could not read {}: {}", path.display(), err);
+        ExitCode::from(EXIT_LOAD)
+    })
+}
+
+// Create the chip8 described by the options and load a ROM into it
+fn new_chip(machine: &MachineOptions, path: &Path, rom: &[u8]) -> Result<Chip8, ExitCode> {
+    let mut chip = Chip8::with_config(machine.config());
+
+    if let Some(interpreter) = &machine.vip_interpreter {
+        let image = fs::read(interpreter).map_err(|err| {
+            eprintln!("Could not read {}: {}", interpreter.display(), err);
+            ExitCode::from(EXIT_LOAD)
+        })?;
+        let Ok(image) = <&[u8; VIP_INTERPRETER_SIZE]>::try_from(image.as_slice()) else {
+            eprintln!(
+                "Could not load {}: a VIP interpreter is {} bytes, not {}",
+                interpreter.display(),
+                VIP_INTERPRETER_SIZE,
+                image.len()
+            );
+            return Err(ExitCode::from(EXIT_LOAD));
+        };
+
+        let seed = machine.seed.unwrap_or_else(rand::random);
+        chip.set_rng(Box::new(VipRng::new(image, seed as u16)));
+    }
+
+    if let Err(err) = chip.load_rom(rom) {
+        eprintln!("Could not load {}: {}", path.display(), err);
+        return Err(ExitCode::from(EXIT_LOAD));
+    }
+
+    Ok(chip)
+}
+
+// Print the listing of a ROM to stdout
+fn disasm(path: &Path) -> ExitCode {
+    let rom = match fs::read(path) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("Could not read {}: {}", path.display(), err);
+            return ExitCode::from(EXIT_LOAD);
+        }
+    };
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let result = disasm::disassemble(&rom, PROGRAM_START)
+        .iter()
+        .try_for_each(|line| writeln!(out, "{}", line))
+        .and_then(|()| out.flush());
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Could not write listing: {}", err);
+            ExitCode::from(EXIT_OUTPUT)
+        }
+    }
+}
+
+// Assemble or compile a source file into a ROM file
+fn build(
+    source: &Path,
+    output: &Path,
+    compile: impl FnOnce(&str) -> Result<Vec<u8>, AsmError>,
+) -> ExitCode {
+    let rom = match compile_source(source, compile) {
+        Ok(rom) => rom,
+        Err(code) => return code,
+    };
+
+    if let Err(err) = fs::write(output, rom) {
+        eprintln!("Could not write {}: {}", output.display(), err);
+        return ExitCode::from(EXIT_OUTPUT);
+    }
+
+    ExitCode::SUCCESS
+}
+
+// Helper function to read and compile a source file, reporting errors on stderr
+fn compile_source(
+    source: &Path,
+    compile: impl FnOnce(&str) -> Result<Vec<u8>, AsmError>,
+) -> Result<Vec<u8>, ExitCode> {
+    let text = match fs::read_to_string(source) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("Could not read {}: {}", source.display(), err);
+            return Err(ExitCode::from(EXIT_LOAD));
+        }
+    };
+
+    compile(&text).map_err(|err| {
+        eprintln!("{}:{}", source.display(), err);
+        ExitCode::from(EXIT_ASSEMBLY)
+    })
+}
+
+// Reasons a run ended unsuccessfully
+enum RunError {
+    Fault { frames: u64, error: Chip8Error },
+    Frontend(io::Error),
+    StopNotReached(u16),
+}
+
+// Sound of a run, rendered frame by frame for --audio-out
+struct AudioTrack {
+    beeper: Beeper,
+    samples: Vec<f32>,
+}
+
+impl AudioTrack {
+    fn new() -> AudioTrack {
+        AudioTrack {
+            beeper: Beeper::new(BeeperConfig::default()),
+            samples: Vec::new(),
+        }
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        audio::write_wav(&mut file, &self.samples, self.beeper.config().sample_rate)?;
+        file.flush()
+    }
+}
+
+fn create_frontend(kind: FrontendKind) -> io::Result<Box<dyn Frontend>> {
+    Ok(match kind {
+        FrontendKind::Terminal => Box::new(TerminalFrontend::new()?),
+        FrontendKind::Headless => Box::new(HeadlessFrontend),
+    })
+}
+
+// Run frames until the frame limit or stop address is reached, or the user quits
+fn run_frames(
+    chip: &mut Chip8,
+    frontend: &mut dyn Frontend,
+    options: &RunOptions,
+    mut track: Option<&mut AudioTrack>,
+) -> Result<(), RunError> {
+    let frame_duration = Duration::from_secs(1) / TIMER_FREQUENCY;
+    let mut frames = 0;
+
+    while options.frame_limit.is_none_or(|limit| frames < limit) {
+        let frame_start = Instant::now();
+
+        if !frontend.poll_input(chip).map_err(RunError::Frontend)? {
+            return Ok(());
+        }
+
+        let result = match options.until_pc {
+            Some(address) => run_frame_until(chip, options.machine.instructions_per_frame, address),
+            None => chip
+                .run_frame(options.machine.instructions_per_frame)
+                .map(Some),
+        };
+
+        let sound = match result {
+            Ok(Some(sound)) => sound,
+            Ok(None) => return Ok(()),
+            Err(error) => return Err(RunError::Fault { frames, error }),
+        };
+
+        if let Some(track) = track.as_deref_mut() {
+            track.beeper.render_chip_frame(chip, &mut track.samples);
+        }
+
+        frontend.present(chip, sound).map_err(RunError::Frontend)?;
+
+        if frontend.realtime() {
+            thread::sleep(frame_duration.saturating_sub(frame_start.elapsed()));
+        }
+
+        frames += 1;
+
+        if chip.has_exited() {
+            break;
+        }
+    }
+
+    match options.until_pc {
+        Some(address) => Err(RunError::StopNotReached(address)),
+        None => Ok(()),
+    }
+}
+
+// Run a frame like Chip8::run_frame, but stop as soon as pc reaches the given address
+// Returns None once the address is reached
+fn run_frame_until(
+    chip: &mut Chip8,
+    instructions_per_frame: usize,
+    address: u16,
+) -> Result<Option<Option<SoundEvent>>, Chip8Error> {
+    for _ in 0..instructions_per_frame {
+        if chip.pc() == address && !chip.is_waiting_for_key() {
+            return Ok(None);
+        }
+
+        if chip.step()? != StepOutcome::Executed {
+            break;
+        }
+    }
+
+    Ok(Some(chip.tick_timers()))
+}
+
+fn save_screenshot(chip: &Chip8, path: &Path, scale: usize) -> io::Result<()> {
+    let format = ImageFormat::from_path(path)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unknown image format"))?;
+    let mut file = BufWriter::new(File::create(path)?);
+
+    screenshot::write_image(
+        &mut file,
+        format,
+        chip.framebuffer(),
+        chip.display_width(),
+        chip.display_height(),
+        scale,
+        &Palette::default(),
+    )?;
+    file.flush()
 }
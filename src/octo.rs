@@ -0,0 +1,1285 @@
+use std::collections::HashMap;
+
+use crate::chip8::PROGRAM_START;
+use crate::error::AsmError;
+use crate::instruction::Instruction;
+use crate::platform::Platform;
+
+// =================================
+// Octo compiler
+// =================================
+
+// Compiles the Octo language (https://github.com/JohnEarnest/Octo) into a ROM. Tokens are
+// separated by whitespace and # starts a comment. Supported are:
+//
+//   : name                      label, calling it by name emits 2NNN
+//   :const name value           :alias name vX           :calc name { expression }
+//   :macro name args { body }   :byte value              :call address
+//   12 0xFF 0b1010              bare numbers are emitted as bytes, e.g. sprite data
+//   loop ... while cond ... again
+//   if cond then statement      if cond begin ... else ... end
+//
+// Conditions are `vX == y`, `!=`, `<`, `>`, `<=`, `>=` (the latter four use vF as scratch
+// register), `vX key` and `vX -key`. :calc has no operator precedence, expressions are
+// evaluated right to left like in Octo itself.
+//
+// Programs start with a jump to the `main` label, unless they begin with it.
+
+// Nesting depth before a macro is assumed to call itself forever
+const MAX_EXPANSION_DEPTH: usize = 1_000;
+
+// Register used by the <, >, <= and >= conditions
+const REG_VF: u8 = 0xF;
+
+/// Compile an Octo program into a ROM loaded at 0x200
+///
+/// Instructions the platform lacks, like `hires` on CHIP-8, are reported as errors.
+pub fn compile(source: &str, platform: Platform) -> Result<Vec<u8>, AsmError> {
+    Compiler::new(tokenize(source), platform).run()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+// Split the source at whitespace, dropping # comments
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+
+    for (index, text) in source.lines().enumerate() {
+        let mut start = None;
+
+        for (column, c) in text.chars().chain([' ']).enumerate() {
+            if c == '#' && start.is_none() {
+                break;
+            }
+
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(column),
+                (true, Some(begin)) => {
+                    tokens.push(Token {
+                        text: text.chars().skip(begin).take(column - begin).collect(),
+                        line: index + 1,
+                        column: begin + 1,
+                    });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    tokens
+}
+
+// Helper function to parse a decimal, 0x hexadecimal or 0b binary number, optionally negative
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+// Helper function to build an error located at a token
+fn error(token: &Token, message: String) -> AsmError {
+    AsmError {
+        line: token.line,
+        column: token.column,
+        message,
+    }
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+}
+
+// Width of an address that is patched once its label is known
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FixupKind {
+    // Low 12 bits of the opcode at offset
+    Address,
+    // 16-bit word at offset
+    Long,
+}
+
+struct Fixup {
+    offset: usize,
+    kind: FixupKind,
+    name: Token,
+}
+
+// Open control flow structure
+enum Block {
+    // if ... begin, holds the offset of the jump to the else branch or the end
+    If {
+        token: Token,
+        jump: usize,
+    },
+    // loop, holds the offsets of the jumps out of the loop added by while
+    Loop {
+        token: Token,
+        start: u16,
+        breaks: Vec<usize>,
+    },
+}
+
+// Right hand side of a condition
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operand {
+    Register(u8),
+    Byte(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Condition {
+    Equal(u8, Operand),
+    NotEqual(u8, Operand),
+    Less(u8, Operand),
+    Greater(u8, Operand),
+    LessOrEqual(u8, Operand),
+    GreaterOrEqual(u8, Operand),
+    Key(u8),
+    NotKey(u8),
+}
+
+struct Compiler {
+    platform: Platform,
+    tokens: Vec<Token>,
+    position: usize,
+    // Token positions where the expansions being compiled end, innermost last
+    expansion_ends: Vec<usize>,
+    rom: Vec<u8>,
+    labels: HashMap<String, u16>,
+    constants: HashMap<String, i64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+}
+
+impl Compiler {
+    fn new(tokens: Vec<Token>, platform: Platform) -> Compiler {
+        Compiler {
+            platform,
+            tokens,
+            position: 0,
+            expansion_ends: Vec::new(),
+            rom: Vec::new(),
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> Result<Vec<u8>, AsmError> {
+        // Jump to main, unless the program starts with it
+        let starts_with_main = matches!(
+            self.tokens.as_slice(),
+            [colon, name, ..] if colon.text == ":" && name.text == "main"
+        );
+        if !starts_with_main {
+            let main = Token {
+                text: "main".to_string(),
+                line: 1,
+                column: 1,
+            };
+            self.fixup(&main, FixupKind::Address);
+            self.emit(&main, Instruction::Jp(0))?;
+        }
+
+        while self.position < self.tokens.len() {
+            self.statement()?;
+        }
+
+        if let Some(block) = self.blocks.last() {
+            let (token, closing) = match block {
+                Block::If { token, .. } => (token, "end"),
+                Block::Loop { token, .. } => (token, "again"),
+            };
+            return Err(error(
+                token,
+                format!("'{}' without '{}'", token.text, closing),
+            ));
+        }
+
+        for fixup in std::mem::take(&mut self.fixups) {
+            let Some(&address) = self.labels.get(&fixup.name.text) else {
+                return Err(error(
+                    &fixup.name,
+                    format!("undefined label '{}'", fixup.name.text),
+                ));
+            };
+
+            match fixup.kind {
+                FixupKind::Address => {
+                    self.check_address(&fixup.name, address as i64)?;
+                    self.rom[fixup.offset] |= (address >> 8) as u8;
+                    self.rom[fixup.offset + 1] = address as u8;
+                }
+                FixupKind::Long => {
+                    self.rom[fixup.offset..fixup.offset + 2]
+                        .copy_from_slice(&address.to_be_bytes());
+                }
+            }
+        }
+
+        Ok(self.rom)
+    }
+
+    // Compile a single statement
+    fn statement(&mut self) -> Result<(), AsmError> {
+        let token = self.next()?;
+
+        if let Some(value) = parse_number(&token.text) {
+            let byte = self.check_byte(&token, value)?;
+            return self.emit_byte(&token, byte);
+        }
+
+        match token.text.as_str() {
+            ":" => {
+                let name = self.name()?;
+                let address = self.here_address(&name)?;
+                if self.labels.insert(name.text.clone(), address).is_some() {
+                    return Err(error(
+                        &name,
+                        format!("label '{}' is already defined", name.text),
+                    ));
+                }
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.next()?;
+                let value = self.value(&value)?;
+                self.constants.insert(name.text, value);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.next()?;
+                let register = self.register(&register)?;
+                self.aliases.insert(name.text, register);
+            }
+            ":calc" => {
+                let name = self.name()?;
+                let body = self.braces(&name)?;
+                let value = self.calc(&name, &body)?;
+                self.constants.insert(name.text, value);
+            }
+            ":macro" => {
+                let name = self.name()?;
+                let mut params = Vec::new();
+                while self.peek().is_some_and(|token| token.text != "{") {
+                    params.push(self.next()?.text);
+                }
+                let body = self.braces(&name)?;
+                self.macros.insert(name.text, Macro { params, body });
+            }
+            ":byte" => {
+                let value = self.next()?;
+                let byte = self.byte(&value)?;
+                self.emit_byte(&value, byte)?;
+            }
+            ":call" => {
+                let target = self.next()?;
+                let address = self.address(&target)?;
+                self.emit(&token, Instruction::Call(address))?;
+            }
+
+            "clear" => self.emit(&token, Instruction::Cls)?,
+            "return" | ";" => self.emit(&token, Instruction::Ret)?,
+            "hires" => self.emit(&token, Instruction::High)?,
+            "lores" => self.emit(&token, Instruction::Low)?,
+            "scroll-down" => {
+                let rows = self.nibble_operand()?;
+                self.emit(&token, Instruction::ScrollDown(rows))?;
+            }
+            "scroll-up" => {
+                let rows = self.nibble_operand()?;
+                self.emit(&token, Instruction::ScrollUp(rows))?;
+            }
+            "scroll-left" => self.emit(&token, Instruction::ScrollLeft)?,
+            "scroll-right" => self.emit(&token, Instruction::ScrollRight)?,
+            "exit" => self.emit(&token, Instruction::Exit)?,
+            "audio" => self.emit(&token, Instruction::Audio)?,
+            "plane" => {
+                let planes = self.nibble_operand()?;
+                self.emit(&token, Instruction::Plane(planes))?;
+            }
+            "bcd" => {
+                let x = self.register_operand()?;
+                self.emit(&token, Instruction::LdBVx(x))?;
+            }
+            "save" | "load" => {
+                let x = self.register_operand()?;
+                let instruction = if self.peek().is_some_and(|token| token.text == "-") {
+                    self.next()?;
+                    let y = self.register_operand()?;
+                    match token.text.as_str() {
+                        "save" => Instruction::Save(x, y),
+                        _ => Instruction::Load(x, y),
+                    }
+                } else {
+                    match token.text.as_str() {
+                        "save" => Instruction::LdIVx(x),
+                        _ => Instruction::LdVxI(x),
+                    }
+                };
+                self.emit(&token, instruction)?;
+            }
+            "saveflags" => {
+                let x = self.register_operand()?;
+                self.emit(&token, Instruction::LdRVx(x))?;
+            }
+            "loadflags" => {
+                let x = self.register_operand()?;
+                self.emit(&token, Instruction::LdVxR(x))?;
+            }
+            "sprite" => {
+                let x = self.register_operand()?;
+                let y = self.register_operand()?;
+                let n = self.nibble_operand()?;
+                self.emit(&token, Instruction::Drw { x, y, n })?;
+            }
+            "jump" => {
+                let target = self.next()?;
+                let address = self.address(&target)?;
+                self.emit(&token, Instruction::Jp(address))?;
+            }
+            "jump0" => {
+                let target = self.next()?;
+                let address = self.address(&target)?;
+                self.emit(&token, Instruction::JpV0(address))?;
+            }
+
+            "loop" => {
+                let start = self.here_address(&token)?;
+                self.blocks.push(Block::Loop {
+                    token,
+                    start,
+                    breaks: Vec::new(),
+                });
+            }
+            "while" => {
+                let condition = self.condition()?;
+                let Some(Block::Loop { .. }) = self.blocks.last() else {
+                    return Err(error(&token, "'while' outside of a loop".to_string()));
+                };
+
+                // Leave the loop unless the condition holds
+                self.skip(&token, condition, true)?;
+                let jump = self.rom.len();
+                self.emit(&token, Instruction::Jp(0))?;
+                if let Some(Block::Loop { breaks, .. }) = self.blocks.last_mut() {
+                    breaks.push(jump);
+                }
+            }
+            "again" => {
+                let Some(Block::Loop { start, breaks, .. }) = self.blocks.pop() else {
+                    return Err(error(&token, "'again' without 'loop'".to_string()));
+                };
+
+                let start = self.check_address(&token, start as i64)?;
+                self.emit(&token, Instruction::Jp(start))?;
+                for jump in breaks {
+                    self.patch(&token, jump)?;
+                }
+            }
+            "if" => {
+                let condition = self.condition()?;
+                let keyword = self.next()?;
+
+                match keyword.text.as_str() {
+                    // Skip the statement unless the condition holds
+                    "then" => self.skip(&token, condition, false)?,
+                    // Jump to the else branch or end unless the condition holds
+                    "begin" => {
+                        self.skip(&token, condition, true)?;
+                        let jump = self.rom.len();
+                        self.emit(&token, Instruction::Jp(0))?;
+                        self.blocks.push(Block::If { token, jump });
+                    }
+                    _ => {
+                        return Err(error(
+                            &keyword,
+                            format!("expected 'then' or 'begin', found '{}'", keyword.text),
+                        ));
+                    }
+                }
+            }
+            "else" => {
+                let Some(Block::If { jump, .. }) = self.blocks.last() else {
+                    return Err(error(&token, "'else' without 'begin'".to_string()));
+                };
+                let jump = *jump;
+
+                let end = self.rom.len();
+                self.emit(&token, Instruction::Jp(0))?;
+                self.patch(&token, jump)?;
+                if let Some(Block::If { jump, .. }) = self.blocks.last_mut() {
+                    *jump = end;
+                }
+            }
+            "end" => {
+                let Some(Block::If { jump, .. }) = self.blocks.pop() else {
+                    return Err(error(&token, "'end' without 'begin'".to_string()));
+                };
+                self.patch(&token, jump)?;
+            }
+
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register_operand()?;
+                let instruction = match token.text.as_str() {
+                    "delay" => Instruction::LdDtVx(x),
+                    "buzzer" => Instruction::LdStVx(x),
+                    _ => Instruction::Pitch(x),
+                };
+                self.emit(&token, instruction)?;
+            }
+            "i" => self.index_statement(&token)?,
+
+            _ if self.macros.contains_key(&token.text) => self.expand(&token)?,
+            _ if self.is_register(&token) => self.register_statement(&token)?,
+            _ => {
+                let address = self.address(&token)?;
+                self.emit(&token, Instruction::Call(address))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // i := address, i := long address, i := hex vX, i := bighex vX, i += vX
+    fn index_statement(&mut self, token: &Token) -> Result<(), AsmError> {
+        let operator = self.next()?;
+
+        let instruction = match operator.text.as_str() {
+            ":=" => {
+                let value = self.next()?;
+                match value.text.as_str() {
+                    "hex" => Instruction::LdFVx(self.register_operand()?),
+                    "bighex" => Instruction::LdHfVx(self.register_operand()?),
+                    "long" => {
+                        let target = self.next()?;
+                        let address = self.long_address(&target)?;
+                        Instruction::LdILong(address)
+                    }
+                    _ => Instruction::LdI(self.address(&value)?),
+                }
+            }
+            "+=" => Instruction::AddIVx(self.register_operand()?),
+            _ => return Err(self.unknown_operator(&operator)),
+        };
+
+        self.emit(token, instruction)
+    }
+
+    // Assignments and arithmetic on vX
+    fn register_statement(&mut self, token: &Token) -> Result<(), AsmError> {
+        let x = self.register(token)?;
+        let operator = self.next()?;
+        let value = self.next()?;
+        let y = self
+            .is_register(&value)
+            .then(|| self.register(&value))
+            .transpose()?;
+
+        let instruction = match (operator.text.as_str(), y) {
+            (":=", _) if value.text == "key" => Instruction::LdVxK(x),
+            (":=", _) if value.text == "delay" => Instruction::LdVxDt(x),
+            (":=", _) if value.text == "random" => {
+                let mask = self.next()?;
+                Instruction::Rnd(x, self.byte(&mask)?)
+            }
+            (":=", Some(y)) => Instruction::LdReg(x, y),
+            (":=", None) => Instruction::Ld(x, self.byte(&value)?),
+            ("+=", Some(y)) => Instruction::AddReg(x, y),
+            ("+=", None) => Instruction::AddByte(x, self.byte(&value)?),
+            ("-=", Some(y)) => Instruction::Sub(x, y),
+            ("-=", None) => Instruction::AddByte(x, self.byte(&value)?.wrapping_neg()),
+            ("=-", Some(y)) => Instruction::Subn(x, y),
+            ("|=", Some(y)) => Instruction::Or(x, y),
+            ("&=", Some(y)) => Instruction::And(x, y),
+            ("^=", Some(y)) => Instruction::Xor(x, y),
+            (">>=", Some(y)) => Instruction::Shr(x, y),
+            ("<<=", Some(y)) => Instruction::Shl(x, y),
+            ("=-" | "|=" | "&=" | "^=" | ">>=" | "<<=", None) => {
+                return Err(error(
+                    &value,
+                    format!("expected a register, found '{}'", value.text),
+                ));
+            }
+            _ => return Err(self.unknown_operator(&operator)),
+        };
+
+        self.emit(token, instruction)
+    }
+
+    // Parse a condition of if and while
+    fn condition(&mut self) -> Result<Condition, AsmError> {
+        let x = self.register_operand()?;
+        let operator = self.next()?;
+
+        match operator.text.as_str() {
+            "key" => return Ok(Condition::Key(x)),
+            "-key" => return Ok(Condition::NotKey(x)),
+            _ => {}
+        }
+
+        let value = self.next()?;
+        let operand = if self.is_register(&value) {
+            Operand::Register(self.register(&value)?)
+        } else {
+            Operand::Byte(self.byte(&value)?)
+        };
+
+        Ok(match operator.text.as_str() {
+            "==" => Condition::Equal(x, operand),
+            "!=" => Condition::NotEqual(x, operand),
+            "<" => Condition::Less(x, operand),
+            ">" => Condition::Greater(x, operand),
+            "<=" => Condition::LessOrEqual(x, operand),
+            ">=" => Condition::GreaterOrEqual(x, operand),
+            _ => return Err(self.unknown_operator(&operator)),
+        })
+    }
+
+    // Emit instructions that skip the next one if the condition is `skip_if`
+    fn skip(&mut self, token: &Token, condition: Condition, skip_if: bool) -> Result<(), AsmError> {
+        // Skip if equal, for a condition that holds if equal
+        let equal = |x, operand, skip_if_equal| match (operand, skip_if_equal) {
+            (Operand::Register(y), true) => Instruction::SeReg(x, y),
+            (Operand::Register(y), false) => Instruction::SneReg(x, y),
+            (Operand::Byte(nn), true) => Instruction::SeByte(x, nn),
+            (Operand::Byte(nn), false) => Instruction::SneByte(x, nn),
+        };
+
+        let instruction = match condition {
+            Condition::Equal(x, operand) => equal(x, operand, skip_if),
+            Condition::NotEqual(x, operand) => equal(x, operand, !skip_if),
+            Condition::Key(x) if skip_if => Instruction::Skp(x),
+            Condition::Key(x) => Instruction::Sknp(x),
+            Condition::NotKey(x) if skip_if => Instruction::Sknp(x),
+            Condition::NotKey(x) => Instruction::Skp(x),
+
+            // vF = y, then vF = y - x or x - y leaves the no-borrow flag in vF
+            Condition::Less(x, operand)
+            | Condition::Greater(x, operand)
+            | Condition::LessOrEqual(x, operand)
+            | Condition::GreaterOrEqual(x, operand) => {
+                let load = match operand {
+                    Operand::Register(y) => Instruction::LdReg(REG_VF, y),
+                    Operand::Byte(nn) => Instruction::Ld(REG_VF, nn),
+                };
+                self.emit(token, load)?;
+
+                // Flag is y >= x after subtracting and x >= y after subtracting reversed
+                let (subtract, holds_if) = match condition {
+                    Condition::Greater(..) => (Instruction::Sub(REG_VF, x), 0),
+                    Condition::LessOrEqual(..) => (Instruction::Sub(REG_VF, x), 1),
+                    Condition::Less(..) => (Instruction::Subn(REG_VF, x), 0),
+                    _ => (Instruction::Subn(REG_VF, x), 1),
+                };
+                self.emit(token, subtract)?;
+
+                equal(REG_VF, Operand::Byte(holds_if), skip_if)
+            }
+        };
+
+        self.emit(token, instruction)
+    }
+
+    // Replace a macro invocation by its body with the arguments substituted
+    fn expand(&mut self, token: &Token) -> Result<(), AsmError> {
+        // Expansions whose body has been compiled are no longer nested. One that ends right
+        // here still counts, so a macro calling itself last keeps getting deeper.
+        while self
+            .expansion_ends
+            .last()
+            .is_some_and(|&end| end < self.position)
+        {
+            self.expansion_ends.pop();
+        }
+        if self.expansion_ends.len() >= MAX_EXPANSION_DEPTH {
+            return Err(error(
+                token,
+                format!("macro '{}' expands forever", token.text),
+            ));
+        }
+
+        let count = self.macros[&token.text].params.len();
+        let mut arguments = Vec::with_capacity(count);
+        for _ in 0..count {
+            arguments.push(self.next()?);
+        }
+
+        let definition = &self.macros[&token.text];
+        let body: Vec<Token> = definition
+            .body
+            .iter()
+            .map(|body_token| {
+                match definition
+                    .params
+                    .iter()
+                    .position(|param| *param == body_token.text)
+                {
+                    Some(index) => arguments[index].clone(),
+                    None => body_token.clone(),
+                }
+            })
+            .collect();
+
+        let length = body.len();
+        for end in self.expansion_ends.iter_mut() {
+            if *end > self.position {
+                *end += length;
+            }
+        }
+        self.expansion_ends.push(self.position + length);
+
+        self.tokens.splice(self.position..self.position, body);
+        Ok(())
+    }
+
+    // Evaluate a :calc expression right to left
+    fn calc(&self, at: &Token, tokens: &[Token]) -> Result<i64, AsmError> {
+        let (value, rest) = self.calc_expr(at, tokens)?;
+        match rest.first() {
+            Some(token) => Err(error(token, format!("unexpected '{}'", token.text))),
+            None => Ok(value),
+        }
+    }
+
+    fn calc_expr<'a>(
+        &self,
+        at: &Token,
+        tokens: &'a [Token],
+    ) -> Result<(i64, &'a [Token]), AsmError> {
+        let (lhs, rest) = self.calc_term(at, tokens)?;
+
+        let Some((operator, tail)) = rest.split_first() else {
+            return Ok((lhs, rest));
+        };
+        if operator.text == ")" {
+            return Ok((lhs, rest));
+        }
+
+        let (rhs, rest) = self.calc_expr(operator, tail)?;
+        let value = match operator.text.as_str() {
+            "+" => lhs.wrapping_add(rhs),
+            "-" => lhs.wrapping_sub(rhs),
+            "*" => lhs.wrapping_mul(rhs),
+            "/" | "%" if rhs == 0 => {
+                return Err(error(operator, "division by zero".to_string()));
+            }
+            // Only the minimum divided by -1 overflows
+            "/" => lhs
+                .checked_div(rhs)
+                .ok_or_else(|| self.overflow(operator))?,
+            "%" => lhs
+                .checked_rem(rhs)
+                .ok_or_else(|| self.overflow(operator))?,
+            "&" => lhs & rhs,
+            "|" => lhs | rhs,
+            "^" => lhs ^ rhs,
+            "<<" => lhs.wrapping_shl(rhs as u32),
+            ">>" => lhs.wrapping_shr(rhs as u32),
+            _ => return Err(self.unknown_operator(operator)),
+        };
+
+        Ok((value, rest))
+    }
+
+    fn calc_term<'a>(
+        &self,
+        at: &Token,
+        tokens: &'a [Token],
+    ) -> Result<(i64, &'a [Token]), AsmError> {
+        let Some((token, rest)) = tokens.split_first() else {
+            return Err(error(at, "missing value".to_string()));
+        };
+
+        match token.text.as_str() {
+            "(" => {
+                let (value, rest) = self.calc_expr(token, rest)?;
+                match rest.split_first() {
+                    Some((close, rest)) if close.text == ")" => Ok((value, rest)),
+                    _ => Err(error(token, "'(' without ')'".to_string())),
+                }
+            }
+            "-" => {
+                let (value, rest) = self.calc_term(token, rest)?;
+                let value = value.checked_neg().ok_or_else(|| self.overflow(token))?;
+                Ok((value, rest))
+            }
+            "~" => self
+                .calc_term(token, rest)
+                .map(|(value, rest)| (!value, rest)),
+            "HERE" => Ok((self.here_address(token)? as i64, rest)),
+            _ => Ok((self.value(token)?, rest)),
+        }
+    }
+
+    // Helper function to get the next token
+    fn next(&mut self) -> Result<Token, AsmError> {
+        let Some(token) = self.tokens.get(self.position) else {
+            let last = self.tokens.last().cloned().unwrap_or(Token {
+                text: String::new(),
+                line: 1,
+                column: 1,
+            });
+            return Err(error(&last, "unexpected end of program".to_string()));
+        };
+
+        self.position += 1;
+        Ok(token.clone())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, AsmError> {
+        let token = self.next()?;
+        if token.text != text {
+            return Err(error(
+                &token,
+                format!("expected '{}', found '{}'", text, token.text),
+            ));
+        }
+
+        Ok(token)
+    }
+
+    // Helper function to get a name to define
+    fn name(&mut self) -> Result<Token, AsmError> {
+        let token = self.next()?;
+        if parse_number(&token.text).is_some() || self.is_register(&token) {
+            return Err(error(
+                &token,
+                format!("'{}' can't be used as name", token.text),
+            ));
+        }
+
+        Ok(token)
+    }
+
+    // Helper function to collect the tokens between { and the matching }
+    fn braces(&mut self, at: &Token) -> Result<Vec<Token>, AsmError> {
+        self.expect("{")?;
+
+        let mut body = Vec::new();
+        let mut depth = 0;
+        loop {
+            let Some(token) = self.peek().cloned() else {
+                return Err(error(at, "'{' without '}'".to_string()));
+            };
+            self.position += 1;
+
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" if depth == 0 => return Ok(body),
+                "}" => depth -= 1,
+                _ => {}
+            }
+            body.push(token);
+        }
+    }
+
+    fn is_register(&self, token: &Token) -> bool {
+        self.register(token).is_ok()
+    }
+
+    fn register(&self, token: &Token) -> Result<u8, AsmError> {
+        if let Some(&register) = self.aliases.get(&token.text) {
+            return Ok(register);
+        }
+
+        token
+            .text
+            .strip_prefix(['v', 'V'])
+            .filter(|digit| digit.len() == 1)
+            .and_then(|digit| u8::from_str_radix(digit, 16).ok())
+            .ok_or_else(|| {
+                error(
+                    token,
+                    format!("expected a register, found '{}'", token.text),
+                )
+            })
+    }
+
+    fn register_operand(&mut self) -> Result<u8, AsmError> {
+        let token = self.next()?;
+        self.register(&token)
+    }
+
+    fn nibble_operand(&mut self) -> Result<u8, AsmError> {
+        let token = self.next()?;
+        let value = self.value(&token)?;
+        if !(0..=0xF).contains(&value) {
+            return Err(error(
+                &token,
+                format!("value {} is out of range 0-15", value),
+            ));
+        }
+
+        Ok(value as u8)
+    }
+
+    // Value of a number, constant or already defined label
+    fn value(&self, token: &Token) -> Result<i64, AsmError> {
+        parse_number(&token.text)
+            .or_else(|| self.constants.get(&token.text).copied())
+            .or_else(|| self.labels.get(&token.text).map(|&address| address as i64))
+            .ok_or_else(|| error(token, format!("undefined name '{}'", token.text)))
+    }
+
+    fn byte(&self, token: &Token) -> Result<u8, AsmError> {
+        let value = self.value(token)?;
+        self.check_byte(token, value)
+    }
+
+    // Bytes may also be given as negative numbers
+    fn check_byte(&self, token: &Token, value: i64) -> Result<u8, AsmError> {
+        if !(-128..=255).contains(&value) {
+            return Err(error(
+                token,
+                format!("value {} does not fit in a byte", value),
+            ));
+        }
+
+        Ok(value as u8)
+    }
+
+    fn check_address(&self, token: &Token, address: i64) -> Result<u16, AsmError> {
+        if !(0..=0xFFF).contains(&address) {
+            return Err(error(
+                token,
+                format!("address {:#X} is out of range, use 'i := long'", address),
+            ));
+        }
+
+        Ok(address as u16)
+    }
+
+    // 12-bit address of the instruction emitted next, labels may be defined later
+    fn address(&mut self, token: &Token) -> Result<u16, AsmError> {
+        if self.labels.contains_key(&token.text) || !self.is_label_name(token) {
+            let address = self.value(token)?;
+            return self.check_address(token, address);
+        }
+
+        self.fixup(token, FixupKind::Address);
+        Ok(0)
+    }
+
+    // 16-bit address following the long load emitted next
+    fn long_address(&mut self, token: &Token) -> Result<u16, AsmError> {
+        if self.labels.contains_key(&token.text) || !self.is_label_name(token) {
+            let address = self.value(token)?;
+            if !(0..=0xFFFF).contains(&address) {
+                return Err(error(
+                    token,
+                    format!("address {:#X} is out of range", address),
+                ));
+            }
+            return Ok(address as u16);
+        }
+
+        self.fixup(token, FixupKind::Long);
+        Ok(0)
+    }
+
+    // Whether a name could still become a label
+    fn is_label_name(&self, token: &Token) -> bool {
+        parse_number(&token.text).is_none()
+            && !self.constants.contains_key(&token.text)
+            && !self.is_register(token)
+    }
+
+    fn fixup(&mut self, name: &Token, kind: FixupKind) {
+        // Long addresses are stored behind the F000 opcode
+        let offset = match kind {
+            FixupKind::Address => self.rom.len(),
+            FixupKind::Long => self.rom.len() + 2,
+        };
+
+        self.fixups.push(Fixup {
+            offset,
+            kind,
+            name: name.clone(),
+        });
+    }
+
+    // Point the jump at offset to the current address
+    fn patch(&mut self, token: &Token, offset: usize) -> Result<(), AsmError> {
+        let address = self.check_address(token, self.here() as i64)?;
+        self.rom[offset] |= (address >> 8) as u8;
+        self.rom[offset + 1] = address as u8;
+        Ok(())
+    }
+
+    fn here(&self) -> usize {
+        PROGRAM_START as usize + self.rom.len()
+    }
+
+    // Address of the next byte, which has to be inside memory to be referenced
+    fn here_address(&self, token: &Token) -> Result<u16, AsmError> {
+        let address = self.here();
+        if address >= self.platform.memory_size() {
+            return Err(error(token, "program exceeds memory".to_string()));
+        }
+
+        Ok(address as u16)
+    }
+
+    fn emit(&mut self, token: &Token, instruction: Instruction) -> Result<(), AsmError> {
+        if !self.platform.supports(&instruction) {
+            return Err(error(
+                token,
+                format!(
+                    "'{}' is not supported on {}",
+                    token.text,
+                    self.platform.name()
+                ),
+            ));
+        }
+
+        for byte in instruction.opcode().to_be_bytes() {
+            self.emit_byte(token, byte)?;
+        }
+        if let Instruction::LdILong(address) = instruction {
+            for byte in address.to_be_bytes() {
+                self.emit_byte(token, byte)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn emit_byte(&mut self, token: &Token, byte: u8) -> Result<(), AsmError> {
+        if self.here() >= self.platform.memory_size() {
+            return Err(error(token, "program exceeds memory".to_string()));
+        }
+
+        self.rom.push(byte);
+        Ok(())
+    }
+
+    fn unknown_operator(&self, token: &Token) -> AsmError {
+        error(token, format!("unknown operator '{}'", token.text))
+    }
+
+    fn overflow(&self, token: &Token) -> AsmError {
+        error(token, format!("'{}' overflows", token.text))
+    }
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::{Chip8, Config};
+
+    // Macro to shadow prelude with pretty_assertions
+    macro_rules! assert_eq {
+        ($($tt:tt)*) => {
+            pretty_assertions::assert_eq!($($tt)*)
+        };
+    }
+
+    // Helper function to compile for CHIP-8
+    fn chip8(source: &str) -> Result<Vec<u8>, AsmError> {
+        compile(source, Platform::Chip8)
+    }
+
+    // Helper function to build the expected error
+    fn err(line: usize, column: usize, message: &str) -> Result<Vec<u8>, AsmError> {
+        Err(AsmError {
+            line,
+            column,
+            message: message.to_string(),
+        })
+    }
+
+    // Helper function to run a compiled program until it reaches an endless jump
+    fn run(source: &str) -> Chip8 {
+        let mut chip = Chip8::with_config(Config {
+            seed: Some(0),
+            ..Config::default()
+        });
+        chip.init(&chip8(source).unwrap());
+
+        for _ in 0..1000 {
+            if chip.memory()[chip.pc() as usize..][..2] == (0x1000 | chip.pc()).to_be_bytes() {
+                break;
+            }
+            chip.step().unwrap();
+        }
+
+        chip
+    }
+
+    #[test]
+    fn test_statements() {
+        let source = "
+            : main
+                clear
+                v0 := 5  v1 := v0  v2 += 3  v2 += v1  v2 -= 1  v3 -= v2  v3 =- v2
+                v4 |= v5  v4 &= v5  v4 ^= v5  v4 >>= v5  v4 <<= v5
+                va := random 0x0F  vb := key  vc := delay
+                delay := va  buzzer := vb
+                i := digits  i := hex v0  i += v1
+                bcd v2  save v3  load v3
+                sprite v0 v1 5
+                draw
+                jump0 main
+            : draw
+                return
+            : digits
+                0xF0 0x90 -1
+        ";
+
+        let expected = [
+            0x00, 0xE0, 0x60, 0x05, 0x81, 0x00, 0x72, 0x03, // clear, :=, +=
+            0x82, 0x14, 0x72, 0xFF, 0x83, 0x25, 0x83, 0x27, // +=, -=, =-
+            0x84, 0x51, 0x84, 0x52, 0x84, 0x53, 0x84, 0x56, // |=, &=, ^=, >>=
+            0x84, 0x5E, 0xCA, 0x0F, 0xFB, 0x0A, 0xFC, 0x07, // <<=, random, key, delay
+            0xFA, 0x15, 0xFB, 0x18, 0xA2, 0x38, 0xF0, 0x29, // timers, i :=
+            0xF1, 0x1E, 0xF2, 0x33, 0xF3, 0x55, 0xF3, 0x65, // i +=, bcd, save, load
+            0xD0, 0x15, 0x22, 0x36, 0xB2, 0x00, 0x00, 0xEE, // sprite, call, jump0, return
+            0xF0, 0x90, 0xFF, // sprite data
+        ];
+        assert_eq!(Ok(expected.to_vec()), chip8(source));
+    }
+
+    #[test]
+    fn test_main() {
+        // Jump to main unless the program starts with it
+        assert_eq!(
+            Ok(vec![0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]),
+            chip8(": sub return : main sub")
+        );
+        assert_eq!(Ok(vec![0x00, 0xE0]), chip8(": main clear"));
+        assert_eq!(err(1, 1, "undefined label 'main'"), chip8("clear"));
+    }
+
+    #[test]
+    fn test_directives() {
+        let source = "
+            :const SPEED 3
+            :alias x v5
+            :calc SPRITES { 2 * ( SPEED + 1 ) }
+            :calc RIGHT { 10 - 4 - 1 }
+            :macro move reg amount { reg += amount }
+            : main
+                move x SPEED
+                x := SPRITES
+                x := RIGHT
+                :byte 7
+                :call main
+        ";
+
+        // Definitions come before main, so it is jumped to. 10 - (4 - 1) as calc is evaluated
+        // right to left
+        let expected = [
+            0x12, 0x02, 0x75, 0x03, 0x65, 0x08, 0x65, 0x07, 0x07, 0x22, 0x02,
+        ];
+        assert_eq!(Ok(expected.to_vec()), chip8(source));
+    }
+
+    #[test]
+    fn test_macro_uses() {
+        // Only nesting is limited, not how often a macro is used
+        let source = format!(":macro inc {{ v0 += 1 }} : main {}", "inc ".repeat(10_001));
+
+        let rom = compile(&source, Platform::XoChip).unwrap();
+
+        // Behind the jump to main
+        assert_eq!(20_004, rom.len());
+        assert!(rom[2..].chunks(2).all(|opcode| opcode == [0x70, 0x01]));
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let source = "
+            : main
+                loop
+                    v0 += 1
+                    if v0 == 10 then v1 := 1
+                    if v0 != v2 begin
+                        v2 := 0
+                    else
+                        v2 := 1
+                    end
+                    while v0 key
+                again
+        ";
+
+        let expected = [
+            0x70, 0x01, // loop: v0 += 1
+            0x40, 0x0A, 0x61, 0x01, // if then: skip unless v0 == 10
+            0x90, 0x20, 0x12, 0x0E, 0x62, 0x00, 0x12, 0x10, // if begin, else
+            0x62, 0x01, // else branch
+            0xE0, 0x9E, 0x12, 0x16, // while: leave unless key v0
+            0x12, 0x00, // again
+        ];
+        assert_eq!(Ok(expected.to_vec()), chip8(source));
+    }
+
+    #[test]
+    fn test_comparisons() {
+        // Each comparison sets a bit of v3 when it holds
+        let compare = |a: u8, b: u8| {
+            let source = format!(
+                ": main
+                    v0 := {} v1 := {}
+                    if v0 < v1 then v3 += 1
+                    if v0 > v1 then v3 += 2
+                    if v0 <= v1 then v3 += 4
+                    if v0 >= v1 then v3 += 8
+                    if v0 < {} then v3 += 16
+                    if v0 >= {} then v3 += 32
+                    : end jump end",
+                a, b, b, b
+            );
+            run(&source).registers()[3]
+        };
+
+        assert_eq!(1 | 4 | 16, compare(1, 2));
+        assert_eq!(2 | 8 | 32, compare(200, 100));
+        assert_eq!(4 | 8 | 32, compare(7, 7));
+    }
+
+    #[test]
+    fn test_loop() {
+        // Sum of 1 to 10
+        let chip = run(": main
+            loop
+                v0 += 1
+                v1 += v0
+                while v0 != 10
+            again
+            : end jump end");
+
+        assert_eq!(55, chip.registers()[1]);
+    }
+
+    #[test]
+    fn test_platforms() {
+        let source = ": main hires scroll-down 2 i := long data plane 3 : data";
+
+        assert_eq!(
+            err(1, 8, "'hires' is not supported on chip8"),
+            chip8(source)
+        );
+        assert_eq!(
+            err(1, 28, "'i' is not supported on schip"),
+            compile(source, Platform::SuperChip)
+        );
+        assert_eq!(
+            Ok(vec![
+                0x00, 0xFF, 0x00, 0xC2, 0xF0, 0x00, 0x02, 0x0A, 0xF3, 0x01
+            ]),
+            compile(source, Platform::XoChip)
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            (
+                ": main\n  v0 := 256",
+                err(2, 9, "value 256 does not fit in a byte"),
+            ),
+            (
+                ": main v0 += nowhere",
+                err(1, 14, "undefined name 'nowhere'"),
+            ),
+            (": main nowhere", err(1, 8, "undefined label 'nowhere'")),
+            (
+                ": main : main",
+                err(1, 10, "label 'main' is already defined"),
+            ),
+            (": main v0 ** 1", err(1, 11, "unknown operator '**'")),
+            (": main loop", err(1, 8, "'loop' without 'again'")),
+            (": main if v0 == 1 begin", err(1, 8, "'if' without 'end'")),
+            (": main again", err(1, 8, "'again' without 'loop'")),
+            (
+                ": main if v0 == 1 else",
+                err(1, 19, "expected 'then' or 'begin', found 'else'"),
+            ),
+            (": main v0 :=", err(1, 11, "unexpected end of program")),
+            (
+                ": main sprite v0 v1 16",
+                err(1, 21, "value 16 is out of range 0-15"),
+            ),
+            (":calc x { 1 / 0 }", err(1, 13, "division by zero")),
+            (
+                ":calc a { 9223372036854775807 + 1 } :calc b { - a }",
+                err(1, 47, "'-' overflows"),
+            ),
+            (
+                ":calc a { 9223372036854775807 + 1 } :calc b { a / -1 }",
+                err(1, 49, "'/' overflows"),
+            ),
+            (
+                ":calc a { 9223372036854775807 + 1 } :calc b { a % -1 }",
+                err(1, 49, "'%' overflows"),
+            ),
+            (
+                ":macro m { m } : main m",
+                err(1, 12, "macro 'm' expands forever"),
+            ),
+            (
+                ": main jump 0x1000",
+                err(1, 13, "address 0x1000 is out of range, use 'i := long'"),
+            ),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(expected, chip8(source), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        // Data up to the last byte of the 64K XO-CHIP memory
+        let data = "0\n".repeat(0x10000 - 0x200);
+        let line = 0x10000 - 0x200 + 2;
+
+        let full = compile(&format!(": main\n{}", data), Platform::XoChip).unwrap();
+        assert_eq!(0x10000 - 0x200, full.len());
+
+        // Column of the token that needs an address past the end
+        for (source, column) in [(": end", 3), ("loop again", 1), (":calc end { HERE }", 13)] {
+            assert_eq!(
+                err(line, column, "program exceeds memory"),
+                compile(&format!(": main\n{}{}", data, source), Platform::XoChip),
+                "{}",
+                source
+            );
+        }
+    }
+}
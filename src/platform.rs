@@ -0,0 +1,96 @@
+use crate::instruction::Instruction;
+use crate::quirks::Quirks;
+
+// =================================
+// Instruction sets of the different CHIP-8 variants
+// =================================
+
+/// Instruction set a chip8 emulates
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Platform {
+    /// Original CHIP-8 with a 64x32 display
+    #[default]
+    Chip8,
+    /// SUPER-CHIP 1.1, adding a 128x64 hi-res mode, scrolling, a large font and RPL user flags
+    SuperChip,
+    /// XO-CHIP, extending SUPER-CHIP with 64 KiB of memory, two bitplanes and audio patterns
+    XoChip,
+}
+
+impl Platform {
+    /// Names accepted by [`Platform::from_name`]
+    pub const NAMES: [&'static str; 3] = ["chip8", "schip", "xochip"];
+
+    /// Look up a platform by name, see [`Platform::NAMES`]
+    pub fn from_name(name: &str) -> Option<Platform> {
+        match name.to_ascii_lowercase().as_str() {
+            "chip8" | "chip-8" => Some(Platform::Chip8),
+            "schip" | "super-chip" => Some(Platform::SuperChip),
+            "xochip" | "xo-chip" => Some(Platform::XoChip),
+            _ => None,
+        }
+    }
+
+    /// Name of the platform, see [`Platform::NAMES`]
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Chip8 => "chip8",
+            Platform::SuperChip => "schip",
+            Platform::XoChip => "xochip",
+        }
+    }
+
+    /// Quirks of the reference interpreter of the platform
+    pub fn default_quirks(self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::default(),
+            Platform::SuperChip => Quirks::super_chip(),
+            Platform::XoChip => Quirks::xo_chip(),
+        }
+    }
+
+    /// Size of the address space in bytes
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::Chip8 | Platform::SuperChip => 0x1000,
+            Platform::XoChip => 0x10000,
+        }
+    }
+
+    /// Whether the SUPER-CHIP instructions are available
+    pub fn has_super_chip(self) -> bool {
+        match self {
+            Platform::Chip8 => false,
+            Platform::SuperChip | Platform::XoChip => true,
+        }
+    }
+
+    /// Whether the XO-CHIP instructions are available
+    pub fn has_xo_chip(self) -> bool {
+        self == Platform::XoChip
+    }
+
+    /// Whether the instruction is part of the instruction set of the platform
+    pub fn supports(self, instruction: &Instruction) -> bool {
+        match instruction {
+            Instruction::ScrollDown(_)
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Exit
+            | Instruction::Low
+            | Instruction::High
+            | Instruction::LdHfVx(_)
+            | Instruction::LdRVx(_)
+            | Instruction::LdVxR(_) => self.has_super_chip(),
+            Instruction::ScrollUp(_)
+            | Instruction::Save(..)
+            | Instruction::Load(..)
+            | Instruction::LdILong(_)
+            | Instruction::Plane(_)
+            | Instruction::Audio
+            | Instruction::Pitch(_) => self.has_xo_chip(),
+            Instruction::Unknown(_) => false,
+            _ => true,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+// =================================
+// Quirks of the different CHIP-8 interpreters
+// =================================
+
+/// How FX55/FX65 change I after a register transfer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum IndexIncrement {
+    /// I stays unchanged (SUPER-CHIP 1.1)
+    #[default]
+    None,
+    /// I += X (CHIP-48)
+    ByX,
+    /// I += X + 1 (COSMAC VIP, XO-CHIP)
+    ByXPlusOne,
+}
+
+/// Behaviour of the instructions that are ambiguous between interpreters
+///
+/// The default is the behaviour described by Cowgod's technical reference
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Quirks {
+    /// 8XY1, 8XY2 and 8XY3 reset VF to 0
+    pub vf_reset: bool,
+    /// 8XY6 and 8XYE shift VY and store the result in VX instead of shifting VX in place
+    pub shift_uses_vy: bool,
+    /// Change of I after FX55 and FX65
+    pub index_increment: IndexIncrement,
+    /// BNNN is interpreted as BXNN and jumps to XNN + VX instead of NNN + V0
+    pub jump_uses_vx: bool,
+    /// DXYN wraps sprites around the screen edges instead of clipping them
+    pub wrap_sprites: bool,
+}
+
+impl Quirks {
+    /// The original interpreter on the RCA COSMAC VIP
+    pub const fn cosmac_vip() -> Quirks {
+        Quirks {
+            vf_reset: true,
+            shift_uses_vy: true,
+            index_increment: IndexIncrement::ByXPlusOne,
+            jump_uses_vx: false,
+            wrap_sprites: false,
+        }
+    }
+
+    /// CHIP-48 on the HP-48 calculators
+    pub const fn chip48() -> Quirks {
+        Quirks {
+            vf_reset: false,
+            shift_uses_vy: false,
+            index_increment: IndexIncrement::ByX,
+            jump_uses_vx: true,
+            wrap_sprites: false,
+        }
+    }
+
+    /// SUPER-CHIP 1.1 on the HP-48 calculators
+    pub const fn super_chip() -> Quirks {
+        Quirks {
+            vf_reset: false,
+            shift_uses_vy: false,
+            index_increment: IndexIncrement::None,
+            jump_uses_vx: true,
+            wrap_sprites: false,
+        }
+    }
+
+    /// XO-CHIP as implemented by Octo
+    pub const fn xo_chip() -> Quirks {
+        Quirks {
+            vf_reset: false,
+            shift_uses_vy: true,
+            index_increment: IndexIncrement::ByXPlusOne,
+            jump_uses_vx: false,
+            wrap_sprites: true,
+        }
+    }
+
+    /// Names accepted by [`Quirks::preset`]
+    pub const PRESET_NAMES: [&'static str; 5] = ["cowgod", "vip", "chip48", "schip", "xochip"];
+
+    /// Look up a preset by name, see [`Quirks::PRESET_NAMES`]
+    pub fn preset(name: &str) -> Option<Quirks> {
+        match name.to_ascii_lowercase().as_str() {
+            "cowgod" | "default" => Some(Quirks::default()),
+            "vip" | "cosmac-vip" => Some(Quirks::cosmac_vip()),
+            "chip48" | "chip-48" => Some(Quirks::chip48()),
+            "schip" | "super-chip" => Some(Quirks::super_chip()),
+            "xochip" | "xo-chip" => Some(Quirks::xo_chip()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,615 @@
+use std::io::{self, BufRead, Write};
+
+use chip8::chip8::AccessKind;
+use chip8::debugger::{
+    self, Breakpoint, Debugger, Register, Stop, WatchAccess, WatchHit, WatchTarget, Watchpoint,
+};
+use chip8::expr::{Expr, Template};
+
+// =================================
+// Interactive debugger prompt
+// =================================
+
+// Reads one command per line and prints the result. Addresses and values are hexadecimal with or
+// without 0x prefix, counts are decimal. Conditions and log messages take the rest of the line and
+// use the expression language of the library.
+
+pub const HELP: &str = "\
+Commands:
+  s, step [n]          Execute n instructions (default 1), stopping at breakpoints
+  n, next              Execute one instruction, stepping over subroutine calls
+  finish               Run until the current subroutine returns
+  c, continue          Run until a breakpoint is hit or the program stops
+  b, break [addr]      Set a breakpoint, or list all breakpoints
+  b <addr> if <expr>   Only stop at addr if expr is non-zero, e.g. v3 == 0x10 && [i+2] != 0
+                       (registers, i, sp, pc, dt, st, [addr] bytes and the hit count hits)
+  log <addr> <message> Print message instead of stopping at addr, {expr} and {expr:x} in it
+                       are replaced by the value of expr
+  d, delete <addr>     Remove a breakpoint or logpoint
+  watch [what] [value] Stop after writes to memory at addr or addr-end, or after a register
+                       (v0-vf, i, dt, st) changes, to value if given. Lists all without what
+  rwatch <addr>        Stop after memory at addr or addr-end is fetched or read
+  awatch <addr>        Stop after memory at addr or addr-end is accessed in any way
+  unwatch <n>          Remove a watchpoint
+  r, regs              Show the registers, timers and stack
+  x [addr] [len]       Hexdump memory (default: around I)
+  press <key>          Hold down a key of the hex keypad
+  release <key>        Release a key of the hex keypad
+  h, help              Show this message
+  q, quit              Leave the debugger";
+
+const PROMPT: &str = "(chip8) ";
+
+// Instructions continue, next and finish run before handing control back
+const RUN_LIMIT: u64 = 1_000_000;
+
+// Bytes x dumps if no length is given
+const DEFAULT_DUMP_LENGTH: usize = 64;
+
+// Run the prompt until quit or the end of input
+pub fn run<R: BufRead, W: Write>(
+    debugger: &mut Debugger,
+    mut input: R,
+    output: &mut W,
+) -> io::Result<()> {
+    writeln!(output, "{}", location(debugger))?;
+
+    loop {
+        write!(output, "{}", PROMPT)?;
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = words.split_first() else {
+            continue;
+        };
+
+        match execute(debugger, command, args, &line) {
+            Ok(Some(reply)) => writeln!(output, "{}", reply)?,
+            Ok(None) => return Ok(()),
+            Err(err) => writeln!(output, "Error: {}", err)?,
+        }
+    }
+}
+
+// Execute a single command of line, None means quit
+fn execute(
+    debugger: &mut Debugger,
+    command: &str,
+    args: &[&str],
+    line: &str,
+) -> Result<Option<String>, String> {
+    let reply = match (command, args) {
+        ("s" | "step", [] | [_]) => {
+            let count = match args {
+                [count] => count
+                    .parse()
+                    .map_err(|_| format!("invalid count '{}'", count))?,
+                _ => 1,
+            };
+
+            let stop = debugger.step_n(count);
+            stopped(debugger, stop)
+        }
+        ("n" | "next", []) => {
+            let stop = debugger.step_over(RUN_LIMIT);
+            stopped(debugger, stop)
+        }
+        ("finish", []) => {
+            if debugger.chip().sp() == 0 {
+                return Err("not inside a subroutine".to_string());
+            }
+            let stop = debugger.step_out(RUN_LIMIT);
+            stopped(debugger, stop)
+        }
+        ("c" | "continue", []) => {
+            let stop = debugger.resume(RUN_LIMIT);
+            stopped(debugger, stop)
+        }
+        ("b" | "break", []) => {
+            let breakpoints: Vec<String> = debugger
+                .breakpoints()
+                .map(|(address, breakpoint)| describe_breakpoint(address, breakpoint))
+                .collect();
+            if breakpoints.is_empty() {
+                "No breakpoints".to_string()
+            } else {
+                breakpoints.join("\n")
+            }
+        }
+        ("b" | "break", [address]) => {
+            let address = parse_address(debugger, address)?;
+            debugger.insert_breakpoint(address, Breakpoint::default());
+            format!("Breakpoint at {:04X}", address)
+        }
+        ("b" | "break", [address, "if", _, ..]) => {
+            let address = parse_address(debugger, address)?;
+            let condition = Expr::parse(rest_of_line(line, 3))
+                .map_err(|err| format!("invalid condition, {}", err))?;
+            let reply = format!("Breakpoint at {:04X} if {}", address, condition);
+            let breakpoint = Breakpoint {
+                condition: Some(condition),
+                ..Breakpoint::default()
+            };
+            debugger.insert_breakpoint(address, breakpoint);
+            reply
+        }
+        ("log", [address, _, ..]) => {
+            let address = parse_address(debugger, address)?;
+            let message = Template::parse(rest_of_line(line, 2))
+                .map_err(|err| format!("invalid message, {}", err))?;
+            let reply = format!("Logpoint at {:04X}: {}", address, message);
+            let breakpoint = Breakpoint {
+                log: Some(message),
+                ..Breakpoint::default()
+            };
+            debugger.insert_breakpoint(address, breakpoint);
+            reply
+        }
+        ("d" | "delete", [address]) => {
+            let address = parse_address(debugger, address)?;
+            if !debugger.clear_breakpoint(address) {
+                return Err(format!("no breakpoint at {:04X}", address));
+            }
+            format!("Deleted breakpoint at {:04X}", address)
+        }
+        ("watch", []) => {
+            let watchpoints: Vec<String> = debugger
+                .watchpoints()
+                .map(|(id, watchpoint)| format!("{}: {}", id, describe_watchpoint(watchpoint)))
+                .collect();
+            if watchpoints.is_empty() {
+                "No watchpoints".to_string()
+            } else {
+                watchpoints.join("\n")
+            }
+        }
+        ("watch" | "rwatch" | "awatch", [target] | [target, _]) => {
+            let watchpoint = match (command, Register::from_name(target), args) {
+                ("watch", Some(register), [_]) => Watchpoint::Register {
+                    register,
+                    value: None,
+                },
+                ("watch", Some(register), [_, value]) => Watchpoint::Register {
+                    register,
+                    value: Some(parse_value(value)?),
+                },
+                (_, None, [_]) => {
+                    let (start, end) = parse_range(debugger, target)?;
+                    let access = match command {
+                        "watch" => WatchAccess::Write,
+                        "rwatch" => WatchAccess::Read,
+                        _ => WatchAccess::ReadWrite,
+                    };
+                    Watchpoint::Memory { start, end, access }
+                }
+                _ => return Err(format!("wrong arguments for '{}', see help", command)),
+            };
+            add_watchpoint(debugger, watchpoint)?
+        }
+        ("unwatch", [id]) => {
+            let id = id
+                .parse()
+                .map_err(|_| format!("invalid watchpoint '{}'", id))?;
+            if !debugger.remove_watchpoint(id) {
+                return Err(format!("no watchpoint {}", id));
+            }
+            format!("Deleted watchpoint {}", id)
+        }
+        ("r" | "regs", []) => debugger::dump_state(debugger.chip()),
+        ("x", []) => debugger.dump_index(),
+        ("x", [address] | [address, _]) => {
+            let start = parse_address(debugger, address)? as usize;
+            let length = match args {
+                [_, length] => length
+                    .parse()
+                    .map_err(|_| format!("invalid length '{}'", length))?,
+                _ => DEFAULT_DUMP_LENGTH,
+            };
+            debugger::hexdump(debugger.chip().memory(), start, length)
+        }
+        ("press", [key]) => {
+            let key = parse_key(key)?;
+            debugger.chip_mut().press_key(key);
+            format!("Key {:X} down", key)
+        }
+        ("release", [key]) => {
+            let key = parse_key(key)?;
+            debugger.chip_mut().release_key(key);
+            format!("Key {:X} up", key)
+        }
+        ("h" | "help", []) => HELP.to_string(),
+        ("q" | "quit", []) => return Ok(None),
+        (
+            "s" | "step" | "n" | "next" | "finish" | "c" | "continue" | "b" | "break" | "log" | "d"
+            | "delete" | "watch" | "rwatch" | "awatch" | "unwatch" | "r" | "regs" | "x" | "press"
+            | "release" | "h" | "help" | "q" | "quit",
+            _,
+        ) => return Err(format!("wrong arguments for '{}', see help", command)),
+        _ => return Err(format!("unknown command '{}', see help", command)),
+    };
+
+    Ok(Some(reply))
+}
+
+// Helper function to describe why execution stopped, followed by the next instruction. Messages of
+// the logpoints reached on the way come first.
+fn stopped(debugger: &mut Debugger, stop: Stop) -> String {
+    let mut lines = debugger.take_log();
+    lines.push(match stop {
+        Stop::Stepped => location(debugger),
+        stop => format!("{}\n{}", describe_stop(stop), location(debugger)),
+    });
+
+    lines.join("\n")
+}
+
+fn describe_stop(stop: Stop) -> String {
+    match stop {
+        Stop::Stepped => "Stepped".to_string(),
+        Stop::Breakpoint(address) => format!("Breakpoint at {:04X}", address),
+        Stop::Watchpoint(hit) => describe_hit(&hit),
+        Stop::WaitingForKey => "Waiting for a key, use press and release".to_string(),
+        Stop::Exited => "Program exited".to_string(),
+        Stop::Limit => format!("Stopped after {} instructions", RUN_LIMIT),
+        Stop::Fault(error) => format!("ROM fault: {}", error),
+    }
+}
+
+fn describe_breakpoint(address: u16, breakpoint: &Breakpoint) -> String {
+    let mut description = format!("{:04X}", address);
+    if let Some(condition) = &breakpoint.condition {
+        description += &format!(" if {}", condition);
+    }
+    if let Some(message) = &breakpoint.log {
+        description += &format!(" log {}", message);
+    }
+    match breakpoint.hits {
+        0 => {}
+        1 => description += " (1 hit)",
+        hits => description += &format!(" ({} hits)", hits),
+    }
+
+    description
+}
+
+// Helper function to add a watchpoint and confirm it
+fn add_watchpoint(debugger: &mut Debugger, watchpoint: Watchpoint) -> Result<String, String> {
+    let id = debugger
+        .add_watchpoint(watchpoint)
+        .ok_or("watched memory is out of range")?;
+
+    Ok(format!(
+        "Watchpoint {}: {}",
+        id,
+        describe_watchpoint(watchpoint)
+    ))
+}
+
+fn describe_watchpoint(watchpoint: Watchpoint) -> String {
+    match watchpoint {
+        Watchpoint::Memory { start, end, access } => {
+            let access = match access {
+                WatchAccess::Read => "read",
+                WatchAccess::Write => "write",
+                WatchAccess::ReadWrite => "access",
+            };
+            if start == end {
+                format!("{} {:04X}", access, start)
+            } else {
+                format!("{} {:04X}-{:04X}", access, start, end)
+            }
+        }
+        Watchpoint::Register { register, value } => match value {
+            Some(value) => format!("{} changes to {}", register, format_value(register, value)),
+            None => format!("{} changes", register),
+        },
+    }
+}
+
+fn describe_hit(hit: &WatchHit) -> String {
+    let at = format!("at {:04X} ({})", hit.pc, hit.instruction);
+
+    match hit.target {
+        WatchTarget::Register(register) => format!(
+            "Watchpoint {}: {} changed {}: {} -> {}",
+            hit.id,
+            register,
+            at,
+            format_value(register, hit.old),
+            format_value(register, hit.new)
+        ),
+        WatchTarget::Memory(address) if hit.kind == AccessKind::Write => format!(
+            "Watchpoint {}: write of {:04X} {}: {:02X} -> {:02X}",
+            hit.id, address, at, hit.old, hit.new
+        ),
+        WatchTarget::Memory(address) => format!(
+            "Watchpoint {}: {} of {:04X} {}: {:02X}",
+            hit.id,
+            if hit.kind == AccessKind::Fetch {
+                "fetch"
+            } else {
+                "read"
+            },
+            address,
+            at,
+            hit.new
+        ),
+    }
+}
+
+// Helper function to format a register value with the width of the register
+fn format_value(register: Register, value: u16) -> String {
+    match register {
+        Register::I => format!("{:04X}", value),
+        _ => format!("{:02X}", value),
+    }
+}
+
+// Helper function to format the instruction at the program counter
+fn location(debugger: &Debugger) -> String {
+    let pc = debugger.chip().pc();
+
+    match debugger.chip().instruction_at(pc) {
+        Some(instruction) => format!("{:04X}: {}", pc, instruction),
+        None => format!("{:04X}: out of memory", pc),
+    }
+}
+
+// Helper function to skip the first words of line, keeping the spacing of the rest
+fn rest_of_line(line: &str, words: usize) -> &str {
+    let mut rest = line.trim();
+    for _ in 0..words {
+        rest = rest
+            .split_once(char::is_whitespace)
+            .map_or("", |(_, rest)| rest.trim_start());
+    }
+
+    rest
+}
+
+fn parse_address(debugger: &Debugger, text: &str) -> Result<u16, String> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+
+    match usize::from_str_radix(digits, 16) {
+        Ok(address) if address < debugger.chip().memory().len() => Ok(address as u16),
+        Ok(_) => Err(format!("address {} is out of range", text)),
+        Err(_) => Err(format!("invalid address '{}'", text)),
+    }
+}
+
+// Single address or inclusive range like 300-30F
+fn parse_range(debugger: &Debugger, text: &str) -> Result<(u16, u16), String> {
+    let (start, end) = text.split_once('-').unwrap_or((text, text));
+    let (start, end) = (
+        parse_address(debugger, start)?,
+        parse_address(debugger, end)?,
+    );
+
+    if start > end {
+        return Err(format!("range {} is empty", text));
+    }
+    Ok((start, end))
+}
+
+fn parse_value(text: &str) -> Result<u16, String> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+
+    u16::from_str_radix(digits, 16).map_err(|_| format!("invalid value '{}'", text))
+}
+
+fn parse_key(text: &str) -> Result<u8, String> {
+    match u8::from_str_radix(text, 16) {
+        Ok(key) if key <= 0xF => Ok(key),
+        _ => Err(format!("invalid key '{}', keys are 0-F", text)),
+    }
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8::Chip8;
+
+    // Helper function to run a debugger session on a ROM and return its output
+    fn session(rom: &[u8], input: &str) -> String {
+        let mut chip = Chip8::new();
+        chip.init(rom);
+        let mut debugger = Debugger::new(chip, 10);
+
+        let mut output = Vec::new();
+        run(&mut debugger, input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_session() {
+        let rom = [
+            0x60, 0x05, // 0200: LD V0, 0x05
+            0x22, 0x08, // 0202: CALL 0x208
+            0xA2, 0x0C, // 0204: LD I, 0x20C
+            0x12, 0x06, // 0206: JP 0x206
+            0x70, 0x01, // 0208: ADD V0, 0x01
+            0x00, 0xEE, // 020A: RET
+            0xAB, 0xCD, // 020C: data
+        ];
+        let input = "s\nn\nb 20a\nb\nc\nfinish\ns 1\nx\nx 0x20C 2\nd 20A\nr\nq\ns\n";
+
+        let expected = "\
+0200: LD V0, 0x05
+(chip8) 0202: CALL 0x208
+(chip8) 0204: LD I, 0x20C
+(chip8) Breakpoint at 020A
+(chip8) 020A
+(chip8) Stopped after 1000000 instructions
+0206: JP 0x206
+(chip8) Error: not inside a subroutine
+(chip8) 0206: JP 0x206
+(chip8) 01F0: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+0200: 60 05 22 08 A2 0C 12 06 70 01 00 EE AB CD 00 00
+0210: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+(chip8) 020C: AB CD
+(chip8) Deleted breakpoint at 020A
+(chip8) V0=06 V1=00 V2=00 V3=00 V4=00 V5=00 V6=00 V7=00 V8=00 V9=00 VA=00 VB=00 VC=00 VD=00 VE=00 VF=00
+PC=0206 I=020C SP=0 DT=0 ST=0
+Stack: []
+(chip8) ";
+        assert_eq!(expected, session(&rom, input));
+    }
+
+    #[test]
+    fn test_step_breakpoint() {
+        let rom = [
+            0x60, 0x05, // 0200: LD V0, 0x05
+            0x22, 0x06, // 0202: CALL 0x206
+            0x12, 0x04, // 0204: JP 0x204
+            0x70, 0x01, // 0206: ADD V0, 0x01
+            0x00, 0xEE, // 0208: RET
+        ];
+        let input = "b 206\ns 10\ns 2\n";
+
+        let expected = "\
+0200: LD V0, 0x05
+(chip8) Breakpoint at 0206
+(chip8) Breakpoint at 0206
+0206: ADD V0, 0x01
+(chip8) 0204: JP 0x204
+(chip8) ";
+        assert_eq!(expected, session(&rom, input));
+    }
+
+    #[test]
+    fn test_breakpoints_and_keys() {
+        let rom = [
+            0xF3, 0x0A, // 0200: LD V3, K
+            0x00, 0xEE, // 0202: RET
+        ];
+        let input = "b 202\nc\npress 7\nc\nrelease 7\nc\nc\n";
+
+        let expected = "\
+0200: LD V3, K
+(chip8) Breakpoint at 0202
+(chip8) Waiting for a key, use press and release
+0202: RET
+(chip8) Key 7 down
+(chip8) Waiting for a key, use press and release
+0202: RET
+(chip8) Key 7 up
+(chip8) Breakpoint at 0202
+0202: RET
+(chip8) ROM fault: stack underflow (opcode 00EE at 0202)
+0202: RET
+(chip8) ";
+        assert_eq!(expected, session(&rom, input));
+    }
+
+    #[test]
+    fn test_conditions_and_logpoints() {
+        let rom = [
+            0x70, 0x01, // 0200: ADD V0, 0x01
+            0xA3, 0x00, // 0202: LD I, 0x300
+            0xF0, 0x55, // 0204: LD [I], V0
+            0x12, 0x00, // 0206: JP 0x200
+        ];
+        let input = "b 204 if v0 == 3 && hits > 1\nlog 206 v0={v0:x}  [i]={[i]}\nc\nb\n\
+                     b 204 if v0 ==\nlog 200 {v0\nb 204 if hits % 4 == 0\nc\n";
+
+        let expected = "\
+0200: ADD V0, 0x01
+(chip8) Breakpoint at 0204 if v0 == 3 && hits > 1
+(chip8) Logpoint at 0206: v0={v0:x}  [i]={[i]}
+(chip8) v0=1  [i]=1
+v0=2  [i]=2
+Breakpoint at 0204
+0204: LD [I], V0
+(chip8) 0204 if v0 == 3 && hits > 1 (3 hits)
+0206 log v0={v0:x}  [i]={[i]} (2 hits)
+(chip8) Error: invalid condition, column 6: missing value
+(chip8) Error: invalid message, column 1: '{' without '}'
+(chip8) Breakpoint at 0204 if hits % 4 == 0
+(chip8) v0=3  [i]=3
+v0=4  [i]=4
+v0=5  [i]=5
+v0=6  [i]=6
+Breakpoint at 0204
+0204: LD [I], V0
+(chip8) ";
+        assert_eq!(expected, session(&rom, input));
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let rom = [
+            0xA3, 0x00, // 0200: LD I, 0x300
+            0x60, 0x2A, // 0202: LD V0, 0x2A
+            0xF0, 0x33, // 0204: LD B, V0
+            0xF0, 0x65, // 0206: LD V0, [I]
+            0x12, 0x08, // 0208: JP 0x208
+        ];
+        let input = "watch 301-302\nrwatch 300\nwatch v0 4\nawatch 208\nwatch\nc\nc\nc\nunwatch 3\n\
+                     c\nunwatch 3\nwatch st 1 2\nrwatch v0\nwatch fff-1000\n";
+
+        let expected = "\
+0200: LD I, 0x300
+(chip8) Watchpoint 1: write 0301-0302
+(chip8) Watchpoint 2: read 0300
+(chip8) Watchpoint 3: V0 changes to 04
+(chip8) Watchpoint 4: access 0208
+(chip8) 1: write 0301-0302
+2: read 0300
+3: V0 changes to 04
+4: access 0208
+(chip8) Watchpoint 1: write of 0301 at 0204 (LD B, V0): 00 -> 04
+0206: LD V0, [I]
+(chip8) Watchpoint 2: read of 0300 at 0206 (LD V0, [I]): 00
+0208: JP 0x208
+(chip8) Watchpoint 4: fetch of 0208 at 0208 (JP 0x208): 12
+0208: JP 0x208
+(chip8) Deleted watchpoint 3
+(chip8) Watchpoint 4: fetch of 0208 at 0208 (JP 0x208): 12
+0208: JP 0x208
+(chip8) Error: no watchpoint 3
+(chip8) Error: wrong arguments for 'watch', see help
+(chip8) Error: wrong arguments for 'rwatch', see help
+(chip8) Error: address 1000 is out of range
+(chip8) ";
+        assert_eq!(expected, session(&rom, input));
+    }
+
+    #[test]
+    fn test_errors() {
+        let input = "jump\nb\nb 1000\nb zz\nd 200\nx 200 many\npress 10\nstep 1 2\nhelp\n";
+
+        let output = session(&[0x12, 0x00], input);
+        let replies: Vec<&str> = output.split(PROMPT).skip(1).collect();
+
+        assert_eq!(
+            vec![
+                "Error: unknown command 'jump', see help\n",
+                "No breakpoints\n",
+                "Error: address 1000 is out of range\n",
+                "Error: invalid address 'zz'\n",
+                "Error: no breakpoint at 0200\n",
+                "Error: invalid length 'many'\n",
+                "Error: invalid key '10', keys are 0-F\n",
+                "Error: wrong arguments for 'step', see help\n",
+                &format!("{}\n", HELP),
+                "",
+            ],
+            replies
+        );
+    }
+}
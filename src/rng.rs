@@ -0,0 +1,120 @@
+use std::fmt;
+
+// =================================
+// Random sources for CXNN
+// =================================
+
+// Both generators are implemented here instead of taking one from rand, whose StdRng may change
+// its algorithm with any release. A seed has to produce the same numbers forever, otherwise
+// replays and tests would break with a routine dependency update.
+
+/// Size of the COSMAC VIP CHIP-8 interpreter, see [`VipRng`]
+pub const VIP_INTERPRETER_SIZE: usize = 0x200;
+
+/// Source of the random bytes used by CXNN
+///
+/// Implementations must be cloneable so a snapshot of the emulator includes the generator state
+pub trait RandomSource: Send + fmt::Debug {
+    /// Produce the next random byte
+    fn next_byte(&mut self) -> u8;
+
+    /// Clone the generator including its current state
+    fn clone_box(&self) -> Box<dyn RandomSource>;
+}
+
+impl Clone for Box<dyn RandomSource> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Default random source, reproducible from its seed
+///
+/// Uses SplitMix64, which is fast, passes common statistical tests and is fixed by this crate.
+#[derive(Clone, Debug)]
+pub struct SeededRng {
+    seed: u64,
+    state: u64,
+}
+
+impl SeededRng {
+    /// Create a generator from a fixed seed
+    pub fn new(seed: u64) -> SeededRng {
+        SeededRng { seed, state: seed }
+    }
+
+    /// Create a generator from a random seed, which can be read back to replay a run
+    pub fn from_entropy() -> SeededRng {
+        SeededRng::new(rand::random())
+    }
+
+    /// Seed the generator was created from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RandomSource for SeededRng {
+    fn next_byte(&mut self) -> u8 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^= value >> 31;
+
+        // The high bits are the best mixed ones
+        (value >> 56) as u8
+    }
+
+    fn clone_box(&self) -> Box<dyn RandomSource> {
+        Box::new(self.clone())
+    }
+}
+
+/// Random source modelled on the RND routine of the original COSMAC VIP interpreter
+///
+/// The VIP had no random number hardware. Its interpreter keeps a 16-bit seed in register R9,
+/// increments it for every CXNN and adds the byte of its own code page 0x01xx addressed by the
+/// low byte of the seed to the high byte, which becomes the result. The numbers therefore depend
+/// on the interpreter image, which the host has to provide as it is not part of this crate.
+#[derive(Clone)]
+pub struct VipRng {
+    // Second page of the interpreter, 0x0100-0x01FF, the only one the routine reads
+    page: [u8; 0x100],
+    seed: u16,
+}
+
+impl VipRng {
+    /// Create a generator from a dump of the interpreter at 0x0000-0x01FF and the initial R9
+    pub fn new(interpreter: &[u8; VIP_INTERPRETER_SIZE], seed: u16) -> VipRng {
+        let mut page = [0; 0x100];
+        page.copy_from_slice(&interpreter[0x100..]);
+
+        VipRng { page, seed }
+    }
+
+    /// Current value of R9
+    pub fn seed(&self) -> u16 {
+        self.seed
+    }
+}
+
+impl fmt::Debug for VipRng {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VipRng").field("seed", &self.seed).finish()
+    }
+}
+
+impl RandomSource for VipRng {
+    fn next_byte(&mut self) -> u8 {
+        let [high, low] = self.seed.wrapping_add(1).to_be_bytes();
+        let value = high.wrapping_add(self.page[low as usize]);
+        self.seed = u16::from_be_bytes([value, low]);
+
+        value
+    }
+
+    fn clone_box(&self) -> Box<dyn RandomSource> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+use chip8::chip8::RPL_FLAGS;
+
+// =================================
+// Persistent SUPER-CHIP RPL user flags
+// =================================
+
+// Flags are kept in one file per ROM, named after a hash of the ROM contents so renaming or
+// moving a ROM keeps its high scores
+
+// Default directory of the flag files, $XDG_DATA_HOME/chip8/rpl or ~/.local/share/chip8/rpl
+pub fn default_dir() -> Option<PathBuf> {
+    let data = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+
+    Some(data.join("chip8").join("rpl"))
+}
+
+// Path of the flag file of a ROM
+pub fn flags_path(dir: &Path, rom: &[u8]) -> PathBuf {
+    dir.join(format!("{:016x}.rpl", fnv1a(rom)))
+}
+
+// Read saved flags, a missing file means all flags are zero
+// Shorter files are accepted, SUPER-CHIP 1.1 itself only had 8 flags
+pub fn load(path: &Path) -> io::Result<[u8; RPL_FLAGS]> {
+    let mut flags = [0; RPL_FLAGS];
+
+    match fs::read(path) {
+        Ok(saved) => {
+            let len = saved.len().min(RPL_FLAGS);
+            flags[..len].copy_from_slice(&saved[..len]);
+            Ok(flags)
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(flags),
+        Err(err) => Err(err),
+    }
+}
+
+pub fn save(path: &Path, flags: &[u8; RPL_FLAGS]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, flags)
+}
+
+// 64-bit FNV-1a, stable across builds unlike the std hashers
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_path() {
+        // Reference values of FNV-1a
+        assert_eq!(0xCBF2_9CE4_8422_2325, fnv1a(b""));
+        assert_eq!(0xAF63_DC4C_8601_EC8C, fnv1a(b"a"));
+
+        assert_eq!(
+            Path::new("flags/af63dc4c8601ec8c.rpl"),
+            flags_path(Path::new("flags"), b"a")
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = env::temp_dir().join(format!("chip8-rpl-test-{}", std::process::id()));
+        let path = flags_path(&dir, &[0x00, 0xFD]);
+
+        // Nothing saved yet
+        assert_eq!(Ok([0; RPL_FLAGS]), load(&path).map_err(|err| err.kind()));
+
+        let mut flags = [0; RPL_FLAGS];
+        flags[..3].copy_from_slice(&[1, 2, 3]);
+        save(&path, &flags).unwrap();
+        assert_eq!(flags, load(&path).unwrap());
+
+        // Files of the original 8 flags are padded
+        fs::write(&path, [9; 8]).unwrap();
+        let loaded = load(&path).unwrap();
+        assert_eq!([9; 8], loaded[..8]);
+        assert_eq!([0; 8], loaded[8..]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,302 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+// =================================
+// Framebuffer screenshots
+// =================================
+
+// Lit pixels are drawn white on black, PNG uses the palette for the XO-CHIP plane colours
+
+/// Colours of the four values a pixel can take, see [`crate::Chip8::framebuffer`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette(pub [[u8; 3]; 4]);
+
+impl Default for Palette {
+    // Black background, white for the first plane and grays for the second plane and the overlap
+    fn default() -> Self {
+        Palette([
+            [0x00, 0x00, 0x00],
+            [0xFF, 0xFF, 0xFF],
+            [0xAA, 0xAA, 0xAA],
+            [0x55, 0x55, 0x55],
+        ])
+    }
+}
+
+/// Image format of a screenshot
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Plain (ASCII) portable bitmap, easy to diff
+    Pbm,
+    /// 8-bit indexed-colour PNG with the [`Palette`] as PLTE colour table
+    Png,
+}
+
+impl ImageFormat {
+    /// Pick the format from the file extension of a path
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "pbm" => Some(ImageFormat::Pbm),
+            "png" => Some(ImageFormat::Png),
+            _ => None,
+        }
+    }
+}
+
+/// Write a framebuffer with one byte per pixel as an image, each pixel scaled to a
+/// `scale` x `scale` square
+pub fn write_image<W: Write>(
+    out: W,
+    format: ImageFormat,
+    framebuffer: &[u8],
+    width: usize,
+    height: usize,
+    scale: usize,
+    palette: &Palette,
+) -> io::Result<()> {
+    match format {
+        ImageFormat::Pbm => write_pbm(out, framebuffer, width, height, scale),
+        ImageFormat::Png => write_png(out, framebuffer, width, height, scale, palette),
+    }
+}
+
+/// Write a framebuffer as plain PBM, any non-zero pixel is lit
+pub fn write_pbm<W: Write>(
+    mut out: W,
+    framebuffer: &[u8],
+    width: usize,
+    height: usize,
+    scale: usize,
+) -> io::Result<()> {
+    writeln!(out, "P1")?;
+    writeln!(out, "{} {}", width * scale, height * scale)?;
+
+    for row in scaled_rows(framebuffer, width, height, scale) {
+        // PBM uses 1 for black
+        let line: Vec<&str> = row
+            .iter()
+            .map(|&value| if value != 0 { "0" } else { "1" })
+            .collect();
+        writeln!(out, "{}", line.join(" "))?;
+    }
+
+    Ok(())
+}
+
+/// Write a framebuffer as PNG with the palette as colour table
+pub fn write_png<W: Write>(
+    mut out: W,
+    framebuffer: &[u8],
+    width: usize,
+    height: usize,
+    scale: usize,
+    palette: &Palette,
+) -> io::Result<()> {
+    let (image_width, image_height) = (width * scale, height * scale);
+
+    // Every scanline starts with filter type 0 (none)
+    let mut raw = Vec::with_capacity((image_width + 1) * image_height);
+    for row in scaled_rows(framebuffer, width, height, scale) {
+        raw.push(0);
+        raw.extend(row.iter().map(|&value| value & 0x3));
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(image_width as u32).to_be_bytes());
+    header.extend_from_slice(&(image_height as u32).to_be_bytes());
+    // Bit depth 8, indexed colour, deflate, no filter method extensions, no interlacing
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+
+    out.write_all(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'])?;
+    write_chunk(&mut out, b"IHDR", &header)?;
+    write_chunk(&mut out, b"PLTE", palette.0.as_flattened())?;
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&raw))?;
+    write_chunk(&mut out, b"IEND", &[])
+}
+
+// Helper function to expand the framebuffer into rows of scaled pixels
+fn scaled_rows(
+    framebuffer: &[u8],
+    width: usize,
+    height: usize,
+    scale: usize,
+) -> impl Iterator<Item = Vec<u8>> + '_ {
+    (0..height * scale).map(move |y| {
+        (0..width * scale)
+            .map(|x| framebuffer[(y / scale) * width + x / scale])
+            .collect()
+    })
+}
+
+// Helper function to write a PNG chunk including its checksum
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+
+    let crc = crc32(kind.iter().chain(data));
+    out.write_all(&crc.to_be_bytes())
+}
+
+// Wrap data into a zlib stream of uncompressed deflate blocks
+// Screenshots are tiny, so compression is not worth the complexity
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = 0xFFFF;
+
+    // CMF/FLG: deflate with 32K window, no preset dictionary, lowest compression level
+    let mut stream = vec![0x78, 0x01];
+
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        // An empty stream still needs a final block
+        stream.extend_from_slice(&[0x01, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+
+        stream.push(last as u8);
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+// CRC-32 as used by PNG chunks
+fn crc32<'a, I: IntoIterator<Item = &'a u8>>(bytes: I) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+// Adler-32 as used by zlib streams
+fn adler32(bytes: &[u8]) -> u32 {
+    const MODULO: u32 = 65521;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % MODULO;
+        b = (b + a) % MODULO;
+    }
+
+    (b << 16) | a
+}
+
+// ===========================
+// Unit tests
+// ===========================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Macro to shadow prelude with pretty_assertions
+    macro_rules! assert_eq {
+        ($($tt:tt)*) => {
+            pretty_assertions::assert_eq!($($tt)*)
+        };
+    }
+
+    // 3x2 framebuffer with a diagonal, the second pixel is set in both planes
+    const FRAMEBUFFER: [u8; 6] = [
+        1, 0, 0, //
+        0, 3, 0, //
+    ];
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            Some(ImageFormat::Pbm),
+            ImageFormat::from_path(Path::new("a.pbm"))
+        );
+        assert_eq!(
+            Some(ImageFormat::Png),
+            ImageFormat::from_path(Path::new("a.PNG"))
+        );
+        assert_eq!(None, ImageFormat::from_path(Path::new("a.bmp")));
+        assert_eq!(None, ImageFormat::from_path(Path::new("a")));
+    }
+
+    #[test]
+    fn test_write_pbm() {
+        let mut out = Vec::new();
+        write_pbm(&mut out, &FRAMEBUFFER, 3, 2, 1).unwrap();
+
+        assert_eq!("P1\n3 2\n0 1 1\n1 0 1\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_write_pbm_scaled() {
+        let mut out = Vec::new();
+        write_pbm(&mut out, &FRAMEBUFFER, 3, 2, 2).unwrap();
+
+        let expected = "P1\n6 4\n\
+                        0 0 1 1 1 1\n\
+                        0 0 1 1 1 1\n\
+                        1 1 0 0 1 1\n\
+                        1 1 0 0 1 1\n";
+        assert_eq!(expected, String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_checksums() {
+        // Reference values of the zlib implementations
+        assert_eq!(0xAE42_6082, crc32(b"IEND"));
+        assert_eq!(0xCBF4_3926, crc32(b"123456789"));
+        assert_eq!(0x11E6_0398, adler32(b"Wikipedia"));
+    }
+
+    #[test]
+    fn test_zlib_stored_blocks() {
+        let data: Vec<u8> = (0..70_000).map(|i| i as u8).collect();
+        let stream = zlib_stored(&data);
+
+        // Header, two blocks with five bytes of framing each, trailer
+        assert_eq!(2 + 5 + 0xFFFF + 5 + (70_000 - 0xFFFF) + 4, stream.len());
+        assert_eq!(0, stream[2]); // First block is not final
+        assert_eq!(1, stream[2 + 5 + 0xFFFF]); // Second block is final
+        assert_eq!(&data[..0xFFFF], &stream[7..7 + 0xFFFF]);
+    }
+
+    #[test]
+    fn test_write_png() {
+        let mut out = Vec::new();
+        write_png(&mut out, &FRAMEBUFFER, 3, 2, 1, &Palette::default()).unwrap();
+
+        // Signature and header
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &out[..8]);
+        assert_eq!(b"IHDR", &out[12..16]);
+        assert_eq!(&[0, 0, 0, 3, 0, 0, 0, 2, 8, 3, 0, 0, 0], &out[16..29]);
+
+        // Colour table
+        let plte = 8 + 25;
+        assert_eq!(b"PLTE", &out[plte + 4..plte + 8]);
+        assert_eq!(
+            Palette::default().0.as_flattened(),
+            &out[plte + 8..plte + 20]
+        );
+
+        // Image data: one stored block with two filtered scanlines of colour indices
+        let idat = plte + 24;
+        assert_eq!(b"IDAT", &out[idat + 4..idat + 8]);
+        let scanlines = [0, 1, 0, 0, 0, 0, 3, 0];
+        assert_eq!(&scanlines, &out[idat + 8 + 7..idat + 8 + 7 + 8]);
+
+        // Trailer
+        assert_eq!(b"IEND\xae\x42\x60\x82", &out[out.len() - 8..]);
+    }
+}